@@ -0,0 +1,50 @@
+#!/bin/sh
+# Generates certificates for trying out mutual-TLS client authentication
+# locally, in the directory given as the first argument (default: certs):
+#
+#   ca.pem / ca-key.pem             a CA for the proxy to verify clients against
+#   client.pem / client-key.pem     a CA-issued client certificate, for
+#                                   tls_client_auth with the subject DN
+#                                   CN=test-client,O=oa2p
+#   self-signed.pem / -key.pem      a self-signed certificate, for
+#                                   self_signed_tls_client_auth
+#   self-signed.jwks.json           a JWK Set carrying the self-signed
+#                                   certificate, for `oa2p-admin --jwks`
+#   *.header                        the URL-encoded PEM to send in the
+#                                   configured client_cert_header, e.g.
+#                                   curl -H "X-SSL-Client-Cert: $(cat certs/client.header)"
+set -e
+
+out="${1:-certs}"
+mkdir -p "$out"
+cd "$out"
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 365 \
+    -subj "/O=oa2p/CN=oa2p test CA" -keyout ca-key.pem -out ca.pem
+
+openssl req -newkey rsa:2048 -nodes \
+    -subj "/O=oa2p/CN=test-client" -keyout client-key.pem -out client.csr
+openssl x509 -req -days 365 -in client.csr -CA ca.pem -CAkey ca-key.pem \
+    -CAcreateserial -out client.pem
+rm client.csr
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 365 \
+    -subj "/O=oa2p/CN=self-signed-client" -keyout self-signed-key.pem -out self-signed.pem
+
+# The key parameters are derived from the certificate, so the JWK Set is also
+# usable for private_key_jwt.
+b64url() {
+    openssl base64 -A | tr '+/' '-_' | tr -d '='
+}
+modulus=$(openssl x509 -in self-signed.pem -noout -modulus | cut -d= -f2 | xxd -r -p | b64url)
+x5c=$(openssl x509 -in self-signed.pem -outform DER | openssl base64 -A)
+cat > self-signed.jwks.json <<JWKS
+{"keys":[{"kty":"RSA","use":"sig","kid":"self-signed","n":"$modulus","e":"AQAB","x5c":["$x5c"]}]}
+JWKS
+
+for cert in client self-signed; do
+    python3 -c 'import sys, urllib.parse; print(urllib.parse.quote(sys.stdin.read(), safe=""))' \
+        < "$cert.pem" > "$cert.header"
+done
+
+echo "Certificates written to $out"
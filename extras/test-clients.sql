@@ -1,5 +1,18 @@
-INSERT INTO clients (identifier, secret, response_type) VALUES
-  ('abcd1234', '$2y$05$WV4774ZgHYmyY2gWdVB2MuILGdBrG2HP1c6OvPxuSAlphNU2bQ.au', 'confidential');
+INSERT INTO clients (identifier, secret, response_type, token_endpoint_auth_method) VALUES
+  ('abcd1234', '$2y$05$WV4774ZgHYmyY2gWdVB2MuILGdBrG2HP1c6OvPxuSAlphNU2bQ.au', 'confidential', 'client_secret_basic');
 
-INSERT INTO clients (identifier, secret, response_type) VALUES
-  ('abcd4321', '$2y$05$WV4774ZgHYmyY2gWdVB2MuILGdBrG2HP1c6OvPxuSAlphNU2bQ.au', 'vulnerable');
\ No newline at end of file
+INSERT INTO clients (identifier, secret, response_type, token_endpoint_auth_method) VALUES
+  ('abcd4321', '$2y$05$WV4774ZgHYmyY2gWdVB2MuILGdBrG2HP1c6OvPxuSAlphNU2bQ.au', 'vulnerable', 'none');
+
+INSERT INTO client_grant_types (client_id, grant_id)
+  SELECT c.id, g.id FROM clients c, grant_types g
+  WHERE c.identifier = 'abcd1234'
+    AND g.name IN ('authorization_code', 'client_credentials', 'password', 'refresh_token');
+
+INSERT INTO client_grant_types (client_id, grant_id)
+  SELECT c.id, g.id FROM clients c, grant_types g
+  WHERE c.identifier = 'abcd4321'
+    AND g.name IN ('authorization_code', 'refresh_token');
+
+INSERT INTO users (username, password_hash) VALUES
+  ('test-user', '$2y$05$WV4774ZgHYmyY2gWdVB2MuILGdBrG2HP1c6OvPxuSAlphNU2bQ.au');
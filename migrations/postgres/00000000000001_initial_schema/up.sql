@@ -1,6 +1,10 @@
+-- The schema as it was before migrations were introduced. Everything here is
+-- idempotent, so databases created from the old extras/schema.sql can be
+-- migrated in place.
+
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 
-CREATE TABLE clients (
+CREATE TABLE IF NOT EXISTS clients (
   id SERIAL PRIMARY KEY,
   identifier VARCHAR(256) NOT NULL,
   secret VARCHAR(256) NOT NULL,
@@ -9,14 +13,14 @@ CREATE TABLE clients (
     UNIQUE (identifier)
 );
 
-CREATE TABLE grant_types (
+CREATE TABLE IF NOT EXISTS grant_types (
   id SERIAL PRIMARY KEY,
   name VARCHAR(32) NOT NULL,
   CONSTRAINT grant_types__unique_name
     UNIQUE (name)
 );
 
-CREATE TABLE client_redirect_uris (
+CREATE TABLE IF NOT EXISTS client_redirect_uris (
   id SERIAL PRIMARY KEY,
   client_id INTEGER NOT NULL,
   redirect_uri VARCHAR(128) NOT NULL,
@@ -25,7 +29,7 @@ CREATE TABLE client_redirect_uris (
     REFERENCES clients (id)
 );
 
-CREATE TABLE access_tokens (
+CREATE TABLE IF NOT EXISTS access_tokens (
   id SERIAL PRIMARY KEY,
   token uuid NOT NULL DEFAULT uuid_generate_v4(),
   client_id INTEGER NOT NULL,
@@ -43,7 +47,7 @@ CREATE TABLE access_tokens (
     UNIQUE(token)
 );
 
-CREATE TABLE refresh_tokens (
+CREATE TABLE IF NOT EXISTS refresh_tokens (
   id SERIAL PRIMARY KEY,
   token uuid NOT NULL DEFAULT uuid_generate_v4(),
   client_id INTEGER NOT NULL,
@@ -57,7 +61,7 @@ CREATE TABLE refresh_tokens (
     UNIQUE(token)
 );
 
-CREATE TABLE auth_codes (
+CREATE TABLE IF NOT EXISTS auth_codes (
   id SERIAL PRIMARY KEY,
   client_id INTEGER NOT NULL,
   name VARCHAR(64) NOT NULL,
@@ -75,4 +79,5 @@ INSERT INTO grant_types (name) VALUES
   ('token'),
   ('password'),
   ('client_credentials'),
-  ('refresh_token');
+  ('refresh_token')
+ON CONFLICT (name) DO NOTHING;
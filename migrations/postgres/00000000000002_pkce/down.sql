@@ -0,0 +1,6 @@
+ALTER TABLE auth_codes
+  DROP COLUMN code_challenge_method,
+  DROP COLUMN code_challenge;
+
+ALTER TABLE clients
+  DROP COLUMN require_pkce;
@@ -0,0 +1,34 @@
+-- Checking for the columns first keeps this safe on databases created from a
+-- copy of extras/schema.sql that already had them. ADD COLUMN IF NOT EXISTS
+-- would do the same, but needs PostgreSQL 9.6.
+
+DO $$
+BEGIN
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'clients' AND column_name = 'require_pkce'
+  ) THEN
+    ALTER TABLE clients
+      ADD COLUMN require_pkce BOOLEAN NOT NULL DEFAULT FALSE;
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'auth_codes' AND column_name = 'code_challenge'
+  ) THEN
+    ALTER TABLE auth_codes
+      ADD COLUMN code_challenge VARCHAR(128);
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'auth_codes' AND column_name = 'code_challenge_method'
+  ) THEN
+    ALTER TABLE auth_codes
+      ADD COLUMN code_challenge_method VARCHAR(8);
+  END IF;
+END
+$$;
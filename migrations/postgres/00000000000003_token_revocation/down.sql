@@ -0,0 +1,7 @@
+ALTER TABLE access_tokens
+  DROP CONSTRAINT access_tokens__refresh_token_id,
+  DROP COLUMN revoked_at,
+  DROP COLUMN refresh_token_id;
+
+ALTER TABLE refresh_tokens
+  DROP COLUMN revoked_at;
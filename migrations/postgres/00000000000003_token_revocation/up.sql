@@ -0,0 +1,43 @@
+-- Checking for the columns and the constraint first keeps this safe on
+-- databases created from a copy of extras/schema.sql that already had them.
+-- ADD COLUMN IF NOT EXISTS would do the same, but needs PostgreSQL 9.6.
+
+DO $$
+BEGIN
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'refresh_tokens' AND column_name = 'revoked_at'
+  ) THEN
+    ALTER TABLE refresh_tokens
+      ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE;
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'access_tokens' AND column_name = 'refresh_token_id'
+  ) THEN
+    ALTER TABLE access_tokens
+      ADD COLUMN refresh_token_id INTEGER;
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+      AND table_name = 'access_tokens' AND column_name = 'revoked_at'
+  ) THEN
+    ALTER TABLE access_tokens
+      ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE;
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM pg_constraint WHERE conname = 'access_tokens__refresh_token_id'
+  ) THEN
+    ALTER TABLE access_tokens
+      ADD CONSTRAINT access_tokens__refresh_token_id
+        FOREIGN KEY (refresh_token_id)
+        REFERENCES refresh_tokens (id);
+  END IF;
+END
+$$;
@@ -0,0 +1,6 @@
+DROP TABLE client_grant_types;
+
+ALTER TABLE clients
+  DROP COLUMN token_endpoint_auth_method,
+  DROP COLUMN scope,
+  DROP COLUMN client_name;
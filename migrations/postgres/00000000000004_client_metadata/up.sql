@@ -0,0 +1,18 @@
+ALTER TABLE clients
+  ADD COLUMN client_name VARCHAR(255),
+  ADD COLUMN scope VARCHAR(255),
+  ADD COLUMN token_endpoint_auth_method VARCHAR(32) NOT NULL DEFAULT 'client_secret_basic';
+
+CREATE TABLE client_grant_types (
+  id SERIAL PRIMARY KEY,
+  client_id INTEGER NOT NULL,
+  grant_id INTEGER NOT NULL,
+  CONSTRAINT client_grant_types__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT client_grant_types__grant_id
+    FOREIGN KEY (grant_id)
+    REFERENCES grant_types (id),
+  CONSTRAINT client_grant_types__unique_grant
+    UNIQUE (client_id, grant_id)
+);
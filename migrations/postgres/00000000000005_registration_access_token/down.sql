@@ -0,0 +1,2 @@
+ALTER TABLE clients
+  DROP COLUMN registration_access_token;
@@ -0,0 +1,5 @@
+DROP TABLE client_assertions;
+
+ALTER TABLE clients
+  DROP COLUMN jwt_secret,
+  DROP COLUMN jwks;
@@ -0,0 +1,20 @@
+-- Keys for the private_key_jwt and client_secret_jwt authentication methods.
+-- The JWK Set is stored as JSON. The shared secret has to be usable to verify
+-- signatures, so unlike clients.secret it is not hashed.
+ALTER TABLE clients
+  ADD COLUMN jwks TEXT,
+  ADD COLUMN jwt_secret VARCHAR(256);
+
+-- The assertions clients authenticated with, kept until they expire so they
+-- cannot be replayed.
+CREATE TABLE client_assertions (
+  id SERIAL PRIMARY KEY,
+  client_id INTEGER NOT NULL,
+  jti VARCHAR(256) NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  CONSTRAINT client_assertions__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT client_assertions__unique_jti
+    UNIQUE (client_id, jti)
+);
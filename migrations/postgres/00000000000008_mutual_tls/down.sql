@@ -0,0 +1,5 @@
+ALTER TABLE access_tokens
+  DROP COLUMN cert_thumbprint;
+
+ALTER TABLE clients
+  DROP COLUMN tls_client_auth_subject_dn;
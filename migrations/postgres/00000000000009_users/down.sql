@@ -0,0 +1,7 @@
+ALTER TABLE refresh_tokens
+  DROP COLUMN user_id;
+
+ALTER TABLE access_tokens
+  DROP COLUMN user_id;
+
+DROP TABLE users;
@@ -0,0 +1,21 @@
+-- Resource owners, who authenticate with the password grant.
+CREATE TABLE users (
+  id SERIAL PRIMARY KEY,
+  username VARCHAR(256) NOT NULL,
+  password_hash VARCHAR(256) NOT NULL,
+  CONSTRAINT users__unique_username
+    UNIQUE (username)
+);
+
+-- The resource owner a token was issued on behalf of, if any.
+ALTER TABLE access_tokens
+  ADD COLUMN user_id INTEGER,
+  ADD CONSTRAINT access_tokens__user_id
+    FOREIGN KEY (user_id)
+    REFERENCES users (id);
+
+ALTER TABLE refresh_tokens
+  ADD COLUMN user_id INTEGER,
+  ADD CONSTRAINT refresh_tokens__user_id
+    FOREIGN KEY (user_id)
+    REFERENCES users (id);
@@ -0,0 +1,3 @@
+ALTER TABLE refresh_tokens
+  DROP COLUMN rotated_at,
+  DROP COLUMN family_id;
@@ -0,0 +1,8 @@
+-- Rotated refresh tokens point at the first token of their family, and are
+-- kept around once replaced, so that presenting them again can be detected.
+ALTER TABLE refresh_tokens
+  ADD COLUMN family_id INTEGER,
+  ADD COLUMN rotated_at TIMESTAMP WITH TIME ZONE,
+  ADD CONSTRAINT refresh_tokens__family_id
+    FOREIGN KEY (family_id)
+    REFERENCES refresh_tokens (id);
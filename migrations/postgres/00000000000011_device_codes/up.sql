@@ -0,0 +1,33 @@
+-- Grant type URNs don't fit the original column size.
+ALTER TABLE grant_types
+  ALTER COLUMN name TYPE VARCHAR(64);
+
+INSERT INTO grant_types (name) VALUES
+  ('urn:ietf:params:oauth:grant-type:device_code')
+ON CONFLICT (name) DO NOTHING;
+
+-- Pending device authorizations (RFC 8628). The device polls with the
+-- device_code, while the resource owner enters the user_code on the
+-- verification page, which records their decision in status.
+CREATE TABLE device_codes (
+  id SERIAL PRIMARY KEY,
+  client_id INTEGER NOT NULL,
+  device_code VARCHAR(64) NOT NULL,
+  user_code VARCHAR(16) NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  poll_interval INTEGER NOT NULL,
+  last_polled_at TIMESTAMP WITH TIME ZONE,
+  user_id INTEGER,
+  status VARCHAR(16) NOT NULL DEFAULT 'pending',
+  CONSTRAINT device_codes__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT device_codes__user_id
+    FOREIGN KEY (user_id)
+    REFERENCES users (id),
+  CONSTRAINT device_codes__unique_device_code
+    UNIQUE (device_code),
+  CONSTRAINT device_codes__unique_user_code
+    UNIQUE (user_code)
+);
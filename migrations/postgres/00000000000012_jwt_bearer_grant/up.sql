@@ -0,0 +1,3 @@
+INSERT INTO grant_types (name) VALUES
+  ('urn:ietf:params:oauth:grant-type:jwt-bearer')
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,17 @@
+ALTER TABLE access_tokens
+  DROP COLUMN audience,
+  DROP COLUMN act;
+
+-- The clients given the grant, and the tokens issued with it, refer to it.
+DELETE FROM client_grant_types
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange'
+  );
+DELETE FROM access_tokens
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange'
+  );
+DELETE FROM grant_types
+  WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange';
@@ -0,0 +1,9 @@
+INSERT INTO grant_types (name) VALUES
+  ('urn:ietf:params:oauth:grant-type:token-exchange')
+ON CONFLICT (name) DO NOTHING;
+
+-- The services an exchanged access token is meant for, space delimited, and
+-- the JSON act claim naming the parties acting on behalf of its subject.
+ALTER TABLE access_tokens
+  ADD COLUMN audience VARCHAR(1024),
+  ADD COLUMN act TEXT;
@@ -0,0 +1,3 @@
+-- The grant types given to existing clients cannot be told apart from the
+-- ones they registered, so they stay behind.
+SELECT 1;
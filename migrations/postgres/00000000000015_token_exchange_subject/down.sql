@@ -0,0 +1,2 @@
+ALTER TABLE access_tokens
+  DROP COLUMN subject_token_id;
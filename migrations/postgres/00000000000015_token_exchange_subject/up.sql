@@ -0,0 +1,4 @@
+-- The access token an exchanged access token was issued for, so that revoking
+-- the one revokes the other.
+ALTER TABLE access_tokens
+  ADD COLUMN subject_token_id INTEGER REFERENCES access_tokens (id) ON DELETE SET NULL;
@@ -0,0 +1 @@
+DROP TABLE bearer_assertions;
@@ -0,0 +1,6 @@
+DROP TABLE auth_codes;
+DROP TABLE refresh_tokens;
+DROP TABLE access_tokens;
+DROP TABLE client_redirect_uris;
+DROP TABLE grant_types;
+DROP TABLE clients;
@@ -0,0 +1,89 @@
+-- UUIDs are generated by the provider, and stored in their hyphenated text
+-- form. Everything here is idempotent, so databases created from the old
+-- extras/schema.sqlite.sql can be migrated in place.
+
+CREATE TABLE IF NOT EXISTS clients (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  identifier VARCHAR(256) NOT NULL,
+  secret VARCHAR(256) NOT NULL,
+  response_type VARCHAR(64) NOT NULL,
+  require_pkce BOOLEAN NOT NULL DEFAULT FALSE,
+  CONSTRAINT clients__unique_identifier
+    UNIQUE (identifier)
+);
+
+CREATE TABLE IF NOT EXISTS grant_types (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name VARCHAR(32) NOT NULL,
+  CONSTRAINT grant_types__unique_name
+    UNIQUE (name)
+);
+
+CREATE TABLE IF NOT EXISTS client_redirect_uris (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  client_id INTEGER NOT NULL,
+  redirect_uri VARCHAR(128) NOT NULL,
+  CONSTRAINT client_redirect_uris__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id)
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP,
+  revoked_at TIMESTAMP,
+  CONSTRAINT refresh_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT refresh_tokens__token
+    UNIQUE(token)
+);
+
+CREATE TABLE IF NOT EXISTS access_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  grant_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  refresh_token_id INTEGER,
+  revoked_at TIMESTAMP,
+  CONSTRAINT access_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT access_tokens__grant_id
+    FOREIGN KEY (grant_id)
+    REFERENCES grant_types (id),
+  CONSTRAINT access_tokens__refresh_token_id
+    FOREIGN KEY (refresh_token_id)
+    REFERENCES refresh_tokens (id),
+  CONSTRAINT access_tokens__unique_token
+    UNIQUE(token)
+);
+
+CREATE TABLE IF NOT EXISTS auth_codes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  client_id INTEGER NOT NULL,
+  name VARCHAR(64) NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  redirect_uri VARCHAR(128) NOT NULL,
+  user_id INTEGER,
+  code_challenge VARCHAR(128),
+  code_challenge_method VARCHAR(8),
+  CONSTRAINT auth_codes__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id)
+);
+
+INSERT OR IGNORE INTO grant_types (name) VALUES
+  ('authorization_code'),
+  ('token'),
+  ('password'),
+  ('client_credentials'),
+  ('refresh_token');
@@ -0,0 +1,17 @@
+-- SQLite cannot drop columns, so the table is rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+DROP TABLE client_grant_types;
+
+CREATE TABLE clients_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  identifier VARCHAR(256) NOT NULL,
+  secret VARCHAR(256) NOT NULL,
+  response_type VARCHAR(64) NOT NULL,
+  require_pkce BOOLEAN NOT NULL DEFAULT FALSE,
+  CONSTRAINT clients__unique_identifier
+    UNIQUE (identifier)
+);
+INSERT INTO clients_new (id, identifier, secret, response_type, require_pkce)
+  SELECT id, identifier, secret, response_type, require_pkce FROM clients;
+DROP TABLE clients;
+ALTER TABLE clients_new RENAME TO clients;
@@ -0,0 +1,3 @@
+-- The bcrypt hash of the token clients manage their registration with. Clients
+-- created by other means have none, and cannot be managed through the API.
+ALTER TABLE clients ADD COLUMN registration_access_token VARCHAR(256);
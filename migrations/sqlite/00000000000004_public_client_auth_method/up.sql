@@ -0,0 +1,6 @@
+-- Public clients used to be let through without credentials based on their
+-- response_type alone. Each client now declares how it authenticates, so
+-- existing public clients carry on as they were.
+UPDATE clients
+  SET token_endpoint_auth_method = 'none'
+  WHERE response_type <> 'confidential';
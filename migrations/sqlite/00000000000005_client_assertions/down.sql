@@ -0,0 +1,21 @@
+-- SQLite cannot drop columns, so the table is rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+DROP TABLE client_assertions;
+
+CREATE TABLE clients_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  identifier VARCHAR(256) NOT NULL,
+  secret VARCHAR(256) NOT NULL,
+  response_type VARCHAR(64) NOT NULL,
+  require_pkce BOOLEAN NOT NULL DEFAULT FALSE,
+  client_name VARCHAR(255),
+  scope VARCHAR(255),
+  token_endpoint_auth_method VARCHAR(32) NOT NULL DEFAULT 'client_secret_basic',
+  registration_access_token VARCHAR(256),
+  CONSTRAINT clients__unique_identifier
+    UNIQUE (identifier)
+);
+INSERT INTO clients_new (id, identifier, secret, response_type, require_pkce, client_name, scope, token_endpoint_auth_method, registration_access_token)
+  SELECT id, identifier, secret, response_type, require_pkce, client_name, scope, token_endpoint_auth_method, registration_access_token FROM clients;
+DROP TABLE clients;
+ALTER TABLE clients_new RENAME TO clients;
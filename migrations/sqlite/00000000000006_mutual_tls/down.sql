@@ -0,0 +1,48 @@
+-- SQLite cannot drop columns, so the tables are rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+CREATE TABLE clients_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  identifier VARCHAR(256) NOT NULL,
+  secret VARCHAR(256) NOT NULL,
+  response_type VARCHAR(64) NOT NULL,
+  require_pkce BOOLEAN NOT NULL DEFAULT FALSE,
+  client_name VARCHAR(255),
+  scope VARCHAR(255),
+  token_endpoint_auth_method VARCHAR(32) NOT NULL DEFAULT 'client_secret_basic',
+  registration_access_token VARCHAR(256),
+  jwks TEXT,
+  jwt_secret VARCHAR(256),
+  CONSTRAINT clients__unique_identifier
+    UNIQUE (identifier)
+);
+INSERT INTO clients_new (id, identifier, secret, response_type, require_pkce, client_name, scope, token_endpoint_auth_method, registration_access_token, jwks, jwt_secret)
+  SELECT id, identifier, secret, response_type, require_pkce, client_name, scope, token_endpoint_auth_method, registration_access_token, jwks, jwt_secret FROM clients;
+DROP TABLE clients;
+ALTER TABLE clients_new RENAME TO clients;
+
+CREATE TABLE access_tokens_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  grant_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  refresh_token_id INTEGER,
+  revoked_at TIMESTAMP,
+  CONSTRAINT access_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT access_tokens__grant_id
+    FOREIGN KEY (grant_id)
+    REFERENCES grant_types (id),
+  CONSTRAINT access_tokens__refresh_token_id
+    FOREIGN KEY (refresh_token_id)
+    REFERENCES refresh_tokens (id),
+  CONSTRAINT access_tokens__unique_token
+    UNIQUE(token)
+);
+INSERT INTO access_tokens_new (id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at)
+  SELECT id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at FROM access_tokens;
+DROP TABLE access_tokens;
+ALTER TABLE access_tokens_new RENAME TO access_tokens;
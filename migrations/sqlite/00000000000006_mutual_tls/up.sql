@@ -0,0 +1,5 @@
+-- The certificate subject DN tls_client_auth clients are identified by.
+ALTER TABLE clients ADD COLUMN tls_client_auth_subject_dn VARCHAR(512);
+
+-- The thumbprint of the client certificate an access token is bound to.
+ALTER TABLE access_tokens ADD COLUMN cert_thumbprint VARCHAR(64);
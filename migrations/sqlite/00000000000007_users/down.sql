@@ -0,0 +1,50 @@
+-- SQLite cannot drop columns, so the tables are rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+CREATE TABLE access_tokens_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  grant_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  refresh_token_id INTEGER,
+  revoked_at TIMESTAMP,
+  cert_thumbprint VARCHAR(64),
+  CONSTRAINT access_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT access_tokens__grant_id
+    FOREIGN KEY (grant_id)
+    REFERENCES grant_types (id),
+  CONSTRAINT access_tokens__refresh_token_id
+    FOREIGN KEY (refresh_token_id)
+    REFERENCES refresh_tokens (id),
+  CONSTRAINT access_tokens__unique_token
+    UNIQUE(token)
+);
+INSERT INTO access_tokens_new (id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at, cert_thumbprint)
+  SELECT id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at, cert_thumbprint FROM access_tokens;
+DROP TABLE access_tokens;
+ALTER TABLE access_tokens_new RENAME TO access_tokens;
+
+CREATE TABLE refresh_tokens_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP,
+  revoked_at TIMESTAMP,
+  CONSTRAINT refresh_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT refresh_tokens__token
+    UNIQUE(token)
+);
+INSERT INTO refresh_tokens_new (id, token, client_id, scope, issued_at, expires_at, revoked_at)
+  SELECT id, token, client_id, scope, issued_at, expires_at, revoked_at FROM refresh_tokens;
+DROP TABLE refresh_tokens;
+ALTER TABLE refresh_tokens_new RENAME TO refresh_tokens;
+
+DROP TABLE users;
@@ -0,0 +1,13 @@
+-- Resource owners, who authenticate with the password grant.
+CREATE TABLE users (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username VARCHAR(256) NOT NULL,
+  password_hash VARCHAR(256) NOT NULL,
+  CONSTRAINT users__unique_username
+    UNIQUE (username)
+);
+
+-- The resource owner a token was issued on behalf of, if any.
+ALTER TABLE access_tokens ADD COLUMN user_id INTEGER REFERENCES users (id);
+
+ALTER TABLE refresh_tokens ADD COLUMN user_id INTEGER REFERENCES users (id);
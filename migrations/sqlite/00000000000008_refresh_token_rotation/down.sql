@@ -0,0 +1,21 @@
+-- SQLite cannot drop columns, so the table is rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+CREATE TABLE refresh_tokens_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP,
+  revoked_at TIMESTAMP,
+  user_id INTEGER REFERENCES users (id),
+  CONSTRAINT refresh_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT refresh_tokens__token
+    UNIQUE(token)
+);
+INSERT INTO refresh_tokens_new (id, token, client_id, scope, issued_at, expires_at, revoked_at, user_id)
+  SELECT id, token, client_id, scope, issued_at, expires_at, revoked_at, user_id FROM refresh_tokens;
+DROP TABLE refresh_tokens;
+ALTER TABLE refresh_tokens_new RENAME TO refresh_tokens;
@@ -0,0 +1,5 @@
+-- Rotated refresh tokens point at the first token of their family, and are
+-- kept around once replaced, so that presenting them again can be detected.
+ALTER TABLE refresh_tokens ADD COLUMN family_id INTEGER REFERENCES refresh_tokens (id);
+
+ALTER TABLE refresh_tokens ADD COLUMN rotated_at TIMESTAMP;
@@ -0,0 +1,15 @@
+DROP TABLE device_codes;
+
+-- The clients given the grant, and the tokens issued with it, refer to it.
+DELETE FROM client_grant_types
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:device_code'
+  );
+DELETE FROM access_tokens
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:device_code'
+  );
+DELETE FROM grant_types
+  WHERE name = 'urn:ietf:params:oauth:grant-type:device_code';
@@ -0,0 +1,13 @@
+-- The clients given the grant, and the tokens issued with it, refer to it.
+DELETE FROM client_grant_types
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:jwt-bearer'
+  );
+DELETE FROM access_tokens
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:jwt-bearer'
+  );
+DELETE FROM grant_types
+  WHERE name = 'urn:ietf:params:oauth:grant-type:jwt-bearer';
@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO grant_types (name) VALUES
+  ('urn:ietf:params:oauth:grant-type:jwt-bearer');
@@ -0,0 +1,44 @@
+-- SQLite cannot drop columns, so the table is rebuilt without them. Foreign
+-- keys are not enforced while that happens.
+CREATE TABLE access_tokens_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token CHAR(36) NOT NULL,
+  client_id INTEGER NOT NULL,
+  grant_id INTEGER NOT NULL,
+  scope VARCHAR(255) NOT NULL,
+  issued_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  refresh_token_id INTEGER,
+  revoked_at TIMESTAMP,
+  cert_thumbprint VARCHAR(64),
+  user_id INTEGER REFERENCES users (id),
+  CONSTRAINT access_tokens__client_id
+    FOREIGN KEY (client_id)
+    REFERENCES clients (id),
+  CONSTRAINT access_tokens__grant_id
+    FOREIGN KEY (grant_id)
+    REFERENCES grant_types (id),
+  CONSTRAINT access_tokens__refresh_token_id
+    FOREIGN KEY (refresh_token_id)
+    REFERENCES refresh_tokens (id),
+  CONSTRAINT access_tokens__unique_token
+    UNIQUE(token)
+);
+INSERT INTO access_tokens_new (id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at, cert_thumbprint, user_id)
+  SELECT id, token, client_id, grant_id, scope, issued_at, expires_at, refresh_token_id, revoked_at, cert_thumbprint, user_id FROM access_tokens;
+DROP TABLE access_tokens;
+ALTER TABLE access_tokens_new RENAME TO access_tokens;
+
+-- The clients given the grant, and the tokens issued with it, refer to it.
+DELETE FROM client_grant_types
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange'
+  );
+DELETE FROM access_tokens
+  WHERE grant_id IN (
+    SELECT id FROM grant_types
+    WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange'
+  );
+DELETE FROM grant_types
+  WHERE name = 'urn:ietf:params:oauth:grant-type:token-exchange';
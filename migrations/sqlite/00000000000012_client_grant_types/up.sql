@@ -0,0 +1,9 @@
+-- Clients may now only use the grant types they registered. Clients that
+-- registered none could use every grant so far, and keep doing so. Clients
+-- that did register grant types only get to refresh tokens if they registered
+-- refresh_token too.
+INSERT OR IGNORE INTO client_grant_types (client_id, grant_id)
+  SELECT c.id, g.id FROM clients c CROSS JOIN grant_types g
+  WHERE NOT EXISTS (
+    SELECT 1 FROM client_grant_types cg WHERE cg.client_id = c.id
+  );
@@ -0,0 +1,11 @@
+-- The assertions exchanged through the JWT bearer grant, kept until they
+-- expire so they cannot be replayed. A jti is only unique to its issuer, and
+-- an assertion may be presented by any client, so they are keyed by issuer.
+CREATE TABLE bearer_assertions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  issuer VARCHAR(256) NOT NULL,
+  jti VARCHAR(256) NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  CONSTRAINT bearer_assertions__unique_jti
+    UNIQUE (issuer, jti)
+);
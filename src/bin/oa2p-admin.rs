@@ -0,0 +1,601 @@
+//! oa2p-admin manages the clients and users of a provider, against the
+//! database configured in config.toml. Secrets are generated and hashed here,
+//! and the plaintext is printed exactly once, when it is created or reset.
+//! User passwords are read from stdin, and never printed.
+
+extern crate clap;
+extern crate oa2p;
+extern crate serde_json;
+extern crate uuid;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use oa2p::STORE;
+use oa2p::models::db::{Client, GrantType, NewClientBuilder, NewClientRedirectUriBuilder,
+                       NewUserBuilder, User};
+use oa2p::store::Store;
+use oa2p::utils;
+use oa2p::utils::client_auth;
+use oa2p::utils::grant;
+use oa2p::utils::jwk::JwkSet;
+use oa2p::utils::registration;
+use std::fs::File;
+use std::io;
+use std::process;
+use uuid::Uuid;
+
+/// The grant types clients are created with, unless others are given.
+const DEFAULT_GRANT_TYPES: &[&str] = &["authorization_code", "refresh_token"];
+
+/// Prints an error and exits with a non-zero status.
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    process::exit(1);
+}
+
+fn find_client(store: &dyn Store, identifier: &str) -> Client {
+    match store.find_client(identifier) {
+        Ok(Some(client)) => client,
+        Ok(None) => fail(&format!("no client with identifier [{}]", identifier)),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+fn find_user(store: &dyn Store, username: &str) -> User {
+    match store.user_store().find_user(username) {
+        Ok(Some(user)) => user,
+        Ok(None) => fail(&format!("no user with username [{}]", username)),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+/// Reads a password from the first line of stdin, so that it stays out of the
+/// shell history, and returns its hash.
+fn read_password() -> String {
+    let mut password = String::new();
+    io::stdin()
+        .read_line(&mut password)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    let password = password.trim_right_matches(|c| c == '\n' || c == '\r');
+    if password.is_empty() {
+        fail("the password must not be empty");
+    }
+
+    utils::hash_password(password).unwrap_or_else(|e| fail(&e.to_string()))
+}
+
+fn check_redirect_uri(uri: &str) {
+    if !registration::check_redirect_uri_syntax(uri) {
+        fail(&format!(
+            "redirect URI [{}] must be absolute, and must not include a fragment",
+            uri
+        ));
+    }
+}
+
+/// Looks up the grant types named with `--grant-type`, if any were.
+fn read_grant_types(store: &dyn Store, args: &ArgMatches) -> Option<Vec<GrantType>> {
+    let names: Vec<String> = args.values_of("grant_type")?
+        .map(|n| n.to_owned())
+        .collect();
+    Some(find_grant_types(store, &names))
+}
+
+fn find_grant_types(store: &dyn Store, names: &[String]) -> Vec<GrantType> {
+    grant::find_grant_types(store, names).unwrap_or_else(|name| {
+        fail(&format!("[{}] is not a grant type the provider accepts", name))
+    })
+}
+
+fn new_secret() -> (String, String) {
+    let secret = utils::generate_client_secret();
+    let hash = utils::hash_client_secret(&secret).unwrap_or_else(|e| fail(&e.to_string()));
+    (secret, hash)
+}
+
+/// Reads a JWK Set from a file, making sure its signing keys are usable, and
+/// returns it in the form it is stored in.
+fn read_jwks(path: &str) -> String {
+    let file = File::open(path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    let jwks: JwkSet =
+        serde_json::from_reader(file).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    for jwk in &jwks.keys {
+        if jwk.use_.as_ref().map_or(true, |u| u == "sig") {
+            if let Err(e) = jwk.to_verifying_key() {
+                fail(&format!("{}: key {:?} is not usable: {}", path, jwk.kid, e));
+            }
+        }
+    }
+
+    serde_json::to_string(&jwks).unwrap_or_else(|e| fail(&e.to_string()))
+}
+
+/// `client_secret_jwt` clients sign their assertions with their secret, so it
+/// has to be kept in a form signatures can be checked with.
+fn jwt_secret(auth_method: &str, secret: &str) -> Option<String> {
+    if auth_method == "client_secret_jwt" {
+        Some(secret.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Certificate clients need what identifies their certificate: a subject DN
+/// for `tls_client_auth`, and the certificates themselves, as `x5c` entries of
+/// their JWK Set, for `self_signed_tls_client_auth`.
+fn check_certificate(auth_method: &str, subject_dn: Option<&String>, jwks: Option<&String>) {
+    match auth_method {
+        "tls_client_auth" if subject_dn.is_none() => {
+            fail("tls_client_auth clients need --tls-subject-dn")
+        }
+        "self_signed_tls_client_auth" => {
+            let certificates = jwks
+                .and_then(|jwks| serde_json::from_str::<JwkSet>(jwks).ok())
+                .map_or(0, |jwks| jwks.certificates().len());
+            if certificates == 0 {
+                fail("self_signed_tls_client_auth clients need --jwks with x5c certificates");
+            }
+        }
+        _ => {}
+    }
+}
+
+fn print_client(store: &dyn Store, client: &Client) {
+    println!("identifier:    {}", client.identifier);
+    println!("response_type: {}", client.response_type);
+    println!("require_pkce:  {}", client.require_pkce);
+    println!(
+        "client_name:   {}",
+        client.client_name.as_ref().map(|n| n.as_str()).unwrap_or("")
+    );
+    println!(
+        "scope:         {}",
+        client.scope.as_ref().map(|s| s.as_str()).unwrap_or("")
+    );
+    println!("auth_method:   {}", client.token_endpoint_auth_method);
+    let jwks = client
+        .jwks
+        .as_ref()
+        .and_then(|jwks| serde_json::from_str::<JwkSet>(jwks).ok());
+    println!(
+        "jwks:          {}",
+        jwks.map(|jwks| format!("{} key(s)", jwks.keys.len()))
+            .unwrap_or_default()
+    );
+    println!(
+        "tls_subject_dn: {}",
+        client
+            .tls_client_auth_subject_dn
+            .as_ref()
+            .map(|dn| dn.as_str())
+            .unwrap_or("")
+    );
+
+    let grant_types = store
+        .client_grant_types(client)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    println!("grant_types:");
+    for grant_type in grant_types {
+        println!("  {}", grant_type.name);
+    }
+
+    let uris = store
+        .redirect_uris(client)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    println!("redirect_uris:");
+    for uri in uris {
+        println!("  {}", uri.redirect_uri);
+    }
+}
+
+fn list(store: &dyn Store) {
+    let clients = store.clients().unwrap_or_else(|e| fail(&e.to_string()));
+    for client in clients {
+        println!(
+            "{}\t{}\trequire_pkce={}",
+            client.identifier, client.response_type, client.require_pkce
+        );
+    }
+}
+
+fn show(store: &dyn Store, args: &ArgMatches) {
+    let client = find_client(store, args.value_of("identifier").unwrap());
+    print_client(store, &client);
+}
+
+fn create(store: &dyn Store, args: &ArgMatches) {
+    let identifier = args.value_of("identifier")
+        .map(|i| i.to_owned())
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    let redirect_uris: Vec<&str> = args.values_of("redirect_uri")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    for uri in &redirect_uris {
+        check_redirect_uri(uri);
+    }
+
+    // Public clients have nothing to authenticate with by default
+    let response_type = args.value_of("type").unwrap();
+    let default_auth_method = if response_type == "confidential" {
+        client_auth::DEFAULT_AUTH_METHOD
+    } else {
+        "none"
+    };
+    let auth_method = args.value_of("auth_method").unwrap_or(default_auth_method);
+    let jwks = args.value_of("jwks").map(read_jwks);
+    if auth_method == "private_key_jwt" && jwks.is_none() {
+        fail("private_key_jwt clients need --jwks");
+    }
+    let subject_dn = args.value_of("tls_subject_dn").map(|dn| dn.to_owned());
+    check_certificate(auth_method, subject_dn.as_ref(), jwks.as_ref());
+    let grant_types = read_grant_types(store, args).unwrap_or_else(|| {
+        let names: Vec<String> = DEFAULT_GRANT_TYPES.iter().map(|n| (*n).to_owned()).collect();
+        find_grant_types(store, &names)
+    });
+
+    let (secret, hash) = new_secret();
+    let new_client = NewClientBuilder::default()
+        .identifier(identifier)
+        .secret(hash)
+        .response_type(response_type)
+        .require_pkce(args.is_present("require_pkce"))
+        .client_name(args.value_of("name").map(|n| n.to_owned()))
+        .scope(None::<String>)
+        .token_endpoint_auth_method(auth_method)
+        .registration_access_token(None::<String>)
+        .jwks(jwks)
+        .jwt_secret(jwt_secret(auth_method, &secret))
+        .tls_client_auth_subject_dn(subject_dn)
+        .build()
+        .unwrap_or_else(|e| fail(&e));
+    let client = store
+        .insert_client(&new_client)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+
+    for uri in redirect_uris {
+        add_redirect_uri_to(store, &client, uri);
+    }
+    store
+        .set_client_grant_types(&client, &grant_types)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+
+    print_client(store, &client);
+    println!("secret:        {}", secret);
+    println!();
+    println!("The secret is not stored, and cannot be shown again.");
+}
+
+fn update(store: &dyn Store, args: &ArgMatches) {
+    let mut client = find_client(store, args.value_of("identifier").unwrap());
+
+    if let Some(response_type) = args.value_of("type") {
+        client.response_type = response_type.to_owned();
+    }
+    if let Some(auth_method) = args.value_of("auth_method") {
+        client.token_endpoint_auth_method = auth_method.to_owned();
+    }
+    if let Some(path) = args.value_of("jwks") {
+        client.jwks = Some(read_jwks(path));
+    }
+    if let Some(subject_dn) = args.value_of("tls_subject_dn") {
+        client.tls_client_auth_subject_dn = Some(subject_dn.to_owned());
+    }
+    if let Some(require_pkce) = args.value_of("require_pkce") {
+        client.require_pkce = require_pkce == "true";
+    }
+    let secret = if args.is_present("reset_secret") {
+        let (secret, hash) = new_secret();
+        client.secret = hash;
+        client.jwt_secret = jwt_secret(&client.token_endpoint_auth_method, &secret);
+        Some(secret)
+    } else {
+        None
+    };
+
+    // The plaintext secret is only available when it is reset
+    if client.token_endpoint_auth_method != "client_secret_jwt" {
+        client.jwt_secret = None;
+    } else if client.jwt_secret.is_none() {
+        fail("switching to client_secret_jwt needs --reset-secret");
+    }
+    if client.token_endpoint_auth_method == "private_key_jwt" && client.jwks.is_none() {
+        fail("private_key_jwt clients need --jwks");
+    }
+    check_certificate(
+        &client.token_endpoint_auth_method,
+        client.tls_client_auth_subject_dn.as_ref(),
+        client.jwks.as_ref(),
+    );
+    let grant_types = read_grant_types(store, args);
+
+    let client = store
+        .update_client(&client)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    if let Some(grant_types) = grant_types {
+        store
+            .set_client_grant_types(&client, &grant_types)
+            .unwrap_or_else(|e| fail(&e.to_string()));
+    }
+
+    print_client(store, &client);
+    if let Some(secret) = secret {
+        println!("secret:        {}", secret);
+        println!();
+        println!("The secret is not stored, and cannot be shown again.");
+    }
+}
+
+fn delete(store: &dyn Store, args: &ArgMatches) {
+    let client = find_client(store, args.value_of("identifier").unwrap());
+    match store.delete_client(&client) {
+        Ok(true) => println!("Deleted client [{}].", client.identifier),
+        Ok(false) => fail(&format!("client [{}] was already deleted", client.identifier)),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+fn add_redirect_uri_to(store: &dyn Store, client: &Client, uri: &str) {
+    if store
+        .has_redirect_uri(client, uri)
+        .unwrap_or_else(|e| fail(&e.to_string()))
+    {
+        return;
+    }
+
+    let new_uri = NewClientRedirectUriBuilder::default()
+        .client_id(client.id)
+        .redirect_uri(uri)
+        .build()
+        .unwrap_or_else(|e| fail(&e));
+    store
+        .insert_redirect_uri(&new_uri)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+}
+
+fn add_redirect_uri(store: &dyn Store, args: &ArgMatches) {
+    let client = find_client(store, args.value_of("identifier").unwrap());
+    let uri = args.value_of("redirect_uri").unwrap();
+    check_redirect_uri(uri);
+
+    add_redirect_uri_to(store, &client, uri);
+    print_client(store, &client);
+}
+
+fn remove_redirect_uri(store: &dyn Store, args: &ArgMatches) {
+    let client = find_client(store, args.value_of("identifier").unwrap());
+    let uri = args.value_of("redirect_uri").unwrap();
+
+    match store.delete_redirect_uri(&client, uri) {
+        Ok(true) => print_client(store, &client),
+        Ok(false) => fail(&format!("[{}] is not registered for the client", uri)),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+fn list_users(store: &dyn Store) {
+    let users = store
+        .user_store()
+        .users()
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    for user in users {
+        println!("{}", user.username);
+    }
+}
+
+fn create_user(store: &dyn Store, args: &ArgMatches) {
+    let new_user = NewUserBuilder::default()
+        .username(args.value_of("username").unwrap())
+        .password_hash(read_password())
+        .build()
+        .unwrap_or_else(|e| fail(&e));
+    let user = store
+        .user_store()
+        .insert_user(&new_user)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+
+    println!("Created user [{}].", user.username);
+}
+
+fn set_password(store: &dyn Store, args: &ArgMatches) {
+    let mut user = find_user(store, args.value_of("username").unwrap());
+    user.password_hash = read_password();
+    store
+        .user_store()
+        .update_user(&user)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+
+    println!("Updated the password of user [{}].", user.username);
+}
+
+fn delete_user(store: &dyn Store, args: &ArgMatches) {
+    let user = find_user(store, args.value_of("username").unwrap());
+    match store.user_store().delete_user(&user) {
+        Ok(true) => println!("Deleted user [{}].", user.username),
+        Ok(false) => fail(&format!("user [{}] was already deleted", user.username)),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+fn auth_method_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("auth_method")
+        .long("auth-method")
+        .takes_value(true)
+        .possible_values(client_auth::SUPPORTED_AUTH_METHODS)
+        .help("How the client authenticates at the token endpoint")
+}
+
+fn jwks_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("jwks")
+        .long("jwks")
+        .takes_value(true)
+        .help("A file holding the JWK Set private_key_jwt assertions are verified with")
+}
+
+fn tls_subject_dn_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("tls_subject_dn")
+        .long("tls-subject-dn")
+        .takes_value(true)
+        .help("The subject DN of the certificate tls_client_auth clients present, e.g. CN=client,O=Example")
+}
+
+fn grant_type_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("grant_type")
+        .long("grant-type")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help(help)
+}
+
+fn identifier_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("identifier")
+        .required(true)
+        .help("The client identifier")
+}
+
+fn username_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("username")
+        .required(true)
+        .help("The username")
+}
+
+fn redirect_uri_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("redirect_uri")
+        .required(true)
+        .help("An absolute redirect URI, without a fragment")
+}
+
+fn main() {
+    let matches = App::new("oa2p-admin")
+        .about("Manages the clients and users of an oa2p provider")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("list").about("Lists every client"))
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Shows a client, its grant types and its redirect URIs")
+                .arg(identifier_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Creates a client, and prints its secret")
+                .arg(
+                    Arg::with_name("identifier")
+                        .long("identifier")
+                        .takes_value(true)
+                        .help("The client identifier. A random one is generated if omitted"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .takes_value(true)
+                        .help("A human readable name for the client"),
+                )
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .takes_value(true)
+                        .default_value("confidential")
+                        .help("The client type. Anything but confidential is a public client"),
+                )
+                .arg(auth_method_arg())
+                .arg(jwks_arg())
+                .arg(tls_subject_dn_arg())
+                .arg(grant_type_arg(
+                    "A grant type the client may use. May be repeated. Defaults to \
+                     authorization_code and refresh_token",
+                ))
+                .arg(
+                    Arg::with_name("require_pkce")
+                        .long("require-pkce")
+                        .help("Rejects authorization requests without a code_challenge"),
+                )
+                .arg(
+                    Arg::with_name("redirect_uri")
+                        .long("redirect-uri")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A redirect URI to register. May be repeated"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("update")
+                .about("Updates a client")
+                .arg(identifier_arg())
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .takes_value(true)
+                        .help("The client type"),
+                )
+                .arg(auth_method_arg())
+                .arg(jwks_arg())
+                .arg(tls_subject_dn_arg())
+                .arg(grant_type_arg(
+                    "A grant type the client may use. May be repeated. Replaces the \
+                     client's grant types",
+                ))
+                .arg(
+                    Arg::with_name("require_pkce")
+                        .long("require-pkce")
+                        .takes_value(true)
+                        .possible_values(&["true", "false"])
+                        .help("Whether authorization requests need a code_challenge"),
+                )
+                .arg(
+                    Arg::with_name("reset_secret")
+                        .long("reset-secret")
+                        .help("Replaces the secret, and prints the new one"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("delete")
+                .about("Deletes a client, along with its tokens and codes")
+                .arg(identifier_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("add-redirect-uri")
+                .about("Registers a redirect URI for a client")
+                .arg(identifier_arg())
+                .arg(redirect_uri_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("remove-redirect-uri")
+                .about("Removes a redirect URI from a client")
+                .arg(identifier_arg())
+                .arg(redirect_uri_arg()),
+        )
+        .subcommand(SubCommand::with_name("list-users").about("Lists every user"))
+        .subcommand(
+            SubCommand::with_name("create-user")
+                .about("Creates a user, reading their password from stdin")
+                .arg(username_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("set-password")
+                .about("Replaces a user's password, reading it from stdin")
+                .arg(username_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("delete-user")
+                .about("Deletes a user, along with the tokens issued on their behalf")
+                .arg(username_arg()),
+        )
+        .get_matches();
+
+    let store = &*STORE.get().unwrap_or_else(|e| fail(&e.to_string()));
+
+    match matches.subcommand() {
+        ("list", Some(_)) => list(store),
+        ("show", Some(args)) => show(store, args),
+        ("create", Some(args)) => create(store, args),
+        ("update", Some(args)) => update(store, args),
+        ("delete", Some(args)) => delete(store, args),
+        ("add-redirect-uri", Some(args)) => add_redirect_uri(store, args),
+        ("remove-redirect-uri", Some(args)) => remove_redirect_uri(store, args),
+        ("list-users", Some(_)) => list_users(store),
+        ("create-user", Some(args)) => create_user(store, args),
+        ("set-password", Some(args)) => set_password(store, args),
+        ("delete-user", Some(args)) => delete_user(store, args),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,195 @@
+//! The keystore module holds the keys the provider signs tokens with.
+//!
+//! Exactly one key is active, and is used for every new signature. Keys that
+//! are no longer active are retired: they stay published (and usable for
+//! verification) for `retired_key_ttl` seconds, so tokens signed shortly
+//! before a rotation remain valid until they expire.
+//!
+//! Keys are configured under `[oauth.jwt]` in config.toml. Sending the process
+//! a SIGHUP makes it re-read that section, which is how keys are rotated
+//! without a restart.
+
+use SETTINGS;
+use chrono::{Duration, NaiveDateTime};
+use chrono::offset::Utc;
+use config::{Config, ConfigError, File as ConfigFile};
+use models::configuration::{JwtSettings, TokenFormat};
+use signal_hook;
+use std::ops::Add;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use utils::jwt::SigningKey;
+
+lazy_static! {
+    static ref KEY_STORE: RwLock<KeyStore> = RwLock::new(KeyStore::default());
+    static ref RELOAD_REQUESTED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+}
+
+#[derive(Debug)]
+pub struct StoredKey {
+    pub key: SigningKey,
+    pub retired_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyStore {
+    keys: Vec<StoredKey>,
+    retired_key_ttl: i64,
+}
+
+impl KeyStore {
+    /// Builds a key store from settings. Keys that were already known to the
+    /// previous store keep their retirement time, so reloading does not extend
+    /// their grace period.
+    ///
+    /// Retirement times only live in memory: on startup, every configured key
+    /// but the active one counts as retired just now, so a restart starts
+    /// their grace period over.
+    pub fn load(settings: &JwtSettings, previous: &KeyStore) -> Result<KeyStore, String> {
+        let now = Utc::now().naive_utc();
+        let mut keys = Vec::new();
+
+        for key_settings in &settings.keys {
+            let key = SigningKey::from_pem_file(
+                &key_settings.private_key,
+                key_settings.algorithm,
+                Some(key_settings.kid.clone()),
+            ).map_err(|e| format!("Unable to load key [{}]: {}", key_settings.kid, e))?;
+
+            let retired_at = if key_settings.kid == settings.active_kid {
+                None
+            } else {
+                previous
+                    .get(&key_settings.kid)
+                    .and_then(|k| k.retired_at)
+                    .or(Some(now))
+            };
+
+            keys.push(StoredKey { key, retired_at });
+        }
+
+        if !keys.iter().any(|k| k.retired_at.is_none()) {
+            return Err(format!(
+                "The active key [{}] is not among the configured keys",
+                settings.active_kid
+            ));
+        }
+
+        Ok(KeyStore {
+            keys,
+            retired_key_ttl: settings.retired_key_ttl,
+        })
+    }
+
+    fn get(&self, kid: &str) -> Option<&StoredKey> {
+        self.keys
+            .iter()
+            .find(|k| k.key.kid.as_ref().map(|v| v.as_str()) == Some(kid))
+    }
+
+    fn is_published(&self, key: &StoredKey) -> bool {
+        match key.retired_at {
+            None => true,
+            Some(retired_at) => {
+                retired_at.add(Duration::seconds(self.retired_key_ttl)) > Utc::now().naive_utc()
+            }
+        }
+    }
+
+    /// The key new signatures should be made with.
+    pub fn active(&self) -> Option<&SigningKey> {
+        self.keys
+            .iter()
+            .find(|k| k.retired_at.is_none())
+            .map(|k| &k.key)
+    }
+
+    /// Every key that is active, or retired but still within its grace period.
+    pub fn published(&self) -> Vec<&SigningKey> {
+        self.keys
+            .iter()
+            .filter(|k| self.is_published(k))
+            .map(|k| &k.key)
+            .collect()
+    }
+
+    /// Finds the published key a token was signed with. Tokens without a `kid`
+    /// are only matched to the active key.
+    pub fn find(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        match kid {
+            Some(kid) => self.get(kid)
+                .filter(|k| self.is_published(k))
+                .map(|k| &k.key),
+            None => self.active(),
+        }
+    }
+}
+
+/// Reads the `[oauth.jwt]` section of config.toml, if there is one.
+fn read_settings() -> Result<Option<JwtSettings>, String> {
+    let mut config_data = Config::new();
+    config_data
+        .merge(ConfigFile::with_name("config.toml"))
+        .map_err(|e| e.to_string())?;
+
+    match config_data.get::<JwtSettings>("oauth.jwt") {
+        Ok(settings) => Ok(Some(settings)),
+        Err(ConfigError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn reload() {
+    info!("Reloading signing keys.");
+    let settings = match read_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Unable to read signing key settings, keeping current keys: {}", e);
+            return;
+        }
+    };
+
+    let mut store = KEY_STORE.write().unwrap();
+    let reloaded = match settings {
+        Some(ref settings) => KeyStore::load(settings, &store),
+        // Tokens could no longer be signed
+        None if SETTINGS.oauth.token_format == TokenFormat::Jwt => {
+            Err("[oauth.jwt] is missing, but token_format is \"jwt\"".to_owned())
+        }
+        None => Ok(KeyStore::default()),
+    };
+
+    match reloaded {
+        Ok(reloaded) => {
+            *store = reloaded;
+            info!(
+                "Signing keys reloaded, active key is [{:?}]",
+                store.active().and_then(|k| k.kid.clone())
+            );
+        }
+        Err(e) => error!("Unable to reload signing keys, keeping current keys: {}", e),
+    }
+}
+
+/// Loads the configured keys, and arranges for them to be reloaded on SIGHUP.
+pub fn init(settings: Option<&JwtSettings>) -> Result<(), String> {
+    if let Some(settings) = settings {
+        let store = KeyStore::load(settings, &KeyStore::default())?;
+        *KEY_STORE.write().unwrap() = store;
+    }
+
+    signal_hook::flag::register(signal_hook::SIGHUP, Arc::clone(&RELOAD_REQUESTED))
+        .map_err(|e| format!("Unable to register the SIGHUP handler: {}", e))?;
+
+    Ok(())
+}
+
+/// Returns the current key store, picking up any reload requested since the
+/// last call.
+pub fn read() -> RwLockReadGuard<'static, KeyStore> {
+    if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+        reload();
+    }
+
+    KEY_STORE.read().unwrap()
+}
@@ -0,0 +1,59 @@
+#![feature(plugin, custom_derive, macro_vis_matcher)]
+#![plugin(rocket_codegen)]
+
+extern crate argon2;
+extern crate base64;
+extern crate bcrypt;
+extern crate chrono;
+extern crate config;
+#[macro_use]
+extern crate lazy_static;
+extern crate url;
+extern crate uuid;
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate diesel_codegen;
+#[macro_use]
+extern crate diesel_migrations;
+extern crate r2d2;
+extern crate r2d2_diesel;
+extern crate reqwest;
+extern crate serde;
+extern crate signal_hook;
+extern crate sha2;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate derive_builder;
+extern crate rocket;
+extern crate rocket_contrib;
+#[macro_use]
+extern crate log;
+extern crate openssl;
+
+pub mod keystore;
+pub mod models;
+pub mod persistence;
+pub mod store;
+pub mod utils;
+pub mod web;
+
+lazy_static! {
+    pub static ref SETTINGS: models::configuration::AppSettings = {
+        use config::{Config, File as ConfigFile};
+        let mut config_data = Config::new();
+        config_data
+            .merge(ConfigFile::with_name("config.toml"))
+            .unwrap();
+        config_data
+            .try_into()
+            .expect("Error initializing application settings from the config.toml file; crashing!")
+    };
+}
+
+lazy_static! {
+    pub static ref STORE: Box<dyn store::StoreProvider> = store::connect(&SETTINGS.db);
+}
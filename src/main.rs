@@ -1,80 +1,64 @@
-#![feature(plugin, custom_derive, macro_vis_matcher)]
-#![plugin(rocket_codegen)]
-
-extern crate base64;
-extern crate bcrypt;
-extern crate chrono;
-extern crate config;
-#[macro_use]
-extern crate lazy_static;
-extern crate uuid;
-#[macro_use]
-extern crate diesel;
-#[macro_use]
-extern crate diesel_codegen;
-extern crate r2d2;
-extern crate r2d2_diesel;
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-#[macro_use]
-extern crate serde_json;
-#[macro_use]
-extern crate derive_builder;
-extern crate rocket;
 #[macro_use]
 extern crate log;
 extern crate log4rs;
+extern crate oa2p;
+extern crate rocket;
 
-use diesel::pg::PgConnection;
-use r2d2::Pool;
-use r2d2_diesel::ConnectionManager;
+use oa2p::models::configuration::{TokenFormat, DEFAULT_ISSUER};
+use oa2p::{keystore, web, SETTINGS, STORE};
 
-mod models;
-mod persistence;
-mod utils;
-mod web;
+fn main() {
+    log4rs::init_file(".log4rs.yml", Default::default()).unwrap();
 
-lazy_static! {
-    static ref SETTINGS: models::configuration::AppSettings = {
-        use config::{Config, File as ConfigFile};
-        let mut config_data = Config::new();
-        config_data
-            .merge(ConfigFile::with_name("config.toml"))
-            .unwrap();
-        config_data
-            .try_into()
-            .expect("Error initializing application settings from the config.toml file; crashing!")
-    };
-}
+    // `oa2p migrate` brings the schema up to date and exits, without starting
+    // the server.
+    if std::env::args().nth(1).as_ref().map(|a| a.as_str()) == Some("migrate") {
+        STORE.migrate().expect("Failed to run database migrations");
+        return;
+    }
+    if SETTINGS.db.auto_migrate {
+        STORE.migrate().expect("Failed to run database migrations");
+    }
 
-lazy_static! {
-    pub static ref DB_POOL: Pool<ConnectionManager<PgConnection>> = {
-        let db_url = format!(
-            "postgres://{}:{}@{}:{}/{}",
-            &SETTINGS.db.user,
-            &SETTINGS.db.pass,
-            &SETTINGS.db.host,
-            SETTINGS.db.port,
-            &SETTINGS.db.db_name
+    // Discovery, JWTs and the URLs handed to clients are all built from the
+    // issuer, so the fallback only suits local development.
+    if SETTINGS.issuer.is_none() {
+        warn!(
+            "No issuer is configured, falling back to [{}]; set issuer in config.toml to the provider's public URL",
+            DEFAULT_ISSUER
         );
-        debug!("db url: {}", &db_url);
-        let manager = ConnectionManager::<PgConnection>::new(db_url);
+    }
 
-        Pool::builder()
-            .max_size(SETTINGS.db.pool_size)
-            .build(manager)
-            .expect("Failed to initialize the DB connection pool")
+    // Fail at boot, rather than on the first token request, if the signing
+    // keys are misconfigured.
+    if SETTINGS.oauth.token_format == TokenFormat::Jwt && SETTINGS.oauth.jwt.is_none() {
+        panic!("token_format is set to jwt, but no [oauth.jwt] settings were provided");
+    }
+    keystore::init(SETTINGS.oauth.jwt.as_ref()).expect("Failed to load the signing keys");
+
+    // Rocket.toml still configures the listener, but the certificate lives
+    // alongside the rest of the provider's settings.
+    let tls = &SETTINGS.tls;
+    let rocket = match (tls.certs.as_ref(), tls.key.as_ref()) {
+        (Some(certs), Some(key)) => {
+            let mut config = rocket::ignite().config().clone();
+            config
+                .set_tls(certs, key)
+                .expect("Failed to load the TLS certificate chain and key");
+            rocket::custom(config, true)
+        }
+        (None, None) if tls.require_tls => {
+            panic!("require_tls is set, but no [tls] certificate chain and key were provided")
+        }
+        (None, None) => rocket::ignite(),
+        _ => panic!("the [tls] section needs both certs and key"),
     };
-}
 
-fn main() {
-    log4rs::init_file(".log4rs.yml", Default::default()).unwrap();
+    let routes = web::routes();
+    let mounted = web::MountedRoutes::new(&routes);
 
-    rocket::ignite()
-        .mount(
-            "/",
-            routes![web::handlers::token::post, web::handlers::introspect::post],
-        )
+    rocket
+        .manage(mounted)
+        .mount("/", routes)
         .launch();
 }
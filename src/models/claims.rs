@@ -0,0 +1,81 @@
+/// Binds a token to the client certificate with the given thumbprint.
+///
+/// See: https://tools.ietf.org/html/rfc8705#section-3.1
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Confirmation {
+    #[serde(rename = "x5t#S256")]
+    pub x5t_s256: String,
+}
+
+// See: https://tools.ietf.org/html/rfc9068#section-2.2
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[builder(setter(into))]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub client_id: String,
+    pub scope: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+/// The party acting on behalf of a token's subject, nesting the parties that
+/// acted before it, most recent first.
+///
+/// See: https://tools.ietf.org/html/rfc8693#section-4.1
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+/// The audience of a token, which may be a single value or a list.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, value: &str) -> bool {
+        match *self {
+            Audience::One(ref aud) => aud == value,
+            Audience::Many(ref auds) => auds.iter().any(|aud| aud == value),
+        }
+    }
+}
+
+// See: https://tools.ietf.org/html/rfc7523#section-3
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClientAssertionClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub jti: String,
+    pub nbf: Option<i64>,
+    pub iat: Option<i64>,
+}
+
+/// The claims of an assertion presented with the JWT bearer grant. Unlike
+/// client assertions, these need not carry a `jti`.
+///
+/// See: https://tools.ietf.org/html/rfc7523#section-3
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JwtBearerClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub jti: Option<String>,
+    pub nbf: Option<i64>,
+    pub iat: Option<i64>,
+}
@@ -1,27 +1,209 @@
+use utils::jwt::Algorithm;
+
+/// The issuer used when config.toml sets none: the address Rocket listens on
+/// by default. It is only good for local development.
+pub const DEFAULT_ISSUER: &str = "http://localhost:8000";
+
 #[derive(Deserialize)]
 pub struct AppSettings {
+    /// The externally visible base URL of the provider. Read it through
+    /// `issuer()`, which falls back to `DEFAULT_ISSUER`.
+    pub issuer: Option<String>,
     pub logging: LoggingSettings,
+    #[serde(default)]
     pub db: DatabaseSettings,
+    #[serde(default)]
+    pub tls: TlsSettings,
     pub oauth: OauthSettings,
 }
 
+impl AppSettings {
+    /// The configured issuer, or `DEFAULT_ISSUER` if there is none.
+    pub fn issuer(&self) -> &str {
+        self.issuer
+            .as_ref()
+            .map_or(DEFAULT_ISSUER, |v| v.as_str())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LoggingSettings {
     pub time_format: String,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct DatabaseSettings {
+    pub backend: StoreBackend,
     pub host: String,
     pub port: u32,
     pub user: String,
     pub pass: String,
     pub db_name: String,
     pub pool_size: u32,
+    pub path: String,
+    pub fixture: Option<String>,
+    /// Runs any pending migrations before the server starts.
+    pub auto_migrate: bool,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> DatabaseSettings {
+        DatabaseSettings {
+            backend: StoreBackend::Postgres,
+            host: "localhost".to_owned(),
+            port: 5432,
+            user: "oa2p".to_owned(),
+            pass: "oa2p".to_owned(),
+            db_name: "oa2p".to_owned(),
+            pool_size: 10,
+            path: "oa2p.sqlite3".to_owned(),
+            fixture: None,
+            auto_migrate: false,
+        }
+    }
+}
+
+/// Serving HTTPS directly, for deployments without a TLS terminating proxy.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// The path to the PEM encoded certificate chain, leaf certificate first.
+    pub certs: Option<String>,
+    /// The path to the PEM encoded private key of the leaf certificate.
+    pub key: Option<String>,
+    /// Refuses to start without a certificate and key, rather than falling
+    /// back to plain HTTP.
+    pub require_tls: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    Postgres,
+    /// Only available when built with the `sqlite-store` feature.
+    Sqlite,
+    /// Only available when built with the `memory-store` feature.
+    Memory,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OauthSettings {
     pub access_token_ttl: i64,
     pub refresh_token_ttl: i64,
+    /// Replaces the refresh token on every refresh, and revokes every token
+    /// descended from the same grant when a replaced one is presented again.
+    #[serde(default)]
+    pub rotate_refresh_tokens: bool,
+    pub auth_code_ttl: i64,
+    pub scopes_supported: Option<Vec<String>>,
+    #[serde(default)]
+    pub token_format: TokenFormat,
+    pub jwt: Option<JwtSettings>,
+    /// Dynamic client registration is only enabled when this section exists.
+    pub registration: Option<RegistrationSettings>,
+    /// Mutual-TLS client authentication is only enabled when this section
+    /// exists.
+    pub mtls: Option<MtlsSettings>,
+    /// The device authorization grant is only enabled when this section
+    /// exists.
+    pub device: Option<DeviceSettings>,
+    /// The JWT bearer grant is only enabled when this section exists.
+    pub jwt_bearer: Option<JwtBearerSettings>,
+    /// The token exchange grant is only enabled when this section exists.
+    pub token_exchange: Option<TokenExchangeSettings>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenFormat {
+    /// Opaque tokens, which resource servers have to introspect.
+    Uuid,
+    /// Self-contained, signed JWTs, as described in RFC 9068.
+    Jwt,
+}
+
+impl Default for TokenFormat {
+    fn default() -> TokenFormat {
+        TokenFormat::Uuid
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JwtSettings {
+    pub audience: String,
+    pub active_kid: String,
+    pub retired_key_ttl: i64,
+    pub keys: Vec<SigningKeySettings>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SigningKeySettings {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub private_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegistrationSettings {
+    /// When set, registration requests must carry this value as a bearer
+    /// token. Otherwise anyone can register a client.
+    pub initial_access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MtlsSettings {
+    /// The request header a TLS terminating proxy forwards the verified client
+    /// certificate in, as URL encoded PEM. The proxy must strip this header
+    /// from incoming requests, or clients could forge it.
+    pub client_cert_header: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceSettings {
+    /// How long, in seconds, the user has to enter a code on the verification
+    /// page.
+    pub code_ttl: i64,
+    /// The minimum number of seconds devices must wait between polls.
+    pub interval: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JwtBearerSettings {
+    /// How long, in seconds, the JWK Sets of trusted issuers are cached for
+    /// before being loaded again.
+    pub jwks_cache_ttl: i64,
+    pub issuers: Vec<TrustedIssuerSettings>,
+}
+
+/// An issuer whose assertions are accepted with the JWT bearer grant.
+#[derive(Debug, Deserialize)]
+pub struct TrustedIssuerSettings {
+    /// The `iss` claim of the assertions the issuer signs.
+    pub issuer: String,
+    /// Where the issuer's JWK Set is loaded from: either the path to a local
+    /// file, or an http(s) URL.
+    pub jwks: String,
+    /// What the `sub` claim of the issuer's assertions identifies.
+    pub subject: SubjectType,
+    /// The scopes tokens issued for the issuer's assertions may carry, as a
+    /// space delimited list. Requests without a scope are given all of them.
+    pub scope: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectType {
+    /// The subject is the identifier of the client the token is issued to.
+    Client,
+    /// The subject is the username of the user the authenticated client acts
+    /// for.
+    User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenExchangeSettings {
+    /// The logical names and resource URIs of the services exchanged tokens
+    /// may be aimed at. Requests for any other target are rejected.
+    pub audiences: Vec<String>,
 }
@@ -3,7 +3,7 @@ use persistence::*;
 use std::fmt;
 use uuid::Uuid;
 
-#[derive(Builder, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[derive(Builder, Clone, Serialize, Deserialize, Identifiable, Queryable, Associations)]
 #[builder(setter(into))]
 #[table_name = "clients"]
 pub struct Client {
@@ -11,19 +11,122 @@ pub struct Client {
     pub identifier: String,
     pub secret: String,
     pub response_type: String,
+    pub require_pkce: bool,
+    pub client_name: Option<String>,
+    /// The scope the client registered for, as a space delimited list.
+    pub scope: Option<String>,
+    pub token_endpoint_auth_method: String,
+    /// The bcrypt hash of the token the client manages its registration with.
+    pub registration_access_token: Option<String>,
+    /// The JWK Set, as JSON, that `private_key_jwt` assertions are verified
+    /// with.
+    pub jwks: Option<String>,
+    /// The key `client_secret_jwt` assertions are signed with. Unlike
+    /// `secret`, it is needed to verify signatures, so it is stored as is.
+    pub jwt_secret: Option<String>,
+    /// The subject DN of the certificate `tls_client_auth` clients present.
+    pub tls_client_auth_subject_dn: Option<String>,
 }
 
 impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Client {{ id: {}, identifier: {}, secret: [REDACTED], response_type: {} }}",
-            self.id, self.identifier, self.response_type
+            "Client {{ id: {}, identifier: {}, secret: [REDACTED], response_type: {}, require_pkce: {}, \
+             client_name: {:?}, scope: {:?}, token_endpoint_auth_method: {}, \
+             registration_access_token: [REDACTED], jwks: {:?}, jwt_secret: [REDACTED], \
+             tls_client_auth_subject_dn: {:?} }}",
+            self.id,
+            self.identifier,
+            self.response_type,
+            self.require_pkce,
+            self.client_name,
+            self.scope,
+            self.token_endpoint_auth_method,
+            self.jwks,
+            self.tls_client_auth_subject_dn
         )
     }
 }
 
-#[derive(Builder, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[derive(Builder, Serialize, Deserialize, Insertable)]
+#[builder(setter(into))]
+#[table_name = "clients"]
+pub struct NewClient {
+    pub identifier: String,
+    /// The bcrypt hash of the client secret.
+    pub secret: String,
+    pub response_type: String,
+    pub require_pkce: bool,
+    pub client_name: Option<String>,
+    pub scope: Option<String>,
+    pub token_endpoint_auth_method: String,
+    pub registration_access_token: Option<String>,
+    pub jwks: Option<String>,
+    pub jwt_secret: Option<String>,
+    pub tls_client_auth_subject_dn: Option<String>,
+}
+
+impl fmt::Debug for NewClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NewClient {{ identifier: {}, secret: [REDACTED], response_type: {}, require_pkce: {}, \
+             client_name: {:?}, scope: {:?}, token_endpoint_auth_method: {}, \
+             registration_access_token: [REDACTED], jwks: {:?}, jwt_secret: [REDACTED], \
+             tls_client_auth_subject_dn: {:?} }}",
+            self.identifier,
+            self.response_type,
+            self.require_pkce,
+            self.client_name,
+            self.scope,
+            self.token_endpoint_auth_method,
+            self.jwks,
+            self.tls_client_auth_subject_dn
+        )
+    }
+}
+
+/// A resource owner, able to authenticate with the `password` grant.
+#[derive(Builder, Clone, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "users"]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    /// The bcrypt or argon2 hash of the user's password.
+    pub password_hash: String,
+}
+
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User {{ id: {}, username: {}, password_hash: [REDACTED] }}",
+            self.id, self.username
+        )
+    }
+}
+
+#[derive(Builder, Serialize, Deserialize, Insertable)]
+#[builder(setter(into))]
+#[table_name = "users"]
+pub struct NewUser {
+    pub username: String,
+    pub password_hash: String,
+}
+
+impl fmt::Debug for NewUser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NewUser {{ username: {}, password_hash: [REDACTED] }}",
+            self.username
+        )
+    }
+}
+
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
 #[builder(setter(into))]
 #[table_name = "grant_types"]
 pub struct GrantType {
@@ -31,7 +134,59 @@ pub struct GrantType {
     pub name: String,
 }
 
-#[derive(Builder, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "client_redirect_uris"]
+pub struct ClientRedirectUri {
+    pub id: i32,
+    pub client_id: i32,
+    pub redirect_uri: String,
+}
+
+#[derive(Builder, Debug, Serialize, Deserialize, Insertable)]
+#[builder(setter(into))]
+#[table_name = "client_redirect_uris"]
+pub struct NewClientRedirectUri {
+    pub client_id: i32,
+    pub redirect_uri: String,
+}
+
+/// A grant type a client registered to use.
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "client_grant_types"]
+pub struct ClientGrantType {
+    pub id: i32,
+    pub client_id: i32,
+    pub grant_id: i32,
+}
+
+/// A client assertion that was used to authenticate, remembered until it
+/// expires so it cannot be replayed.
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "client_assertions"]
+pub struct ClientAssertion {
+    pub id: i32,
+    pub client_id: i32,
+    pub jti: String,
+    pub expires_at: NaiveDateTime,
+}
+
+/// An assertion exchanged through the JWT bearer grant, remembered until it
+/// expires so it cannot be replayed.
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable)]
+#[builder(setter(into))]
+#[table_name = "bearer_assertions"]
+pub struct BearerAssertion {
+    pub id: i32,
+    /// The `iss` of the assertion, whose `jti` values are unique to it.
+    pub issuer: String,
+    pub jti: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
 #[builder(setter(into))]
 #[table_name = "access_tokens"]
 pub struct AccessToken {
@@ -42,20 +197,44 @@ pub struct AccessToken {
     pub scope: String,
     pub issued_at: NaiveDateTime,
     pub expires_at: NaiveDateTime,
+    pub refresh_token_id: Option<i32>,
+    pub revoked_at: Option<NaiveDateTime>,
+    /// The `x5t#S256` thumbprint of the client certificate the token is bound
+    /// to, if any.
+    pub cert_thumbprint: Option<String>,
+    /// The resource owner the token was issued on behalf of, if any.
+    pub user_id: Option<i32>,
+    /// The services a token obtained through token exchange is meant for, as
+    /// a space delimited list. Other tokens are meant for the configured
+    /// audience.
+    pub audience: Option<String>,
+    /// The `act` claim, as JSON, of a token obtained through token exchange:
+    /// the party acting on behalf of the subject, and any prior actors.
+    pub act: Option<String>,
+    /// The access token a token obtained through token exchange was exchanged
+    /// for, if any. Revoking it revokes this token too.
+    pub subject_token_id: Option<i32>,
 }
 
 #[derive(Builder, Debug, Serialize, Deserialize, Insertable)]
 #[builder(setter(into))]
 #[table_name = "access_tokens"]
 pub struct NewAccessToken {
+    pub token: Uuid,
     pub client_id: i32,
     pub grant_id: i32,
     pub scope: String,
     pub issued_at: NaiveDateTime,
     pub expires_at: NaiveDateTime,
+    pub refresh_token_id: Option<i32>,
+    pub cert_thumbprint: Option<String>,
+    pub user_id: Option<i32>,
+    pub audience: Option<String>,
+    pub act: Option<String>,
+    pub subject_token_id: Option<i32>,
 }
 
-#[derive(Builder, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
 #[builder(setter(into))]
 #[table_name = "refresh_tokens"]
 pub struct RefreshToken {
@@ -65,14 +244,101 @@ pub struct RefreshToken {
     pub scope: String,
     pub issued_at: NaiveDateTime,
     pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    /// The resource owner the token was issued on behalf of, if any.
+    pub user_id: Option<i32>,
+    /// The first token of the family the token was rotated from. None for the
+    /// first token itself.
+    pub family_id: Option<i32>,
+    /// When the token was replaced by a newer one of its family.
+    pub rotated_at: Option<NaiveDateTime>,
+}
+
+impl RefreshToken {
+    /// The id shared by every token rotated from the same grant.
+    pub fn family(&self) -> i32 {
+        self.family_id.unwrap_or(self.id)
+    }
 }
 
 #[derive(Builder, Debug, Serialize, Deserialize, Insertable)]
 #[builder(setter(into))]
 #[table_name = "refresh_tokens"]
 pub struct NewRefreshToken {
+    pub token: Uuid,
     pub client_id: i32,
     pub scope: String,
     pub issued_at: NaiveDateTime,
     pub expires_at: Option<NaiveDateTime>,
+    pub user_id: Option<i32>,
+    pub family_id: Option<i32>,
+}
+
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "auth_codes"]
+pub struct AuthCode {
+    pub id: i32,
+    pub client_id: i32,
+    pub name: String,
+    pub scope: String,
+    pub expires_at: NaiveDateTime,
+    pub redirect_uri: String,
+    pub user_id: Option<i32>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Builder, Debug, Serialize, Deserialize, Insertable)]
+#[builder(setter(into))]
+#[table_name = "auth_codes"]
+pub struct NewAuthCode {
+    pub client_id: i32,
+    pub name: String,
+    pub scope: String,
+    pub expires_at: NaiveDateTime,
+    pub redirect_uri: String,
+    pub user_id: Option<i32>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// A pending device authorization. The device polls the token endpoint with
+/// the `device_code`, while the resource owner enters the `user_code` on the
+/// verification page.
+#[derive(Builder, Clone, Debug, Serialize, Deserialize, Identifiable, Queryable, Associations)]
+#[builder(setter(into))]
+#[table_name = "device_codes"]
+pub struct DeviceCode {
+    pub id: i32,
+    pub client_id: i32,
+    pub device_code: String,
+    /// The user code, without the separator it is displayed with.
+    pub user_code: String,
+    pub scope: String,
+    pub expires_at: NaiveDateTime,
+    /// The minimum number of seconds the device must wait between polls.
+    pub poll_interval: i32,
+    pub last_polled_at: Option<NaiveDateTime>,
+    /// The resource owner who approved or denied the request, if any did.
+    pub user_id: Option<i32>,
+    pub status: String,
+}
+
+impl DeviceCode {
+    pub const PENDING: &'static str = "pending";
+    pub const APPROVED: &'static str = "approved";
+    pub const DENIED: &'static str = "denied";
+}
+
+#[derive(Builder, Debug, Serialize, Deserialize, Insertable)]
+#[builder(setter(into))]
+#[table_name = "device_codes"]
+pub struct NewDeviceCode {
+    pub client_id: i32,
+    pub device_code: String,
+    pub user_code: String,
+    pub scope: String,
+    pub expires_at: NaiveDateTime,
+    pub poll_interval: i32,
 }
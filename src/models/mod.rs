@@ -1,3 +1,4 @@
+pub mod claims;
 pub mod configuration;
 pub mod db;
 pub mod requests;
@@ -1,7 +1,55 @@
-#[derive(Builder, Clone, Debug, Deserialize, FromForm, Serialize)]
+use std::fmt;
+
+#[derive(Builder, Clone, Deserialize, FromForm, Serialize)]
 pub struct AccessTokenRequest {
     pub grant_type: Option<String>,
     pub scope: Option<String>,
     pub refresh_token: Option<String>,
     pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+    pub code_verifier: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub device_code: Option<String>,
+    pub assertion: Option<String>,
+    pub subject_token: Option<String>,
+    pub subject_token_type: Option<String>,
+    pub actor_token: Option<String>,
+    pub actor_token_type: Option<String>,
+    pub audience: Option<String>,
+    pub resource: Option<String>,
+    pub requested_token_type: Option<String>,
+}
+
+impl fmt::Debug for AccessTokenRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AccessTokenRequest {{ grant_type: {:?}, scope: {:?}, refresh_token: {:?}, code: {:?}, \
+             redirect_uri: {:?}, client_id: {:?}, client_secret: [REDACTED], \
+             client_assertion_type: {:?}, client_assertion: [REDACTED], code_verifier: {:?}, \
+             username: {:?}, password: [REDACTED], device_code: {:?}, assertion: [REDACTED], \
+             subject_token: [REDACTED], subject_token_type: {:?}, actor_token: [REDACTED], \
+             actor_token_type: {:?}, audience: {:?}, resource: {:?}, requested_token_type: {:?} }}",
+            self.grant_type,
+            self.scope,
+            self.refresh_token,
+            self.code,
+            self.redirect_uri,
+            self.client_id,
+            self.client_assertion_type,
+            self.code_verifier,
+            self.username,
+            self.device_code,
+            self.subject_token_type,
+            self.actor_token_type,
+            self.audience,
+            self.resource,
+            self.requested_token_type
+        )
+    }
 }
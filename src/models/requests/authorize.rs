@@ -0,0 +1,68 @@
+use std::fmt;
+
+// See: https://tools.ietf.org/html/rfc6749#section-4.1.1
+#[derive(Builder, Clone, Debug, Deserialize, FromForm)]
+pub struct AuthorizationRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// The form posted back from the consent page. It echoes the original
+/// authorization request, alongside the token the page was rendered with, the
+/// resource owner's credentials and their decision.
+#[derive(Builder, Clone, Deserialize, FromForm)]
+pub struct AuthorizationConsentRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub csrf_token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub decision: String,
+}
+
+impl fmt::Debug for AuthorizationConsentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AuthorizationConsentRequest {{ response_type: {:?}, client_id: {:?}, \
+             redirect_uri: {:?}, scope: {:?}, state: {:?}, code_challenge: {:?}, \
+             code_challenge_method: {:?}, csrf_token: {:?}, username: {:?}, \
+             password: [REDACTED], decision: {:?} }}",
+            self.response_type,
+            self.client_id,
+            self.redirect_uri,
+            self.scope,
+            self.state,
+            self.code_challenge,
+            self.code_challenge_method,
+            self.csrf_token,
+            self.username,
+            self.decision
+        )
+    }
+}
+
+impl AuthorizationConsentRequest {
+    /// Returns the authorization request the consent form was rendered for.
+    pub fn authorization_request(&self) -> AuthorizationRequest {
+        AuthorizationRequest {
+            response_type: self.response_type.clone(),
+            client_id: self.client_id.clone(),
+            redirect_uri: self.redirect_uri.clone(),
+            scope: self.scope.clone(),
+            state: self.state.clone(),
+            code_challenge: self.code_challenge.clone(),
+            code_challenge_method: self.code_challenge_method.clone(),
+        }
+    }
+}
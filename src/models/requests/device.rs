@@ -0,0 +1,55 @@
+use std::fmt;
+
+// See: https://tools.ietf.org/html/rfc8628#section-3.1
+#[derive(Builder, Clone, Deserialize, FromForm)]
+pub struct DeviceAuthorizationRequest {
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
+
+impl fmt::Debug for DeviceAuthorizationRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DeviceAuthorizationRequest {{ scope: {:?}, client_id: {:?}, \
+             client_secret: [REDACTED], client_assertion_type: {:?}, \
+             client_assertion: [REDACTED] }}",
+            self.scope,
+            self.client_id,
+            self.client_assertion_type
+        )
+    }
+}
+
+/// The query of the verification page, carrying the user code when the
+/// resource owner followed `verification_uri_complete`.
+#[derive(Builder, Clone, Debug, Deserialize, FromForm)]
+pub struct DeviceVerificationRequest {
+    pub user_code: Option<String>,
+}
+
+/// The form posted from the verification page. The resource owner signs in,
+/// and approves or denies the device.
+#[derive(Builder, Clone, Deserialize, FromForm)]
+pub struct DeviceConsentRequest {
+    pub user_code: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub decision: String,
+}
+
+impl fmt::Debug for DeviceConsentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DeviceConsentRequest {{ user_code: {:?}, username: {:?}, password: [REDACTED], \
+             decision: {:?} }}",
+            self.user_code,
+            self.username,
+            self.decision
+        )
+    }
+}
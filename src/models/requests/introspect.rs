@@ -1,5 +1,26 @@
-#[derive(Builder, Clone, Debug, Deserialize, FromForm)]
+use std::fmt;
+
+#[derive(Builder, Clone, Deserialize, FromForm)]
 pub struct IntrospectionRequest {
     pub token: String,
     pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
+
+impl fmt::Debug for IntrospectionRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IntrospectionRequest {{ token: {}, token_type_hint: {:?}, client_id: {:?}, \
+             client_secret: [REDACTED], client_assertion_type: {:?}, \
+             client_assertion: [REDACTED] }}",
+            self.token,
+            self.token_type_hint,
+            self.client_id,
+            self.client_assertion_type
+        )
+    }
 }
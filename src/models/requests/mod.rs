@@ -1,2 +1,6 @@
 pub mod access_token;
+pub mod authorize;
+pub mod device;
 pub mod introspect;
+pub mod register;
+pub mod revoke;
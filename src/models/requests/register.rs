@@ -0,0 +1,45 @@
+use std::fmt;
+use utils::jwk::JwkSet;
+
+// See: https://tools.ietf.org/html/rfc7591#section-2 and
+// https://tools.ietf.org/html/rfc7592#section-2.2
+#[derive(Builder, Clone, Default, Deserialize)]
+#[builder(setter(into))]
+#[serde(default)]
+pub struct ClientRegistrationRequest {
+    pub redirect_uris: Option<Vec<String>>,
+    pub grant_types: Option<Vec<String>>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub scope: Option<String>,
+    pub client_name: Option<String>,
+    /// The client's public keys, required for `private_key_jwt`. Keys carry
+    /// the client's certificates for `self_signed_tls_client_auth`.
+    pub jwks: Option<JwkSet>,
+    /// Required for `tls_client_auth`.
+    pub tls_client_auth_subject_dn: Option<String>,
+    /// Only sent with update requests, and must match the client being
+    /// updated.
+    pub client_id: Option<String>,
+    /// Only sent with update requests. If present, must match the client's
+    /// current secret.
+    pub client_secret: Option<String>,
+}
+
+impl fmt::Debug for ClientRegistrationRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ClientRegistrationRequest {{ redirect_uris: {:?}, grant_types: {:?}, \
+             token_endpoint_auth_method: {:?}, scope: {:?}, client_name: {:?}, jwks: {:?}, \
+             tls_client_auth_subject_dn: {:?}, client_id: {:?}, client_secret: [REDACTED] }}",
+            self.redirect_uris,
+            self.grant_types,
+            self.token_endpoint_auth_method,
+            self.scope,
+            self.client_name,
+            self.jwks,
+            self.tls_client_auth_subject_dn,
+            self.client_id
+        )
+    }
+}
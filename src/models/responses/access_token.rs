@@ -15,6 +15,10 @@ pub struct AccessTokenResponse {
     pub scope: String,
     pub refresh_token: Option<String>,
     pub refresh_expires_in: Option<i64>,
+    /// Only sent back by token exchange.
+    /// See: https://tools.ietf.org/html/rfc8693#section-2.2.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
 }
 
 impl<'r> Responder<'r> for AccessTokenResponse {
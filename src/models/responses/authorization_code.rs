@@ -0,0 +1,35 @@
+use rocket::Request;
+use rocket::http::Status;
+use rocket::http::hyper::header::{CacheControl, CacheDirective, Pragma};
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use url::Url;
+
+// See: https://tools.ietf.org/html/rfc6749#section-4.1.2
+#[derive(Builder, Debug)]
+#[builder(setter(into))]
+pub struct AuthorizationCodeResponse {
+    pub redirect_uri: String,
+    pub code: String,
+    pub state: Option<String>,
+}
+
+impl<'r> Responder<'r> for AuthorizationCodeResponse {
+    fn respond_to(self, _req: &Request) -> RocketResult<'r> {
+        let mut location = Url::parse(&self.redirect_uri).map_err(|_| Status::InternalServerError)?;
+        location.query_pairs_mut().append_pair("code", &self.code);
+        if let Some(state) = self.state {
+            location.query_pairs_mut().append_pair("state", &state);
+        }
+
+        Response::build()
+            .header(CacheControl(vec![
+                CacheDirective::NoCache,
+                CacheDirective::NoStore,
+            ]))
+            .header(Pragma::NoCache)
+            .raw_header("Location", location.into_string())
+            .status(Status::Found)
+            .ok()
+    }
+}
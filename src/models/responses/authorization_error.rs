@@ -0,0 +1,69 @@
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::Request;
+use rocket::http::Status;
+use rocket::http::hyper::header::{CacheControl, CacheDirective, Pragma};
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use url::Url;
+use web::views;
+
+// See: https://tools.ietf.org/html/rfc6749#section-4.1.2.1
+#[derive(Debug)]
+pub enum AuthorizationErrorResponse {
+    /// The client or redirect URI could not be verified, so the error must be
+    /// shown to the resource owner instead of redirecting anywhere.
+    Invalid(OAuth2ErrorResponse),
+    /// The error is sent back to the client on its (verified) redirect URI.
+    Redirect {
+        redirect_uri: String,
+        error: OAuth2ErrorResponse,
+        state: Option<String>,
+    },
+    /// The resource owner could not be signed in, so the consent page is shown
+    /// again.
+    SignInFailed(views::Page),
+}
+
+impl<'r> Responder<'r> for AuthorizationErrorResponse {
+    fn respond_to(self, req: &Request) -> RocketResult<'r> {
+        let mut response = Response::build();
+        response
+            .header(CacheControl(vec![
+                CacheDirective::NoCache,
+                CacheDirective::NoStore,
+            ]))
+            .header(Pragma::NoCache);
+
+        match self {
+            AuthorizationErrorResponse::Invalid(error) => {
+                response
+                    .merge(views::Page(views::error_page(&error)).respond_to(req)?)
+                    .status(Status::BadRequest);
+            }
+            AuthorizationErrorResponse::SignInFailed(page) => {
+                response
+                    .merge(page.respond_to(req)?)
+                    .status(Status::BadRequest);
+            }
+            AuthorizationErrorResponse::Redirect {
+                redirect_uri,
+                error,
+                state,
+            } => {
+                // The redirect URI was already validated against the client's registered
+                // URIs, so it should always parse.
+                let mut location = Url::parse(&redirect_uri).map_err(|_| Status::InternalServerError)?;
+                location.query_pairs_mut().append_pair("error", error.message());
+                if let Some(state) = state {
+                    location.query_pairs_mut().append_pair("state", &state);
+                }
+
+                response
+                    .raw_header("Location", location.into_string())
+                    .status(Status::Found);
+            }
+        }
+
+        response.ok()
+    }
+}
@@ -0,0 +1,65 @@
+use rocket::Request;
+use rocket::http::Status;
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use serde_json;
+use std::fmt;
+use std::io::Cursor;
+use utils::jwk::JwkSet;
+
+// See: https://tools.ietf.org/html/rfc7591#section-3.2.1 and
+// https://tools.ietf.org/html/rfc7592#section-3
+#[derive(Builder, Serialize, Deserialize)]
+#[builder(setter(into))]
+pub struct ClientInformationResponse {
+    pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id_issued_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<i64>,
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub token_endpoint_auth_method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<JwkSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_subject_dn: Option<String>,
+    /// Only returned when the client registers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_access_token: Option<String>,
+    pub registration_client_uri: String,
+}
+
+impl fmt::Debug for ClientInformationResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ClientInformationResponse {{ client_id: {}, client_secret: [REDACTED], \
+             redirect_uris: {:?}, grant_types: {:?}, token_endpoint_auth_method: {}, \
+             registration_access_token: [REDACTED], registration_client_uri: {} }}",
+            self.client_id,
+            self.redirect_uris,
+            self.grant_types,
+            self.token_endpoint_auth_method,
+            self.registration_client_uri
+        )
+    }
+}
+
+impl<'r> Responder<'r> for ClientInformationResponse {
+    fn respond_to(self, _req: &Request) -> RocketResult<'r> {
+        Response::build()
+            .raw_header("Content-Type", "application/json")
+            .raw_header("Cache-Control", "no-store")
+            .raw_header("Pragma", "no-cache")
+            .status(Status::Ok)
+            .sized_body(Cursor::new(serde_json::to_string(&self).unwrap()))
+            .ok()
+    }
+}
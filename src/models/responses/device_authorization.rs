@@ -0,0 +1,31 @@
+use rocket::Request;
+use rocket::http::Status;
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use serde_json;
+use std::io::Cursor;
+
+// See: https://tools.ietf.org/html/rfc8628#section-3.2
+#[derive(Builder, Debug, Serialize, Deserialize)]
+#[builder(setter(into))]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+impl<'r> Responder<'r> for DeviceAuthorizationResponse {
+    fn respond_to(self, _req: &Request) -> RocketResult<'r> {
+        Response::build()
+            .raw_header("Content-Type", "application/json")
+            .raw_header("Cache-Control", "no-cache, no-store")
+            .raw_header("Pragma", "no-cache")
+            .status(Status::Ok)
+            .sized_body(Cursor::new(serde_json::to_string(&self).unwrap()))
+            .ok()
+    }
+}
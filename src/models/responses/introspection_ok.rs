@@ -1,3 +1,4 @@
+use models::claims::{Actor, Audience, Confirmation};
 use rocket::Request;
 use rocket::http::{ContentType, Status};
 use rocket::http::hyper::header::{CacheControl, CacheDirective, Pragma};
@@ -12,8 +13,19 @@ pub struct IntrospectionOkResponse {
     pub active: bool,
     pub scope: Option<String>,
     pub client_id: Option<String>,
+    /// The resource owner the token was issued on behalf of, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
     pub exp: Option<i64>,
     pub iat: Option<i64>,
+    /// Only present for certificate-bound tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+    /// Only present for tokens obtained through token exchange.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
 }
 
 impl<'r> Responder<'r> for IntrospectionOkResponse {
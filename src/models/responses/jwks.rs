@@ -0,0 +1,21 @@
+use rocket::Request;
+use rocket::http::{ContentType, Status};
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use serde_json;
+use std::io::Cursor;
+use utils::jwk::JwkSet;
+
+// See: https://tools.ietf.org/html/rfc7517#section-5
+#[derive(Debug)]
+pub struct JwkSetResponse(pub JwkSet);
+
+impl<'r> Responder<'r> for JwkSetResponse {
+    fn respond_to(self, _req: &Request) -> RocketResult<'r> {
+        Response::build()
+            .header(ContentType::JSON)
+            .status(Status::Ok)
+            .sized_body(Cursor::new(serde_json::to_string(&self.0).unwrap()))
+            .ok()
+    }
+}
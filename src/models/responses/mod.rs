@@ -1,4 +1,10 @@
 pub mod access_token;
+pub mod authorization_code;
+pub mod authorization_error;
+pub mod client_information;
+pub mod device_authorization;
+pub mod discovery;
 pub mod introspection_err;
 pub mod introspection_ok;
+pub mod jwks;
 pub mod oauth2_error;
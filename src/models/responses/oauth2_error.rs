@@ -13,6 +13,22 @@ pub enum OAuth2ErrorResponse {
     UnauthorizedClient,
     UnsupportedGrantType,
     InvalidScope,
+    AccessDenied,
+    UnsupportedResponseType,
+    /// The bearer token sent to a protected endpoint is missing or invalid.
+    /// See: https://tools.ietf.org/html/rfc6750#section-3.1
+    InvalidToken,
+    /// See: https://tools.ietf.org/html/rfc7591#section-3.2.2
+    InvalidRedirectUri,
+    InvalidClientMetadata,
+    /// Errors a device can receive while polling for tokens.
+    /// See: https://tools.ietf.org/html/rfc8628#section-3.5
+    AuthorizationPending,
+    SlowDown,
+    ExpiredToken,
+    /// The requested audience or resource is not one tokens can be issued
+    /// for. See: https://tools.ietf.org/html/rfc8693#section-2.2.2
+    InvalidTarget,
 }
 
 impl OAuth2ErrorResponse {
@@ -24,6 +40,15 @@ impl OAuth2ErrorResponse {
             OAuth2ErrorResponse::UnauthorizedClient => "unauthorized_client",
             OAuth2ErrorResponse::UnsupportedGrantType => "unsupported_grant_type",
             OAuth2ErrorResponse::InvalidScope => "invalid_scope",
+            OAuth2ErrorResponse::AccessDenied => "access_denied",
+            OAuth2ErrorResponse::UnsupportedResponseType => "unsupported_response_type",
+            OAuth2ErrorResponse::InvalidToken => "invalid_token",
+            OAuth2ErrorResponse::InvalidRedirectUri => "invalid_redirect_uri",
+            OAuth2ErrorResponse::InvalidClientMetadata => "invalid_client_metadata",
+            OAuth2ErrorResponse::AuthorizationPending => "authorization_pending",
+            OAuth2ErrorResponse::SlowDown => "slow_down",
+            OAuth2ErrorResponse::ExpiredToken => "expired_token",
+            OAuth2ErrorResponse::InvalidTarget => "invalid_target",
         }
     }
 }
@@ -45,6 +70,11 @@ impl<'r> Responder<'r> for OAuth2ErrorResponse {
                     .raw_header("WWW-Authenticate", "Basic")
                     .status(Status::Unauthorized);
             }
+            OAuth2ErrorResponse::InvalidToken => {
+                response
+                    .raw_header("WWW-Authenticate", "Bearer error=\"invalid_token\"")
+                    .status(Status::Unauthorized);
+            }
             _ => {
                 response.status(Status::BadRequest);
             }
@@ -4,6 +4,22 @@ table! {
         identifier -> VarChar,
         secret -> VarChar,
         response_type -> VarChar,
+        require_pkce -> Bool,
+        client_name -> Nullable<VarChar>,
+        scope -> Nullable<VarChar>,
+        token_endpoint_auth_method -> VarChar,
+        registration_access_token -> Nullable<VarChar>,
+        jwks -> Nullable<Text>,
+        jwt_secret -> Nullable<VarChar>,
+        tls_client_auth_subject_dn -> Nullable<VarChar>,
+    }
+}
+
+table! {
+    users (id) {
+        id -> Integer,
+        username -> VarChar,
+        password_hash -> VarChar,
     }
 }
 
@@ -22,6 +38,32 @@ table! {
     }
 }
 
+table! {
+    client_grant_types (id) {
+        id -> Integer,
+        client_id -> Integer,
+        grant_id -> Integer,
+    }
+}
+
+table! {
+    client_assertions (id) {
+        id -> Integer,
+        client_id -> Integer,
+        jti -> VarChar,
+        expires_at -> Timestamp,
+    }
+}
+
+table! {
+    bearer_assertions (id) {
+        id -> Integer,
+        issuer -> VarChar,
+        jti -> VarChar,
+        expires_at -> Timestamp,
+    }
+}
+
 table! {
     access_tokens (id) {
         id -> Integer,
@@ -31,6 +73,13 @@ table! {
         scope -> VarChar,
         issued_at -> Timestamp,
         expires_at -> Timestamp,
+        refresh_token_id -> Nullable<Integer>,
+        revoked_at -> Nullable<Timestamp>,
+        cert_thumbprint -> Nullable<VarChar>,
+        user_id -> Nullable<Integer>,
+        audience -> Nullable<VarChar>,
+        act -> Nullable<Text>,
+        subject_token_id -> Nullable<Integer>,
     }
 }
 
@@ -42,6 +91,10 @@ table! {
         scope -> VarChar,
         issued_at -> Timestamp,
         expires_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        user_id -> Nullable<Integer>,
+        family_id -> Nullable<Integer>,
+        rotated_at -> Nullable<Timestamp>,
     }
 }
 
@@ -54,5 +107,22 @@ table!{
         expires_at -> Timestamp,
         redirect_uri -> VarChar,
         user_id -> Nullable<Integer>,
+        code_challenge -> Nullable<VarChar>,
+        code_challenge_method -> Nullable<VarChar>,
+    }
+}
+
+table! {
+    device_codes (id) {
+        id -> Integer,
+        client_id -> Integer,
+        device_code -> VarChar,
+        user_code -> VarChar,
+        scope -> VarChar,
+        expires_at -> Timestamp,
+        poll_interval -> Integer,
+        last_polled_at -> Nullable<Timestamp>,
+        user_id -> Nullable<Integer>,
+        status -> VarChar,
     }
 }
@@ -0,0 +1,1078 @@
+//! An in-memory store, for tests and ephemeral development environments.
+//!
+//! Everything lives in the process and is lost on shutdown. Clients and users
+//! are seeded from a TOML or JSON fixture (see `extras/test-clients.toml`),
+//! and the grant types are the same ones the initial migration creates.
+
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use config::{Config, File as ConfigFile};
+use models::db::*;
+use std::sync::{Arc, Mutex, MutexGuard};
+use store::{Store, StoreError, StoreProvider, StoreResult, UserStore};
+use uuid::Uuid;
+
+/// The grant types seeded by the migrations.
+const GRANT_TYPES: &[&str] = &[
+    "authorization_code",
+    "token",
+    "password",
+    "client_credentials",
+    "refresh_token",
+    "urn:ietf:params:oauth:grant-type:device_code",
+    "urn:ietf:params:oauth:grant-type:jwt-bearer",
+    "urn:ietf:params:oauth:grant-type:token-exchange",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct Fixture {
+    #[serde(default)]
+    pub clients: Vec<FixtureClient>,
+    #[serde(default)]
+    pub users: Vec<FixtureUser>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureClient {
+    pub identifier: String,
+    /// The bcrypt hash of the client secret.
+    pub secret: String,
+    pub response_type: String,
+    #[serde(default)]
+    pub require_pkce: bool,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// The names of the grant types the client may use.
+    #[serde(default)]
+    pub grant_types: Vec<String>,
+    pub client_name: Option<String>,
+    pub scope: Option<String>,
+    #[serde(default = "default_auth_method")]
+    pub token_endpoint_auth_method: String,
+    /// A JWK Set, as JSON, for clients using `private_key_jwt`.
+    pub jwks: Option<String>,
+    /// The plaintext key of clients using `client_secret_jwt`.
+    pub jwt_secret: Option<String>,
+    /// The certificate subject DN of clients using `tls_client_auth`.
+    pub tls_client_auth_subject_dn: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureUser {
+    pub username: String,
+    /// The bcrypt or argon2 hash of the user's password.
+    pub password_hash: String,
+}
+
+fn default_auth_method() -> String {
+    "client_secret_basic".to_owned()
+}
+
+impl Fixture {
+    /// Loads a fixture file. The format is picked from the file extension.
+    pub fn load(path: &str) -> Result<Fixture, StoreError> {
+        let mut config_data = Config::new();
+        config_data
+            .merge(ConfigFile::with_name(path))
+            .map_err(|e| StoreError(e.to_string()))?;
+        config_data
+            .try_into()
+            .map_err(|e| StoreError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Data {
+    clients: Vec<Client>,
+    users: Vec<User>,
+    client_redirect_uris: Vec<ClientRedirectUri>,
+    client_grant_types: Vec<ClientGrantType>,
+    client_assertions: Vec<ClientAssertion>,
+    bearer_assertions: Vec<BearerAssertion>,
+    grant_types: Vec<GrantType>,
+    access_tokens: Vec<AccessToken>,
+    refresh_tokens: Vec<RefreshToken>,
+    auth_codes: Vec<AuthCode>,
+    device_codes: Vec<DeviceCode>,
+    last_id: i32,
+}
+
+impl Data {
+    /// Hands out ids the same way a SERIAL column would. A single sequence is
+    /// shared between tables, which is fine as ids are never compared across
+    /// tables.
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+}
+
+pub struct MemoryStoreProvider {
+    data: Arc<Mutex<Data>>,
+}
+
+impl MemoryStoreProvider {
+    pub fn new() -> MemoryStoreProvider {
+        let mut data = Data::default();
+        for name in GRANT_TYPES {
+            let id = data.next_id();
+            data.grant_types.push(GrantType {
+                id,
+                name: (*name).to_owned(),
+            });
+        }
+
+        MemoryStoreProvider {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    /// Adds the clients and users from a fixture to the store.
+    pub fn seed(&self, fixture: &Fixture) -> Result<(), StoreError> {
+        let mut data = lock(&self.data)?;
+        for fixture_client in &fixture.clients {
+            if data.clients
+                .iter()
+                .any(|c| c.identifier == fixture_client.identifier)
+            {
+                return Err(StoreError(format!(
+                    "duplicate client identifier [{}]",
+                    fixture_client.identifier
+                )));
+            }
+
+            let client_id = data.next_id();
+            data.clients.push(Client {
+                id: client_id,
+                identifier: fixture_client.identifier.clone(),
+                secret: fixture_client.secret.clone(),
+                response_type: fixture_client.response_type.clone(),
+                require_pkce: fixture_client.require_pkce,
+                client_name: fixture_client.client_name.clone(),
+                scope: fixture_client.scope.clone(),
+                token_endpoint_auth_method: fixture_client.token_endpoint_auth_method.clone(),
+                registration_access_token: None,
+                jwks: fixture_client.jwks.clone(),
+                jwt_secret: fixture_client.jwt_secret.clone(),
+                tls_client_auth_subject_dn: fixture_client.tls_client_auth_subject_dn.clone(),
+            });
+
+            for redirect_uri in &fixture_client.redirect_uris {
+                let id = data.next_id();
+                data.client_redirect_uris.push(ClientRedirectUri {
+                    id,
+                    client_id,
+                    redirect_uri: redirect_uri.clone(),
+                });
+            }
+
+            for name in &fixture_client.grant_types {
+                let grant_id = data.grant_types
+                    .iter()
+                    .find(|g| &g.name == name)
+                    .map(|g| g.id)
+                    .ok_or_else(|| StoreError(format!("unknown grant type [{}]", name)))?;
+                let id = data.next_id();
+                data.client_grant_types.push(ClientGrantType {
+                    id,
+                    client_id,
+                    grant_id,
+                });
+            }
+        }
+
+        for fixture_user in &fixture.users {
+            if data.users
+                .iter()
+                .any(|u| u.username == fixture_user.username)
+            {
+                return Err(StoreError(format!(
+                    "duplicate username [{}]",
+                    fixture_user.username
+                )));
+            }
+
+            let id = data.next_id();
+            data.users.push(User {
+                id,
+                username: fixture_user.username.clone(),
+                password_hash: fixture_user.password_hash.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl StoreProvider for MemoryStoreProvider {
+    fn get(&self) -> StoreResult<Box<dyn Store>> {
+        Ok(Box::new(MemoryStore {
+            data: Arc::clone(&self.data),
+        }))
+    }
+
+    /// There is no schema to migrate.
+    fn migrate(&self) -> StoreResult<()> {
+        Ok(())
+    }
+}
+
+fn lock(data: &Mutex<Data>) -> StoreResult<MutexGuard<Data>> {
+    data.lock()
+        .map_err(|_| StoreError("memory store lock poisoned".to_owned()))
+}
+
+pub struct MemoryStore {
+    data: Arc<Mutex<Data>>,
+}
+
+impl Store for MemoryStore {
+    fn user_store(&self) -> &dyn UserStore {
+        self
+    }
+
+    fn find_client(&self, identifier: &str) -> StoreResult<Option<Client>> {
+        Ok(lock(&self.data)?
+            .clients
+            .iter()
+            .find(|c| c.identifier == identifier)
+            .cloned())
+    }
+
+    fn find_client_by_id(&self, id: i32) -> StoreResult<Option<Client>> {
+        Ok(lock(&self.data)?
+            .clients
+            .iter()
+            .find(|c| c.id == id)
+            .cloned())
+    }
+
+    fn clients(&self) -> StoreResult<Vec<Client>> {
+        Ok(lock(&self.data)?.clients.clone())
+    }
+
+    fn insert_client(&self, client: &NewClient) -> StoreResult<Client> {
+        let mut data = lock(&self.data)?;
+        if data.clients.iter().any(|c| c.identifier == client.identifier) {
+            return Err(StoreError(format!(
+                "duplicate client identifier [{}]",
+                client.identifier
+            )));
+        }
+
+        let inserted = Client {
+            id: data.next_id(),
+            identifier: client.identifier.clone(),
+            secret: client.secret.clone(),
+            response_type: client.response_type.clone(),
+            require_pkce: client.require_pkce,
+            client_name: client.client_name.clone(),
+            scope: client.scope.clone(),
+            token_endpoint_auth_method: client.token_endpoint_auth_method.clone(),
+            registration_access_token: client.registration_access_token.clone(),
+            jwks: client.jwks.clone(),
+            jwt_secret: client.jwt_secret.clone(),
+            tls_client_auth_subject_dn: client.tls_client_auth_subject_dn.clone(),
+        };
+        data.clients.push(inserted.clone());
+
+        Ok(inserted)
+    }
+
+    fn update_client(&self, client: &Client) -> StoreResult<Client> {
+        let mut data = lock(&self.data)?;
+        let stored = data.clients
+            .iter_mut()
+            .find(|c| c.id == client.id)
+            .ok_or_else(|| StoreError(format!("unknown client [{}]", client.identifier)))?;
+
+        stored.secret = client.secret.clone();
+        stored.response_type = client.response_type.clone();
+        stored.require_pkce = client.require_pkce;
+        stored.client_name = client.client_name.clone();
+        stored.scope = client.scope.clone();
+        stored.token_endpoint_auth_method = client.token_endpoint_auth_method.clone();
+        stored.registration_access_token = client.registration_access_token.clone();
+        stored.jwks = client.jwks.clone();
+        stored.jwt_secret = client.jwt_secret.clone();
+        stored.tls_client_auth_subject_dn = client.tls_client_auth_subject_dn.clone();
+
+        Ok(stored.clone())
+    }
+
+    fn delete_client(&self, client: &Client) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let before = data.clients.len();
+
+        data.clients.retain(|c| c.id != client.id);
+        data.client_redirect_uris.retain(|u| u.client_id != client.id);
+        data.client_grant_types.retain(|g| g.client_id != client.id);
+        data.client_assertions.retain(|a| a.client_id != client.id);
+
+        // The tokens other clients got in exchange for this client's tokens
+        // are linked to its refresh tokens, and go too. Tokens exchanged for
+        // deleted ones lose their link, as ON DELETE SET NULL would have it.
+        let refresh_token_ids: Vec<i32> = data.refresh_tokens
+            .iter()
+            .filter(|t| t.client_id == client.id)
+            .map(|t| t.id)
+            .collect();
+        data.access_tokens.retain(|t| {
+            t.client_id != client.id
+                && !t.refresh_token_id.map_or(false, |id| refresh_token_ids.contains(&id))
+        });
+        let access_token_ids: Vec<i32> = data.access_tokens.iter().map(|t| t.id).collect();
+        for access_token in data.access_tokens.iter_mut() {
+            if access_token.subject_token_id.map_or(false, |id| !access_token_ids.contains(&id)) {
+                access_token.subject_token_id = None;
+            }
+        }
+        data.refresh_tokens.retain(|t| t.client_id != client.id);
+        data.auth_codes.retain(|c| c.client_id != client.id);
+        data.device_codes.retain(|c| c.client_id != client.id);
+
+        Ok(data.clients.len() < before)
+    }
+
+    fn client_grant_types(&self, client: &Client) -> StoreResult<Vec<GrantType>> {
+        let data = lock(&self.data)?;
+        Ok(data.grant_types
+            .iter()
+            .filter(|g| {
+                data.client_grant_types
+                    .iter()
+                    .any(|c| c.client_id == client.id && c.grant_id == g.id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn set_client_grant_types(
+        &self,
+        client: &Client,
+        grant_types: &[GrantType],
+    ) -> StoreResult<()> {
+        let mut data = lock(&self.data)?;
+        data.client_grant_types.retain(|g| g.client_id != client.id);
+        for grant_type in grant_types {
+            let id = data.next_id();
+            data.client_grant_types.push(ClientGrantType {
+                id,
+                client_id: client.id,
+                grant_id: grant_type.id,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn redirect_uris(&self, client: &Client) -> StoreResult<Vec<ClientRedirectUri>> {
+        Ok(lock(&self.data)?
+            .client_redirect_uris
+            .iter()
+            .filter(|u| u.client_id == client.id)
+            .cloned()
+            .collect())
+    }
+
+    fn insert_redirect_uri(&self, uri: &NewClientRedirectUri) -> StoreResult<ClientRedirectUri> {
+        let mut data = lock(&self.data)?;
+        let inserted = ClientRedirectUri {
+            id: data.next_id(),
+            client_id: uri.client_id,
+            redirect_uri: uri.redirect_uri.clone(),
+        };
+        data.client_redirect_uris.push(inserted.clone());
+
+        Ok(inserted)
+    }
+
+    fn delete_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let before = data.client_redirect_uris.len();
+        data.client_redirect_uris
+            .retain(|u| !(u.client_id == client.id && u.redirect_uri == redirect_uri));
+
+        Ok(data.client_redirect_uris.len() < before)
+    }
+
+    fn has_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        Ok(lock(&self.data)?
+            .client_redirect_uris
+            .iter()
+            .any(|u| u.client_id == client.id && u.redirect_uri == redirect_uri))
+    }
+
+    fn record_client_assertion(
+        &self,
+        client: &Client,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+
+        data.client_assertions
+            .retain(|a| a.client_id != client.id || a.expires_at >= now);
+        if data.client_assertions
+            .iter()
+            .any(|a| a.client_id == client.id && a.jti == jti)
+        {
+            return Ok(false);
+        }
+
+        let id = data.next_id();
+        data.client_assertions.push(ClientAssertion {
+            id,
+            client_id: client.id,
+            jti: jti.to_owned(),
+            expires_at,
+        });
+
+        Ok(true)
+    }
+
+    fn record_bearer_assertion(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+
+        data.bearer_assertions
+            .retain(|a| a.issuer != issuer || a.expires_at >= now);
+        if data.bearer_assertions
+            .iter()
+            .any(|a| a.issuer == issuer && a.jti == jti)
+        {
+            return Ok(false);
+        }
+
+        let id = data.next_id();
+        data.bearer_assertions.push(BearerAssertion {
+            id,
+            issuer: issuer.to_owned(),
+            jti: jti.to_owned(),
+            expires_at,
+        });
+
+        Ok(true)
+    }
+
+    fn find_grant_type(&self, name: &str) -> StoreResult<Option<GrantType>> {
+        Ok(lock(&self.data)?
+            .grant_types
+            .iter()
+            .find(|g| g.name == name)
+            .cloned())
+    }
+
+    fn grant_types(&self) -> StoreResult<Vec<GrantType>> {
+        Ok(lock(&self.data)?.grant_types.clone())
+    }
+
+    fn insert_access_token(&self, token: &NewAccessToken) -> StoreResult<AccessToken> {
+        let mut data = lock(&self.data)?;
+        let access_token = AccessToken {
+            id: data.next_id(),
+            token: token.token,
+            client_id: token.client_id,
+            grant_id: token.grant_id,
+            scope: token.scope.clone(),
+            issued_at: token.issued_at,
+            expires_at: token.expires_at,
+            refresh_token_id: token.refresh_token_id,
+            revoked_at: None,
+            cert_thumbprint: token.cert_thumbprint.clone(),
+            user_id: token.user_id,
+            audience: token.audience.clone(),
+            act: token.act.clone(),
+            subject_token_id: token.subject_token_id,
+        };
+        data.access_tokens.push(access_token.clone());
+
+        Ok(access_token)
+    }
+
+    fn find_access_token(&self, token: &Uuid) -> StoreResult<Option<AccessToken>> {
+        Ok(lock(&self.data)?
+            .access_tokens
+            .iter()
+            .find(|t| t.token == *token)
+            .cloned())
+    }
+
+    fn revoke_access_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+
+        let id = match data.access_tokens.iter().find(|t| {
+            t.token == *token && t.client_id == client.id && t.revoked_at.is_none()
+        }) {
+            Some(t) => t.id,
+            None => return Ok(false),
+        };
+
+        // Tokens exchanged for a revoked token go with it, and so do the
+        // tokens exchanged for those
+        let mut ids = vec![id];
+        while !ids.is_empty() {
+            for access_token in data.access_tokens
+                .iter_mut()
+                .filter(|t| ids.contains(&t.id) && t.revoked_at.is_none())
+            {
+                access_token.revoked_at = Some(now);
+            }
+            ids = data.access_tokens
+                .iter()
+                .filter(|t| {
+                    t.subject_token_id.map_or(false, |id| ids.contains(&id)) && t.revoked_at.is_none()
+                })
+                .map(|t| t.id)
+                .collect();
+        }
+
+        Ok(true)
+    }
+
+    fn insert_refresh_token(&self, token: &NewRefreshToken) -> StoreResult<RefreshToken> {
+        let mut data = lock(&self.data)?;
+        let refresh_token = RefreshToken {
+            id: data.next_id(),
+            token: token.token,
+            client_id: token.client_id,
+            scope: token.scope.clone(),
+            issued_at: token.issued_at,
+            expires_at: token.expires_at,
+            revoked_at: None,
+            user_id: token.user_id,
+            family_id: token.family_id,
+            rotated_at: None,
+        };
+        data.refresh_tokens.push(refresh_token.clone());
+
+        Ok(refresh_token)
+    }
+
+    fn find_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        Ok(lock(&self.data)?
+            .refresh_tokens
+            .iter()
+            .filter(|t| {
+                t.token == *token && t.client_id == client.id && t.revoked_at.is_none()
+                    && t.rotated_at.is_none()
+            })
+            .max_by_key(|t| t.issued_at)
+            .cloned())
+    }
+
+    fn find_rotated_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        Ok(lock(&self.data)?
+            .refresh_tokens
+            .iter()
+            .find(|t| t.token == *token && t.client_id == client.id && t.rotated_at.is_some())
+            .cloned())
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        replacement: &NewRefreshToken,
+    ) -> StoreResult<Option<RefreshToken>> {
+        {
+            let mut data = lock(&self.data)?;
+            match data.refresh_tokens.iter_mut().find(|t| {
+                t.id == token.id && t.revoked_at.is_none() && t.rotated_at.is_none()
+            }) {
+                Some(t) => t.rotated_at = Some(Utc::now().naive_utc()),
+                None => return Ok(None),
+            }
+        }
+
+        self.insert_refresh_token(replacement).map(Some)
+    }
+
+    fn revoke_refresh_token_family(&self, token: &RefreshToken) -> StoreResult<()> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+        let family = token.family();
+
+        let mut ids = Vec::new();
+        for refresh_token in data.refresh_tokens
+            .iter_mut()
+            .filter(|t| t.family() == family)
+        {
+            ids.push(refresh_token.id);
+            if refresh_token.revoked_at.is_none() {
+                refresh_token.revoked_at = Some(now);
+            }
+        }
+        for access_token in data.access_tokens.iter_mut().filter(|t| {
+            t.refresh_token_id.map_or(false, |id| ids.contains(&id)) && t.revoked_at.is_none()
+        }) {
+            access_token.revoked_at = Some(now);
+        }
+
+        Ok(())
+    }
+
+    fn revoke_refresh_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+
+        let refresh_token_id = match data.refresh_tokens.iter_mut().find(|t| {
+            t.token == *token && t.client_id == client.id && t.revoked_at.is_none()
+        }) {
+            Some(t) => {
+                t.revoked_at = Some(now);
+                t.id
+            }
+            None => return Ok(false),
+        };
+
+        for access_token in data.access_tokens.iter_mut().filter(|t| {
+            t.refresh_token_id == Some(refresh_token_id) && t.revoked_at.is_none()
+        }) {
+            access_token.revoked_at = Some(now);
+        }
+
+        Ok(true)
+    }
+
+    fn insert_auth_code(&self, code: &NewAuthCode) -> StoreResult<AuthCode> {
+        let mut data = lock(&self.data)?;
+        let auth_code = AuthCode {
+            id: data.next_id(),
+            client_id: code.client_id,
+            name: code.name.clone(),
+            scope: code.scope.clone(),
+            expires_at: code.expires_at,
+            redirect_uri: code.redirect_uri.clone(),
+            user_id: code.user_id,
+            code_challenge: code.code_challenge.clone(),
+            code_challenge_method: code.code_challenge_method.clone(),
+        };
+        data.auth_codes.push(auth_code.clone());
+
+        Ok(auth_code)
+    }
+
+    fn take_auth_code(&self, client: &Client, code: &str) -> StoreResult<Option<AuthCode>> {
+        let mut data = lock(&self.data)?;
+        let position = data.auth_codes
+            .iter()
+            .position(|c| c.name == code && c.client_id == client.id);
+
+        Ok(position.map(|i| data.auth_codes.remove(i)))
+    }
+
+    fn insert_device_code(&self, code: &NewDeviceCode) -> StoreResult<DeviceCode> {
+        let mut data = lock(&self.data)?;
+        if data.device_codes
+            .iter()
+            .any(|c| c.user_code == code.user_code)
+        {
+            return Err(StoreError(format!("duplicate user code [{}]", code.user_code)));
+        }
+
+        let device_code = DeviceCode {
+            id: data.next_id(),
+            client_id: code.client_id,
+            device_code: code.device_code.clone(),
+            user_code: code.user_code.clone(),
+            scope: code.scope.clone(),
+            expires_at: code.expires_at,
+            poll_interval: code.poll_interval,
+            last_polled_at: None,
+            user_id: None,
+            status: DeviceCode::PENDING.to_owned(),
+        };
+        data.device_codes.push(device_code.clone());
+
+        Ok(device_code)
+    }
+
+    fn find_device_code(&self, user_code: &str) -> StoreResult<Option<DeviceCode>> {
+        Ok(lock(&self.data)?
+            .device_codes
+            .iter()
+            .find(|c| c.user_code == user_code)
+            .cloned())
+    }
+
+    fn decide_device_code(
+        &self,
+        code: &DeviceCode,
+        user: &User,
+        approved: bool,
+    ) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let stored = match data.device_codes
+            .iter_mut()
+            .find(|c| c.id == code.id && c.status == DeviceCode::PENDING)
+        {
+            Some(c) => c,
+            None => return Ok(false),
+        };
+
+        stored.status = if approved {
+            DeviceCode::APPROVED
+        } else {
+            DeviceCode::DENIED
+        }.to_owned();
+        stored.user_id = Some(user.id);
+
+        Ok(true)
+    }
+
+    fn poll_device_code(
+        &self,
+        client: &Client,
+        device_code: &str,
+    ) -> StoreResult<Option<DeviceCode>> {
+        let mut data = lock(&self.data)?;
+        let now = Utc::now().naive_utc();
+
+        Ok(data.device_codes
+            .iter_mut()
+            .find(|c| c.device_code == device_code && c.client_id == client.id)
+            .map(|c| {
+                let polled = c.clone();
+                c.last_polled_at = Some(now);
+                polled
+            }))
+    }
+
+    fn slow_down_device_code(&self, code: &DeviceCode, seconds: i32) -> StoreResult<()> {
+        let mut data = lock(&self.data)?;
+        if let Some(stored) = data.device_codes.iter_mut().find(|c| c.id == code.id) {
+            stored.poll_interval += seconds;
+        }
+
+        Ok(())
+    }
+
+    fn take_device_code(&self, code: &DeviceCode) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let before = data.device_codes.len();
+
+        data.device_codes.retain(|c| c.id != code.id);
+
+        Ok(data.device_codes.len() < before)
+    }
+}
+
+impl UserStore for MemoryStore {
+    fn find_user(&self, username: &str) -> StoreResult<Option<User>> {
+        Ok(lock(&self.data)?
+            .users
+            .iter()
+            .find(|u| u.username == username)
+            .cloned())
+    }
+
+    fn find_user_by_id(&self, id: i32) -> StoreResult<Option<User>> {
+        Ok(lock(&self.data)?
+            .users
+            .iter()
+            .find(|u| u.id == id)
+            .cloned())
+    }
+
+    fn users(&self) -> StoreResult<Vec<User>> {
+        Ok(lock(&self.data)?.users.clone())
+    }
+
+    fn insert_user(&self, user: &NewUser) -> StoreResult<User> {
+        let mut data = lock(&self.data)?;
+        if data.users.iter().any(|u| u.username == user.username) {
+            return Err(StoreError(format!("duplicate username [{}]", user.username)));
+        }
+
+        let inserted = User {
+            id: data.next_id(),
+            username: user.username.clone(),
+            password_hash: user.password_hash.clone(),
+        };
+        data.users.push(inserted.clone());
+
+        Ok(inserted)
+    }
+
+    fn update_user(&self, user: &User) -> StoreResult<User> {
+        let mut data = lock(&self.data)?;
+        let stored = data.users
+            .iter_mut()
+            .find(|u| u.id == user.id)
+            .ok_or_else(|| StoreError(format!("unknown user [{}]", user.username)))?;
+
+        stored.password_hash = user.password_hash.clone();
+
+        Ok(stored.clone())
+    }
+
+    fn delete_user(&self, user: &User) -> StoreResult<bool> {
+        let mut data = lock(&self.data)?;
+        let before = data.users.len();
+
+        data.users.retain(|u| u.id != user.id);
+        data.access_tokens.retain(|t| t.user_id != Some(user.id));
+        data.refresh_tokens.retain(|t| t.user_id != Some(user.id));
+        data.auth_codes.retain(|c| c.user_id != Some(user.id));
+        data.device_codes.retain(|c| c.user_id != Some(user.id));
+
+        Ok(data.users.len() < before)
+    }
+}
+
+/// Fixtures shared by the tests of the modules working against a store.
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::sync::Barrier;
+    use std::thread;
+
+    /// A confidential client that may use the refresh_token grant.
+    pub fn fixture_client(identifier: &str) -> FixtureClient {
+        FixtureClient {
+            identifier: identifier.to_owned(),
+            secret: String::new(),
+            response_type: "confidential".to_owned(),
+            require_pkce: false,
+            redirect_uris: vec![],
+            grant_types: vec!["refresh_token".to_owned()],
+            client_name: None,
+            scope: None,
+            token_endpoint_auth_method: default_auth_method(),
+            jwks: None,
+            jwt_secret: None,
+            tls_client_auth_subject_dn: None,
+        }
+    }
+
+    /// A store holding the `first` and `second` clients.
+    pub fn provider() -> MemoryStoreProvider {
+        seeded(vec![fixture_client("first"), fixture_client("second")])
+    }
+
+    /// A store holding the given clients.
+    pub fn seeded(clients: Vec<FixtureClient>) -> MemoryStoreProvider {
+        let provider = MemoryStoreProvider::new();
+        provider
+            .seed(&Fixture {
+                clients,
+                users: vec![],
+            })
+            .unwrap();
+        provider
+    }
+
+    pub fn client(store: &dyn Store, identifier: &str) -> Client {
+        store.find_client(identifier).unwrap().unwrap()
+    }
+
+    pub fn new_refresh_token(client: &Client, family_id: Option<i32>) -> NewRefreshToken {
+        NewRefreshToken {
+            token: Uuid::new_v4(),
+            client_id: client.id,
+            scope: "read".to_owned(),
+            issued_at: Utc::now().naive_utc(),
+            expires_at: None,
+            user_id: None,
+            family_id,
+        }
+    }
+
+    fn new_access_token(store: &dyn Store, client: &Client, refresh_token: &RefreshToken) -> NewAccessToken {
+        let now = Utc::now().naive_utc();
+        NewAccessToken {
+            token: Uuid::new_v4(),
+            client_id: client.id,
+            grant_id: store.find_grant_type("refresh_token").unwrap().unwrap().id,
+            scope: "read".to_owned(),
+            issued_at: now,
+            expires_at: now + Duration::seconds(3600),
+            refresh_token_id: Some(refresh_token.id),
+            cert_thumbprint: None,
+            user_id: None,
+            audience: None,
+            act: None,
+            subject_token_id: None,
+        }
+    }
+
+    #[test]
+    fn rotation_replaces_the_token_within_its_family() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let original = store
+            .insert_refresh_token(&new_refresh_token(&client, None))
+            .unwrap();
+
+        let replacement = store
+            .rotate_refresh_token(&original, &new_refresh_token(&client, Some(original.family())))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(replacement.family(), original.id);
+        assert!(store.find_refresh_token(&client, &original.token).unwrap().is_none());
+        assert!(store.find_rotated_refresh_token(&client, &original.token).unwrap().is_some());
+        assert!(store.find_refresh_token(&client, &replacement.token).unwrap().is_some());
+    }
+
+    #[test]
+    fn a_token_can_only_be_rotated_once() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let original = store
+            .insert_refresh_token(&new_refresh_token(&client, None))
+            .unwrap();
+
+        let first = store
+            .rotate_refresh_token(&original, &new_refresh_token(&client, Some(original.family())))
+            .unwrap();
+        let second = store
+            .rotate_refresh_token(&original, &new_refresh_token(&client, Some(original.family())))
+            .unwrap();
+
+        assert!(first.is_some());
+        assert!(second.is_none());
+    }
+
+    #[test]
+    fn concurrent_rotations_only_let_one_through() {
+        let provider = Arc::new(provider());
+        let store = provider.get().unwrap();
+        let client = client(&*store, "first");
+        let original = store
+            .insert_refresh_token(&new_refresh_token(&client, None))
+            .unwrap();
+
+        // Both requests look the token up before either rotates it
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let provider = Arc::clone(&provider);
+                let barrier = Arc::clone(&barrier);
+                let original = original.clone();
+                let replacement = new_refresh_token(&client, Some(original.family()));
+                thread::spawn(move || {
+                    let store = provider.get().unwrap();
+                    barrier.wait();
+                    store
+                        .rotate_refresh_token(&original, &replacement)
+                        .unwrap()
+                        .is_some()
+                })
+            })
+            .collect();
+        let rotated = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|rotated| *rotated)
+            .count();
+
+        assert_eq!(rotated, 1);
+    }
+
+    #[test]
+    fn revoking_a_family_revokes_its_tokens_and_their_access_tokens() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let original = store
+            .insert_refresh_token(&new_refresh_token(&client, None))
+            .unwrap();
+        let replacement = store
+            .rotate_refresh_token(&original, &new_refresh_token(&client, Some(original.family())))
+            .unwrap()
+            .unwrap();
+        let access_token = store
+            .insert_access_token(&new_access_token(&*store, &client, &replacement))
+            .unwrap();
+        let unrelated = store
+            .insert_refresh_token(&new_refresh_token(&client, None))
+            .unwrap();
+
+        // A replayed token is revoked along with everything rotated from it
+        store.revoke_refresh_token_family(&original).unwrap();
+
+        assert!(store.find_refresh_token(&client, &replacement.token).unwrap().is_none());
+        let access_token = store.find_access_token(&access_token.token).unwrap().unwrap();
+        assert!(access_token.revoked_at.is_some());
+        assert!(store.find_refresh_token(&client, &unrelated.token).unwrap().is_some());
+    }
+
+    #[test]
+    fn deleting_a_client_deletes_the_tokens_exchanged_for_its_tokens() {
+        let store = provider().get().unwrap();
+        let subject_client = client(&*store, "first");
+        let exchanging_client = client(&*store, "second");
+        let refresh_token = store
+            .insert_refresh_token(&new_refresh_token(&subject_client, None))
+            .unwrap();
+        let subject = store
+            .insert_access_token(&new_access_token(&*store, &subject_client, &refresh_token))
+            .unwrap();
+
+        // Exchanged tokens are issued to the exchanging client, but stay
+        // linked to the subject's refresh token
+        let mut exchanged = new_access_token(&*store, &exchanging_client, &refresh_token);
+        exchanged.subject_token_id = Some(subject.id);
+        let exchanged = store.insert_access_token(&exchanged).unwrap();
+        let mut unlinked = new_access_token(&*store, &exchanging_client, &refresh_token);
+        unlinked.refresh_token_id = None;
+        unlinked.subject_token_id = Some(subject.id);
+        let unlinked = store.insert_access_token(&unlinked).unwrap();
+
+        assert!(store.delete_client(&subject_client).unwrap());
+
+        assert!(store.find_access_token(&subject.token).unwrap().is_none());
+        assert!(store.find_access_token(&exchanged.token).unwrap().is_none());
+        let unlinked = store.find_access_token(&unlinked.token).unwrap().unwrap();
+        assert_eq!(unlinked.subject_token_id, None);
+        assert!(store.find_client("second").unwrap().is_some());
+    }
+
+    #[test]
+    fn client_assertions_are_only_accepted_once_per_client() {
+        let store = provider().get().unwrap();
+        let first = client(&*store, "first");
+        let second = client(&*store, "second");
+        let expires_at = Utc::now().naive_utc() + Duration::seconds(60);
+
+        assert!(store.record_client_assertion(&first, "jti", expires_at).unwrap());
+        assert!(!store.record_client_assertion(&first, "jti", expires_at).unwrap());
+        assert!(store.record_client_assertion(&second, "jti", expires_at).unwrap());
+    }
+
+    #[test]
+    fn expired_client_assertions_are_forgotten() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let expired = Utc::now().naive_utc() - Duration::seconds(60);
+
+        assert!(store.record_client_assertion(&client, "jti", expired).unwrap());
+        assert!(store.record_client_assertion(&client, "jti", expired).unwrap());
+    }
+
+    #[test]
+    fn bearer_assertions_are_only_accepted_once_per_issuer() {
+        let store = provider().get().unwrap();
+        let expires_at = Utc::now().naive_utc() + Duration::seconds(60);
+
+        assert!(store.record_bearer_assertion("https://a.example", "jti", expires_at).unwrap());
+        assert!(!store.record_bearer_assertion("https://a.example", "jti", expires_at).unwrap());
+        assert!(store.record_bearer_assertion("https://b.example", "jti", expires_at).unwrap());
+    }
+}
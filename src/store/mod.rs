@@ -0,0 +1,245 @@
+//! The store module abstracts the persistence of clients, grant types, tokens
+//! and authorization codes away from the grant logic.
+//!
+//! Handlers grab a `Store` from the global `StoreProvider` for the duration of
+//! a request, and pass it down to the functions in `utils`. Each backend lives
+//! in its own submodule.
+//!
+//! Resource owners are kept apart, behind the `UserStore` a `Store` hands out,
+//! so that users can live somewhere other than the provider's own database.
+
+#[cfg(feature = "memory-store")]
+pub mod memory;
+pub mod postgres;
+#[cfg(feature = "sqlite-store")]
+pub mod sqlite;
+
+use chrono::NaiveDateTime;
+use models::configuration::{DatabaseSettings, StoreBackend};
+use models::db::*;
+use std::fmt;
+use utils;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct StoreError(pub String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "store error: {}", self.0)
+    }
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+pub trait Store {
+    /// The store resource owners are authenticated against.
+    fn user_store(&self) -> &dyn UserStore;
+
+    /// Finds a client by its public identifier.
+    fn find_client(&self, identifier: &str) -> StoreResult<Option<Client>>;
+
+    /// Finds a client by its id, such as the one a token was issued to.
+    fn find_client_by_id(&self, id: i32) -> StoreResult<Option<Client>>;
+
+    /// Lists every client, in id order.
+    fn clients(&self) -> StoreResult<Vec<Client>>;
+
+    fn insert_client(&self, client: &NewClient) -> StoreResult<Client>;
+
+    /// Saves every field of an existing client, except its identifier.
+    fn update_client(&self, client: &Client) -> StoreResult<Client>;
+
+    /// Deletes a client, along with its redirect URIs, grant types, used
+    /// assertions and every token, authorization code and device code issued
+    /// to it. Returns whether a client was deleted.
+    fn delete_client(&self, client: &Client) -> StoreResult<bool>;
+
+    /// Lists the grant types the client registered to use, in id order.
+    fn client_grant_types(&self, client: &Client) -> StoreResult<Vec<GrantType>>;
+
+    /// Replaces the grant types the client registered to use.
+    fn set_client_grant_types(&self, client: &Client, grant_types: &[GrantType])
+        -> StoreResult<()>;
+
+    /// Lists the redirect URIs registered for the client.
+    fn redirect_uris(&self, client: &Client) -> StoreResult<Vec<ClientRedirectUri>>;
+
+    fn insert_redirect_uri(&self, uri: &NewClientRedirectUri) -> StoreResult<ClientRedirectUri>;
+
+    /// Removes a redirect URI from the client. Returns whether it was
+    /// registered.
+    fn delete_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool>;
+
+    /// Whether the redirect URI is registered for the client.
+    fn has_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool>;
+
+    /// Records the `jti` of an assertion the client authenticated with, until
+    /// the assertion expires. Returns false if the client already used it, in
+    /// which case the assertion is being replayed.
+    fn record_client_assertion(
+        &self,
+        client: &Client,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool>;
+
+    /// Records the `jti` of an assertion exchanged through the JWT bearer
+    /// grant, until the assertion expires. Returns false if an assertion of
+    /// the same issuer with that `jti` was already exchanged, by any client.
+    fn record_bearer_assertion(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool>;
+
+    /// Finds a grant type by name.
+    fn find_grant_type(&self, name: &str) -> StoreResult<Option<GrantType>>;
+
+    /// Lists every known grant type, in id order.
+    fn grant_types(&self) -> StoreResult<Vec<GrantType>>;
+
+    fn insert_access_token(&self, token: &NewAccessToken) -> StoreResult<AccessToken>;
+
+    /// Finds an access token by its value, whether or not it is revoked or
+    /// expired.
+    fn find_access_token(&self, token: &Uuid) -> StoreResult<Option<AccessToken>>;
+
+    /// Revokes an unrevoked access token owned by the client, along with the
+    /// tokens exchanged for it. Returns whether a token was revoked.
+    fn revoke_access_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool>;
+
+    fn insert_refresh_token(&self, token: &NewRefreshToken) -> StoreResult<RefreshToken>;
+
+    /// Finds an unrevoked refresh token owned by the client, that was not
+    /// rotated either.
+    fn find_refresh_token(&self, client: &Client, token: &Uuid)
+        -> StoreResult<Option<RefreshToken>>;
+
+    /// Finds a refresh token owned by the client that was already replaced by
+    /// a newer one of its family, whether or not it was revoked since.
+    fn find_rotated_refresh_token(&self, client: &Client, token: &Uuid)
+        -> StoreResult<Option<RefreshToken>>;
+
+    /// Marks a refresh token as rotated, and inserts the token replacing it.
+    /// Returns None, without inserting anything, if the token was already
+    /// rotated or revoked.
+    fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        replacement: &NewRefreshToken,
+    ) -> StoreResult<Option<RefreshToken>>;
+
+    /// Revokes every refresh token of the token's family, along with every
+    /// access token issued from them.
+    fn revoke_refresh_token_family(&self, token: &RefreshToken) -> StoreResult<()>;
+
+    /// Revokes an unrevoked refresh token owned by the client, along with every
+    /// access token issued from it. Returns whether a token was revoked.
+    fn revoke_refresh_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool>;
+
+    fn insert_auth_code(&self, code: &NewAuthCode) -> StoreResult<AuthCode>;
+
+    /// Atomically finds and removes an authorization code owned by the client,
+    /// so that only one caller can ever redeem it.
+    fn take_auth_code(&self, client: &Client, code: &str) -> StoreResult<Option<AuthCode>>;
+
+    fn insert_device_code(&self, code: &NewDeviceCode) -> StoreResult<DeviceCode>;
+
+    /// Finds a device code by the user code the resource owner entered,
+    /// whatever its status.
+    fn find_device_code(&self, user_code: &str) -> StoreResult<Option<DeviceCode>>;
+
+    /// Records the resource owner's decision on a pending device code.
+    /// Returns false if it was already decided.
+    fn decide_device_code(&self, code: &DeviceCode, user: &User, approved: bool)
+        -> StoreResult<bool>;
+
+    /// Finds a device code owned by the client, and records that the device
+    /// polled it. The code is returned as it was before this poll.
+    fn poll_device_code(&self, client: &Client, device_code: &str)
+        -> StoreResult<Option<DeviceCode>>;
+
+    /// Lengthens the interval the device has to wait between polls.
+    fn slow_down_device_code(&self, code: &DeviceCode, seconds: i32) -> StoreResult<()>;
+
+    /// Removes a device code once its device was told the outcome. Returns
+    /// false if another caller already removed it.
+    fn take_device_code(&self, code: &DeviceCode) -> StoreResult<bool>;
+}
+
+/// Looks up and verifies resource owners. Every backend keeps them in a `users`
+/// table of its own by default.
+pub trait UserStore {
+    /// Finds a user by their username.
+    fn find_user(&self, username: &str) -> StoreResult<Option<User>>;
+
+    fn find_user_by_id(&self, id: i32) -> StoreResult<Option<User>>;
+
+    /// Lists every user, in id order.
+    fn users(&self) -> StoreResult<Vec<User>>;
+
+    fn insert_user(&self, user: &NewUser) -> StoreResult<User>;
+
+    /// Saves the password hash of an existing user.
+    fn update_user(&self, user: &User) -> StoreResult<User>;
+
+    /// Deletes a user, along with every token and code issued on their behalf.
+    /// Returns whether a user was deleted.
+    fn delete_user(&self, user: &User) -> StoreResult<bool>;
+
+    /// Verifies a username and password, returning the user they belong to.
+    /// Stores backed by an external directory can override this, rather than
+    /// hand out password hashes.
+    fn authenticate_user(&self, username: &str, password: &str) -> StoreResult<Option<User>> {
+        Ok(self.find_user(username)?
+            .filter(|user| utils::verify_password(password, &user.password_hash)))
+    }
+}
+
+/// Hands out stores, typically backed by a connection pool.
+pub trait StoreProvider: Send + Sync {
+    fn get(&self) -> StoreResult<Box<dyn Store>>;
+
+    /// Brings the schema up to date by running any pending migrations. Each
+    /// migration that runs is reported on stdout.
+    fn migrate(&self) -> StoreResult<()>;
+}
+
+/// Builds the store provider for the configured backend.
+pub fn connect(settings: &DatabaseSettings) -> Box<dyn StoreProvider> {
+    match settings.backend {
+        StoreBackend::Postgres => Box::new(postgres::PgStoreProvider::new(settings)),
+        StoreBackend::Sqlite => connect_sqlite(settings),
+        StoreBackend::Memory => connect_memory(settings),
+    }
+}
+
+#[cfg(feature = "sqlite-store")]
+fn connect_sqlite(settings: &DatabaseSettings) -> Box<dyn StoreProvider> {
+    Box::new(sqlite::SqliteStoreProvider::new(settings))
+}
+
+#[cfg(not(feature = "sqlite-store"))]
+fn connect_sqlite(_settings: &DatabaseSettings) -> Box<dyn StoreProvider> {
+    panic!("The sqlite store backend requires building with the sqlite-store feature");
+}
+
+#[cfg(feature = "memory-store")]
+fn connect_memory(settings: &DatabaseSettings) -> Box<dyn StoreProvider> {
+    let provider = memory::MemoryStoreProvider::new();
+    if let Some(ref path) = settings.fixture {
+        let fixture = memory::Fixture::load(path).expect("Failed to load the store fixture");
+        provider
+            .seed(&fixture)
+            .expect("Failed to seed the memory store");
+    }
+
+    Box::new(provider)
+}
+
+#[cfg(not(feature = "memory-store"))]
+fn connect_memory(_settings: &DatabaseSettings) -> Box<dyn StoreProvider> {
+    panic!("The memory store backend requires building with the memory-store feature");
+}
@@ -0,0 +1,635 @@
+//! The PostgreSQL store, built on diesel.
+
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use diesel;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel_migrations::RunMigrationsError;
+use models::configuration::DatabaseSettings;
+use models::db::*;
+use persistence::*;
+use r2d2;
+use r2d2::{Pool, PooledConnection};
+use r2d2_diesel::ConnectionManager;
+use std::io;
+use store::{Store, StoreError, StoreProvider, StoreResult, UserStore};
+use uuid::Uuid;
+
+embed_migrations!("migrations/postgres");
+
+impl From<diesel::result::Error> for StoreError {
+    fn from(err: diesel::result::Error) -> StoreError {
+        StoreError(err.to_string())
+    }
+}
+
+impl From<r2d2::Error> for StoreError {
+    fn from(err: r2d2::Error) -> StoreError {
+        StoreError(err.to_string())
+    }
+}
+
+impl From<RunMigrationsError> for StoreError {
+    fn from(err: RunMigrationsError) -> StoreError {
+        StoreError(err.to_string())
+    }
+}
+
+pub struct PgStoreProvider {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl PgStoreProvider {
+    pub fn new(settings: &DatabaseSettings) -> PgStoreProvider {
+        let db_url = format!(
+            "postgres://{}:{}@{}:{}/{}",
+            &settings.user, &settings.pass, &settings.host, settings.port, &settings.db_name
+        );
+        debug!("db url: {}", &db_url);
+        let manager = ConnectionManager::<PgConnection>::new(db_url);
+
+        let pool = Pool::builder()
+            .max_size(settings.pool_size)
+            .build(manager)
+            .expect("Failed to initialize the DB connection pool");
+
+        PgStoreProvider { pool }
+    }
+}
+
+impl StoreProvider for PgStoreProvider {
+    fn get(&self) -> StoreResult<Box<dyn Store>> {
+        Ok(Box::new(PgStore {
+            conn: self.pool.get()?,
+        }))
+    }
+
+    fn migrate(&self) -> StoreResult<()> {
+        let conn = self.pool.get()?;
+        Ok(embedded_migrations::run_with_output(&*conn, &mut io::stdout())?)
+    }
+}
+
+pub struct PgStore {
+    conn: PooledConnection<ConnectionManager<PgConnection>>,
+}
+
+impl Store for PgStore {
+    fn user_store(&self) -> &dyn UserStore {
+        self
+    }
+
+    fn find_client(&self, identifier: &str) -> StoreResult<Option<Client>> {
+        Ok(clients::table
+            .filter(clients::identifier.eq(identifier))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn find_client_by_id(&self, id: i32) -> StoreResult<Option<Client>> {
+        Ok(clients::table
+            .filter(clients::id.eq(id))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn clients(&self) -> StoreResult<Vec<Client>> {
+        Ok(clients::table.order(clients::id.asc()).load(&*self.conn)?)
+    }
+
+    fn insert_client(&self, client: &NewClient) -> StoreResult<Client> {
+        Ok(diesel::insert_into(clients::table)
+            .values(client)
+            .get_result(&*self.conn)?)
+    }
+
+    fn update_client(&self, client: &Client) -> StoreResult<Client> {
+        Ok(diesel::update(clients::table.filter(clients::id.eq(client.id)))
+            .set((
+                clients::secret.eq(&client.secret),
+                clients::response_type.eq(&client.response_type),
+                clients::require_pkce.eq(client.require_pkce),
+                clients::client_name.eq(&client.client_name),
+                clients::scope.eq(&client.scope),
+                clients::token_endpoint_auth_method.eq(&client.token_endpoint_auth_method),
+                clients::registration_access_token.eq(&client.registration_access_token),
+                clients::jwks.eq(&client.jwks),
+                clients::jwt_secret.eq(&client.jwt_secret),
+                clients::tls_client_auth_subject_dn.eq(&client.tls_client_auth_subject_dn),
+            ))
+            .get_result(&*self.conn)?)
+    }
+
+    fn delete_client(&self, client: &Client) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Access tokens go first, as they reference refresh tokens. That
+        // includes the tokens other clients got in exchange for this client's
+        // tokens, which are linked to its refresh tokens.
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let refresh_token_ids: Vec<i32> = refresh_tokens::table
+                .select(refresh_tokens::id)
+                .filter(refresh_tokens::client_id.eq(client.id))
+                .load(conn)?;
+            diesel::delete(
+                access_tokens::table.filter(
+                    access_tokens::client_id
+                        .eq(client.id)
+                        .or(access_tokens::refresh_token_id.eq_any(refresh_token_ids)),
+                ),
+            ).execute(conn)?;
+            diesel::delete(refresh_tokens::table.filter(refresh_tokens::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(auth_codes::table.filter(auth_codes::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(device_codes::table.filter(device_codes::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(
+                client_redirect_uris::table.filter(client_redirect_uris::client_id.eq(client.id)),
+            ).execute(conn)?;
+            diesel::delete(
+                client_grant_types::table.filter(client_grant_types::client_id.eq(client.id)),
+            ).execute(conn)?;
+            diesel::delete(
+                client_assertions::table.filter(client_assertions::client_id.eq(client.id)),
+            ).execute(conn)?;
+
+            diesel::delete(clients::table.filter(clients::id.eq(client.id))).execute(conn)
+        })?;
+
+        Ok(deleted > 0)
+    }
+
+    fn client_grant_types(&self, client: &Client) -> StoreResult<Vec<GrantType>> {
+        let grant_ids: Vec<i32> = client_grant_types::table
+            .select(client_grant_types::grant_id)
+            .filter(client_grant_types::client_id.eq(client.id))
+            .load(&*self.conn)?;
+
+        Ok(grant_types::table
+            .filter(grant_types::id.eq_any(grant_ids))
+            .order(grant_types::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn set_client_grant_types(
+        &self,
+        client: &Client,
+        grant_types: &[GrantType],
+    ) -> StoreResult<()> {
+        let conn = &*self.conn;
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                client_grant_types::table.filter(client_grant_types::client_id.eq(client.id)),
+            ).execute(conn)?;
+
+            for grant_type in grant_types {
+                diesel::insert_into(client_grant_types::table)
+                    .values((
+                        client_grant_types::client_id.eq(client.id),
+                        client_grant_types::grant_id.eq(grant_type.id),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn redirect_uris(&self, client: &Client) -> StoreResult<Vec<ClientRedirectUri>> {
+        Ok(client_redirect_uris::table
+            .filter(client_redirect_uris::client_id.eq(client.id))
+            .order(client_redirect_uris::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn insert_redirect_uri(&self, uri: &NewClientRedirectUri) -> StoreResult<ClientRedirectUri> {
+        Ok(diesel::insert_into(client_redirect_uris::table)
+            .values(uri)
+            .get_result(&*self.conn)?)
+    }
+
+    fn delete_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        let deleted = diesel::delete(
+            client_redirect_uris::table
+                .filter(client_redirect_uris::client_id.eq(client.id))
+                .filter(client_redirect_uris::redirect_uri.eq(redirect_uri)),
+        ).execute(&*self.conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn has_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        let opt: Option<ClientRedirectUri> = client_redirect_uris::table
+            .filter(client_redirect_uris::client_id.eq(client.id))
+            .filter(client_redirect_uris::redirect_uri.eq(redirect_uri))
+            .first(&*self.conn)
+            .optional()?;
+
+        Ok(opt.is_some())
+    }
+
+    fn record_client_assertion(
+        &self,
+        client: &Client,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Expired assertions are rejected before they get here, so they no
+        // longer need to be remembered
+        let recorded = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                client_assertions::table
+                    .filter(client_assertions::client_id.eq(client.id))
+                    .filter(client_assertions::expires_at.lt(Utc::now().naive_utc())),
+            ).execute(conn)?;
+
+            diesel::insert_into(client_assertions::table)
+                .values((
+                    client_assertions::client_id.eq(client.id),
+                    client_assertions::jti.eq(jti),
+                    client_assertions::expires_at.eq(expires_at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)
+        })?;
+
+        Ok(recorded > 0)
+    }
+
+    fn record_bearer_assertion(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        let recorded = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                bearer_assertions::table
+                    .filter(bearer_assertions::issuer.eq(issuer))
+                    .filter(bearer_assertions::expires_at.lt(Utc::now().naive_utc())),
+            ).execute(conn)?;
+
+            diesel::insert_into(bearer_assertions::table)
+                .values((
+                    bearer_assertions::issuer.eq(issuer),
+                    bearer_assertions::jti.eq(jti),
+                    bearer_assertions::expires_at.eq(expires_at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)
+        })?;
+
+        Ok(recorded > 0)
+    }
+
+    fn find_grant_type(&self, name: &str) -> StoreResult<Option<GrantType>> {
+        Ok(grant_types::table
+            .filter(grant_types::name.eq(name))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn grant_types(&self) -> StoreResult<Vec<GrantType>> {
+        Ok(grant_types::table
+            .order(grant_types::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn insert_access_token(&self, token: &NewAccessToken) -> StoreResult<AccessToken> {
+        Ok(diesel::insert_into(access_tokens::table)
+            .values(token)
+            .get_result(&*self.conn)?)
+    }
+
+    fn find_access_token(&self, token: &Uuid) -> StoreResult<Option<AccessToken>> {
+        Ok(access_tokens::table
+            .filter(access_tokens::token.eq(token))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn revoke_access_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let revoked = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let id: i32 = match access_tokens::table
+                .select(access_tokens::id)
+                .filter(access_tokens::token.eq(token))
+                .filter(access_tokens::client_id.eq(client.id))
+                .filter(access_tokens::revoked_at.is_null())
+                .first(conn)
+                .optional()?
+            {
+                Some(id) => id,
+                None => return Ok(false),
+            };
+
+            // Tokens exchanged for a revoked token go with it, and so do the
+            // tokens exchanged for those
+            let mut ids = vec![id];
+            while !ids.is_empty() {
+                diesel::update(
+                    access_tokens::table
+                        .filter(access_tokens::id.eq_any(ids.clone()))
+                        .filter(access_tokens::revoked_at.is_null()),
+                ).set(access_tokens::revoked_at.eq(now))
+                    .execute(conn)?;
+                ids = access_tokens::table
+                    .select(access_tokens::id)
+                    .filter(access_tokens::subject_token_id.eq_any(ids))
+                    .filter(access_tokens::revoked_at.is_null())
+                    .load(conn)?;
+            }
+
+            Ok(true)
+        })?;
+
+        Ok(revoked)
+    }
+
+    fn insert_refresh_token(&self, token: &NewRefreshToken) -> StoreResult<RefreshToken> {
+        Ok(diesel::insert_into(refresh_tokens::table)
+            .values(token)
+            .get_result(&*self.conn)?)
+    }
+
+    fn find_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        Ok(refresh_tokens::table
+            .filter(refresh_tokens::token.eq(token))
+            .filter(refresh_tokens::client_id.eq(client.id))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .filter(refresh_tokens::rotated_at.is_null())
+            .order(refresh_tokens::issued_at.desc())
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn find_rotated_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        Ok(refresh_tokens::table
+            .filter(refresh_tokens::token.eq(token))
+            .filter(refresh_tokens::client_id.eq(client.id))
+            .filter(refresh_tokens::rotated_at.is_not_null())
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        replacement: &NewRefreshToken,
+    ) -> StoreResult<Option<RefreshToken>> {
+        let conn = &*self.conn;
+
+        // Only the request that actually marks the token gets a replacement
+        let rotated = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let marked = diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::id.eq(token.id))
+                    .filter(refresh_tokens::revoked_at.is_null())
+                    .filter(refresh_tokens::rotated_at.is_null()),
+            ).set(refresh_tokens::rotated_at.eq(Utc::now().naive_utc()))
+                .execute(conn)?;
+            if marked == 0 {
+                return Ok(None);
+            }
+
+            diesel::insert_into(refresh_tokens::table)
+                .values(replacement)
+                .get_result(conn)
+                .map(Some)
+        })?;
+
+        Ok(rotated)
+    }
+
+    fn revoke_refresh_token_family(&self, token: &RefreshToken) -> StoreResult<()> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+        let family = token.family();
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let mut ids: Vec<i32> = refresh_tokens::table
+                .select(refresh_tokens::id)
+                .filter(refresh_tokens::family_id.eq(family))
+                .load(conn)?;
+            ids.push(family);
+
+            diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::id.eq_any(ids.clone()))
+                    .filter(refresh_tokens::revoked_at.is_null()),
+            ).set(refresh_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+            diesel::update(
+                access_tokens::table
+                    .filter(access_tokens::refresh_token_id.eq_any(ids))
+                    .filter(access_tokens::revoked_at.is_null()),
+            ).set(access_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn revoke_refresh_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let revoked = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let refresh_token: RefreshToken = match refresh_tokens::table
+                .filter(refresh_tokens::token.eq(token))
+                .filter(refresh_tokens::client_id.eq(client.id))
+                .filter(refresh_tokens::revoked_at.is_null())
+                .first(conn)
+                .optional()?
+            {
+                Some(t) => t,
+                None => return Ok(false),
+            };
+
+            diesel::update(refresh_tokens::table.filter(refresh_tokens::id.eq(refresh_token.id)))
+                .set(refresh_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+            diesel::update(
+                access_tokens::table
+                    .filter(access_tokens::refresh_token_id.eq(refresh_token.id))
+                    .filter(access_tokens::revoked_at.is_null()),
+            ).set(access_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+
+            Ok(true)
+        })?;
+
+        Ok(revoked)
+    }
+
+    fn insert_auth_code(&self, code: &NewAuthCode) -> StoreResult<AuthCode> {
+        Ok(diesel::insert_into(auth_codes::table)
+            .values(code)
+            .get_result(&*self.conn)?)
+    }
+
+    fn take_auth_code(&self, client: &Client, code: &str) -> StoreResult<Option<AuthCode>> {
+        // DELETE ... RETURNING, so concurrent redemptions can't both see the row
+        Ok(diesel::delete(
+            auth_codes::table
+                .filter(auth_codes::name.eq(code))
+                .filter(auth_codes::client_id.eq(client.id)),
+        ).get_result(&*self.conn)
+            .optional()?)
+    }
+
+    fn insert_device_code(&self, code: &NewDeviceCode) -> StoreResult<DeviceCode> {
+        Ok(diesel::insert_into(device_codes::table)
+            .values(code)
+            .get_result(&*self.conn)?)
+    }
+
+    fn find_device_code(&self, user_code: &str) -> StoreResult<Option<DeviceCode>> {
+        Ok(device_codes::table
+            .filter(device_codes::user_code.eq(user_code))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn decide_device_code(
+        &self,
+        code: &DeviceCode,
+        user: &User,
+        approved: bool,
+    ) -> StoreResult<bool> {
+        let status = if approved {
+            DeviceCode::APPROVED
+        } else {
+            DeviceCode::DENIED
+        };
+
+        let decided = diesel::update(
+            device_codes::table
+                .filter(device_codes::id.eq(code.id))
+                .filter(device_codes::status.eq(DeviceCode::PENDING)),
+        ).set((
+            device_codes::status.eq(status),
+            device_codes::user_id.eq(user.id),
+        ))
+            .execute(&*self.conn)?;
+
+        Ok(decided > 0)
+    }
+
+    fn poll_device_code(
+        &self,
+        client: &Client,
+        device_code: &str,
+    ) -> StoreResult<Option<DeviceCode>> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let polled = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let code: DeviceCode = match device_codes::table
+                .filter(device_codes::device_code.eq(device_code))
+                .filter(device_codes::client_id.eq(client.id))
+                .first(conn)
+                .optional()?
+            {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+
+            diesel::update(device_codes::table.filter(device_codes::id.eq(code.id)))
+                .set(device_codes::last_polled_at.eq(now))
+                .execute(conn)?;
+
+            Ok(Some(code))
+        })?;
+
+        Ok(polled)
+    }
+
+    fn slow_down_device_code(&self, code: &DeviceCode, seconds: i32) -> StoreResult<()> {
+        diesel::update(device_codes::table.filter(device_codes::id.eq(code.id)))
+            .set(device_codes::poll_interval.eq(device_codes::poll_interval + seconds))
+            .execute(&*self.conn)?;
+
+        Ok(())
+    }
+
+    fn take_device_code(&self, code: &DeviceCode) -> StoreResult<bool> {
+        let deleted = diesel::delete(device_codes::table.filter(device_codes::id.eq(code.id)))
+            .execute(&*self.conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl UserStore for PgStore {
+    fn find_user(&self, username: &str) -> StoreResult<Option<User>> {
+        Ok(users::table
+            .filter(users::username.eq(username))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn find_user_by_id(&self, id: i32) -> StoreResult<Option<User>> {
+        Ok(users::table
+            .filter(users::id.eq(id))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn users(&self) -> StoreResult<Vec<User>> {
+        Ok(users::table.order(users::id.asc()).load(&*self.conn)?)
+    }
+
+    fn insert_user(&self, user: &NewUser) -> StoreResult<User> {
+        Ok(diesel::insert_into(users::table)
+            .values(user)
+            .get_result(&*self.conn)?)
+    }
+
+    fn update_user(&self, user: &User) -> StoreResult<User> {
+        Ok(diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::password_hash.eq(&user.password_hash))
+            .get_result(&*self.conn)?)
+    }
+
+    fn delete_user(&self, user: &User) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Access tokens go first, as they reference refresh tokens
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(access_tokens::table.filter(access_tokens::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(refresh_tokens::table.filter(refresh_tokens::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(auth_codes::table.filter(auth_codes::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(device_codes::table.filter(device_codes::user_id.eq(user.id)))
+                .execute(conn)?;
+
+            diesel::delete(users::table.filter(users::id.eq(user.id))).execute(conn)
+        })?;
+
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,1011 @@
+//! The SQLite store, built on diesel.
+//!
+//! SQLite has no native UUID type, so tokens are stored in their hyphenated
+//! text form, and are always generated by the application. SQLite also lacks
+//! `RETURNING`, so inserted rows are read back by their token (or id).
+//!
+//! The schema has its own set of migrations, under `migrations/sqlite`.
+
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use models::configuration::DatabaseSettings;
+use models::db::*;
+use r2d2::{Pool, PooledConnection};
+use r2d2_diesel::ConnectionManager;
+use std::io;
+use store::{Store, StoreError, StoreProvider, StoreResult, UserStore};
+use uuid::Uuid;
+
+use self::schema::*;
+
+embed_migrations!("migrations/sqlite");
+
+/// The SQLite flavour of `persistence`, with UUID columns stored as text.
+mod schema {
+    table! {
+        clients (id) {
+            id -> Integer,
+            identifier -> Text,
+            secret -> Text,
+            response_type -> Text,
+            require_pkce -> Bool,
+            client_name -> Nullable<Text>,
+            scope -> Nullable<Text>,
+            token_endpoint_auth_method -> Text,
+            registration_access_token -> Nullable<Text>,
+            jwks -> Nullable<Text>,
+            jwt_secret -> Nullable<Text>,
+            tls_client_auth_subject_dn -> Nullable<Text>,
+        }
+    }
+
+    table! {
+        users (id) {
+            id -> Integer,
+            username -> Text,
+            password_hash -> Text,
+        }
+    }
+
+    table! {
+        grant_types (id) {
+            id -> Integer,
+            name -> Text,
+        }
+    }
+
+    table! {
+        client_redirect_uris (id) {
+            id -> Integer,
+            client_id -> Integer,
+            redirect_uri -> Text,
+        }
+    }
+
+    table! {
+        client_grant_types (id) {
+            id -> Integer,
+            client_id -> Integer,
+            grant_id -> Integer,
+        }
+    }
+
+    table! {
+        client_assertions (id) {
+            id -> Integer,
+            client_id -> Integer,
+            jti -> Text,
+            expires_at -> Timestamp,
+        }
+    }
+
+    table! {
+        bearer_assertions (id) {
+            id -> Integer,
+            issuer -> Text,
+            jti -> Text,
+            expires_at -> Timestamp,
+        }
+    }
+
+    table! {
+        access_tokens (id) {
+            id -> Integer,
+            token -> Text,
+            client_id -> Integer,
+            grant_id -> Integer,
+            scope -> Text,
+            issued_at -> Timestamp,
+            expires_at -> Timestamp,
+            refresh_token_id -> Nullable<Integer>,
+            revoked_at -> Nullable<Timestamp>,
+            cert_thumbprint -> Nullable<Text>,
+            user_id -> Nullable<Integer>,
+            audience -> Nullable<Text>,
+            act -> Nullable<Text>,
+            subject_token_id -> Nullable<Integer>,
+        }
+    }
+
+    table! {
+        refresh_tokens (id) {
+            id -> Integer,
+            token -> Text,
+            client_id -> Integer,
+            scope -> Text,
+            issued_at -> Timestamp,
+            expires_at -> Nullable<Timestamp>,
+            revoked_at -> Nullable<Timestamp>,
+            user_id -> Nullable<Integer>,
+            family_id -> Nullable<Integer>,
+            rotated_at -> Nullable<Timestamp>,
+        }
+    }
+
+    table! {
+        auth_codes (id) {
+            id -> Integer,
+            client_id -> Integer,
+            name -> Text,
+            scope -> Text,
+            expires_at -> Timestamp,
+            redirect_uri -> Text,
+            user_id -> Nullable<Integer>,
+            code_challenge -> Nullable<Text>,
+            code_challenge_method -> Nullable<Text>,
+        }
+    }
+
+    table! {
+        device_codes (id) {
+            id -> Integer,
+            client_id -> Integer,
+            device_code -> Text,
+            user_code -> Text,
+            scope -> Text,
+            expires_at -> Timestamp,
+            poll_interval -> Integer,
+            last_polled_at -> Nullable<Timestamp>,
+            user_id -> Nullable<Integer>,
+            status -> Text,
+        }
+    }
+}
+
+fn parse_token(token: &str) -> StoreResult<Uuid> {
+    Uuid::parse_str(token).map_err(|_| StoreError(format!("malformed token [{}] in database", token)))
+}
+
+#[derive(Queryable)]
+struct AccessTokenRow {
+    id: i32,
+    token: String,
+    client_id: i32,
+    grant_id: i32,
+    scope: String,
+    issued_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+    refresh_token_id: Option<i32>,
+    revoked_at: Option<NaiveDateTime>,
+    cert_thumbprint: Option<String>,
+    user_id: Option<i32>,
+    audience: Option<String>,
+    act: Option<String>,
+    subject_token_id: Option<i32>,
+}
+
+impl AccessTokenRow {
+    fn into_model(self) -> StoreResult<AccessToken> {
+        Ok(AccessToken {
+            id: self.id,
+            token: parse_token(&self.token)?,
+            client_id: self.client_id,
+            grant_id: self.grant_id,
+            scope: self.scope,
+            issued_at: self.issued_at,
+            expires_at: self.expires_at,
+            refresh_token_id: self.refresh_token_id,
+            revoked_at: self.revoked_at,
+            cert_thumbprint: self.cert_thumbprint,
+            user_id: self.user_id,
+            audience: self.audience,
+            act: self.act,
+            subject_token_id: self.subject_token_id,
+        })
+    }
+}
+
+#[derive(Queryable)]
+struct RefreshTokenRow {
+    id: i32,
+    token: String,
+    client_id: i32,
+    scope: String,
+    issued_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    revoked_at: Option<NaiveDateTime>,
+    user_id: Option<i32>,
+    family_id: Option<i32>,
+    rotated_at: Option<NaiveDateTime>,
+}
+
+impl RefreshTokenRow {
+    fn into_model(self) -> StoreResult<RefreshToken> {
+        Ok(RefreshToken {
+            id: self.id,
+            token: parse_token(&self.token)?,
+            client_id: self.client_id,
+            scope: self.scope,
+            issued_at: self.issued_at,
+            expires_at: self.expires_at,
+            revoked_at: self.revoked_at,
+            user_id: self.user_id,
+            family_id: self.family_id,
+            rotated_at: self.rotated_at,
+        })
+    }
+}
+
+pub struct SqliteStoreProvider {
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+}
+
+impl SqliteStoreProvider {
+    pub fn new(settings: &DatabaseSettings) -> SqliteStoreProvider {
+        debug!("sqlite database: {}", &settings.path);
+        let manager = ConnectionManager::<SqliteConnection>::new(settings.path.clone());
+
+        let pool = Pool::builder()
+            .max_size(settings.pool_size)
+            .build(manager)
+            .expect("Failed to initialize the DB connection pool");
+
+        SqliteStoreProvider { pool }
+    }
+}
+
+impl StoreProvider for SqliteStoreProvider {
+    fn get(&self) -> StoreResult<Box<dyn Store>> {
+        Ok(Box::new(SqliteStore {
+            conn: self.pool.get()?,
+        }))
+    }
+
+    fn migrate(&self) -> StoreResult<()> {
+        let conn = self.pool.get()?;
+        Ok(embedded_migrations::run_with_output(&*conn, &mut io::stdout())?)
+    }
+}
+
+pub struct SqliteStore {
+    conn: PooledConnection<ConnectionManager<SqliteConnection>>,
+}
+
+impl SqliteStore {
+    fn find_refresh_token_by_value(&self, token: &Uuid) -> StoreResult<Option<RefreshToken>> {
+        let row: Option<RefreshTokenRow> = refresh_tokens::table
+            .filter(refresh_tokens::token.eq(token.hyphenated().to_string()))
+            .first(&*self.conn)
+            .optional()?;
+
+        match row {
+            Some(r) => r.into_model().map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Store for SqliteStore {
+    fn user_store(&self) -> &dyn UserStore {
+        self
+    }
+
+    fn find_client(&self, identifier: &str) -> StoreResult<Option<Client>> {
+        Ok(clients::table
+            .filter(clients::identifier.eq(identifier))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn find_client_by_id(&self, id: i32) -> StoreResult<Option<Client>> {
+        Ok(clients::table
+            .filter(clients::id.eq(id))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn clients(&self) -> StoreResult<Vec<Client>> {
+        Ok(clients::table.order(clients::id.asc()).load(&*self.conn)?)
+    }
+
+    fn insert_client(&self, client: &NewClient) -> StoreResult<Client> {
+        diesel::insert_into(clients::table)
+            .values((
+                clients::identifier.eq(&client.identifier),
+                clients::secret.eq(&client.secret),
+                clients::response_type.eq(&client.response_type),
+                clients::require_pkce.eq(client.require_pkce),
+                clients::client_name.eq(&client.client_name),
+                clients::scope.eq(&client.scope),
+                clients::token_endpoint_auth_method.eq(&client.token_endpoint_auth_method),
+                clients::registration_access_token.eq(&client.registration_access_token),
+                clients::jwks.eq(&client.jwks),
+                clients::jwt_secret.eq(&client.jwt_secret),
+                clients::tls_client_auth_subject_dn.eq(&client.tls_client_auth_subject_dn),
+            ))
+            .execute(&*self.conn)?;
+
+        self.find_client(&client.identifier)?
+            .ok_or_else(|| StoreError("inserted client could not be read back".to_owned()))
+    }
+
+    fn update_client(&self, client: &Client) -> StoreResult<Client> {
+        diesel::update(clients::table.filter(clients::id.eq(client.id)))
+            .set((
+                clients::secret.eq(&client.secret),
+                clients::response_type.eq(&client.response_type),
+                clients::require_pkce.eq(client.require_pkce),
+                clients::client_name.eq(&client.client_name),
+                clients::scope.eq(&client.scope),
+                clients::token_endpoint_auth_method.eq(&client.token_endpoint_auth_method),
+                clients::registration_access_token.eq(&client.registration_access_token),
+                clients::jwks.eq(&client.jwks),
+                clients::jwt_secret.eq(&client.jwt_secret),
+                clients::tls_client_auth_subject_dn.eq(&client.tls_client_auth_subject_dn),
+            ))
+            .execute(&*self.conn)?;
+
+        Ok(clients::table
+            .filter(clients::id.eq(client.id))
+            .first(&*self.conn)?)
+    }
+
+    fn delete_client(&self, client: &Client) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Access tokens go first, as they reference refresh tokens. That
+        // includes the tokens other clients got in exchange for this client's
+        // tokens, which are linked to its refresh tokens.
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let refresh_token_ids: Vec<i32> = refresh_tokens::table
+                .select(refresh_tokens::id)
+                .filter(refresh_tokens::client_id.eq(client.id))
+                .load(conn)?;
+            diesel::delete(
+                access_tokens::table.filter(
+                    access_tokens::client_id
+                        .eq(client.id)
+                        .or(access_tokens::refresh_token_id.eq_any(refresh_token_ids)),
+                ),
+            ).execute(conn)?;
+            diesel::delete(refresh_tokens::table.filter(refresh_tokens::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(auth_codes::table.filter(auth_codes::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(device_codes::table.filter(device_codes::client_id.eq(client.id)))
+                .execute(conn)?;
+            diesel::delete(
+                client_redirect_uris::table.filter(client_redirect_uris::client_id.eq(client.id)),
+            ).execute(conn)?;
+            diesel::delete(
+                client_grant_types::table.filter(client_grant_types::client_id.eq(client.id)),
+            ).execute(conn)?;
+            diesel::delete(
+                client_assertions::table.filter(client_assertions::client_id.eq(client.id)),
+            ).execute(conn)?;
+
+            diesel::delete(clients::table.filter(clients::id.eq(client.id))).execute(conn)
+        })?;
+
+        Ok(deleted > 0)
+    }
+
+    fn client_grant_types(&self, client: &Client) -> StoreResult<Vec<GrantType>> {
+        let grant_ids: Vec<i32> = client_grant_types::table
+            .select(client_grant_types::grant_id)
+            .filter(client_grant_types::client_id.eq(client.id))
+            .load(&*self.conn)?;
+
+        Ok(grant_types::table
+            .filter(grant_types::id.eq_any(grant_ids))
+            .order(grant_types::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn set_client_grant_types(
+        &self,
+        client: &Client,
+        grant_types: &[GrantType],
+    ) -> StoreResult<()> {
+        let conn = &*self.conn;
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                client_grant_types::table.filter(client_grant_types::client_id.eq(client.id)),
+            ).execute(conn)?;
+
+            for grant_type in grant_types {
+                diesel::insert_into(client_grant_types::table)
+                    .values((
+                        client_grant_types::client_id.eq(client.id),
+                        client_grant_types::grant_id.eq(grant_type.id),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn redirect_uris(&self, client: &Client) -> StoreResult<Vec<ClientRedirectUri>> {
+        Ok(client_redirect_uris::table
+            .filter(client_redirect_uris::client_id.eq(client.id))
+            .order(client_redirect_uris::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn insert_redirect_uri(&self, uri: &NewClientRedirectUri) -> StoreResult<ClientRedirectUri> {
+        let conn = &*self.conn;
+
+        let inserted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(client_redirect_uris::table)
+                .values((
+                    client_redirect_uris::client_id.eq(uri.client_id),
+                    client_redirect_uris::redirect_uri.eq(&uri.redirect_uri),
+                ))
+                .execute(conn)?;
+
+            client_redirect_uris::table
+                .filter(client_redirect_uris::client_id.eq(uri.client_id))
+                .order(client_redirect_uris::id.desc())
+                .first(conn)
+        })?;
+
+        Ok(inserted)
+    }
+
+    fn delete_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        let deleted = diesel::delete(
+            client_redirect_uris::table
+                .filter(client_redirect_uris::client_id.eq(client.id))
+                .filter(client_redirect_uris::redirect_uri.eq(redirect_uri)),
+        ).execute(&*self.conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    fn has_redirect_uri(&self, client: &Client, redirect_uri: &str) -> StoreResult<bool> {
+        let opt: Option<ClientRedirectUri> = client_redirect_uris::table
+            .filter(client_redirect_uris::client_id.eq(client.id))
+            .filter(client_redirect_uris::redirect_uri.eq(redirect_uri))
+            .first(&*self.conn)
+            .optional()?;
+
+        Ok(opt.is_some())
+    }
+
+    fn record_client_assertion(
+        &self,
+        client: &Client,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Expired assertions are rejected before they get here, so they no
+        // longer need to be remembered
+        let recorded = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                client_assertions::table
+                    .filter(client_assertions::client_id.eq(client.id))
+                    .filter(client_assertions::expires_at.lt(Utc::now().naive_utc())),
+            ).execute(conn)?;
+
+            let used: Option<i32> = client_assertions::table
+                .select(client_assertions::id)
+                .filter(client_assertions::client_id.eq(client.id))
+                .filter(client_assertions::jti.eq(jti))
+                .first(conn)
+                .optional()?;
+            if used.is_some() {
+                return Ok(false);
+            }
+
+            diesel::insert_into(client_assertions::table)
+                .values((
+                    client_assertions::client_id.eq(client.id),
+                    client_assertions::jti.eq(jti),
+                    client_assertions::expires_at.eq(expires_at),
+                ))
+                .execute(conn)?;
+
+            Ok(true)
+        })?;
+
+        Ok(recorded)
+    }
+
+    fn record_bearer_assertion(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: NaiveDateTime,
+    ) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        let recorded = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(
+                bearer_assertions::table
+                    .filter(bearer_assertions::issuer.eq(issuer))
+                    .filter(bearer_assertions::expires_at.lt(Utc::now().naive_utc())),
+            ).execute(conn)?;
+
+            let used: Option<i32> = bearer_assertions::table
+                .select(bearer_assertions::id)
+                .filter(bearer_assertions::issuer.eq(issuer))
+                .filter(bearer_assertions::jti.eq(jti))
+                .first(conn)
+                .optional()?;
+            if used.is_some() {
+                return Ok(false);
+            }
+
+            diesel::insert_into(bearer_assertions::table)
+                .values((
+                    bearer_assertions::issuer.eq(issuer),
+                    bearer_assertions::jti.eq(jti),
+                    bearer_assertions::expires_at.eq(expires_at),
+                ))
+                .execute(conn)?;
+
+            Ok(true)
+        })?;
+
+        Ok(recorded)
+    }
+
+    fn find_grant_type(&self, name: &str) -> StoreResult<Option<GrantType>> {
+        Ok(grant_types::table
+            .filter(grant_types::name.eq(name))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn grant_types(&self) -> StoreResult<Vec<GrantType>> {
+        Ok(grant_types::table
+            .order(grant_types::id.asc())
+            .load(&*self.conn)?)
+    }
+
+    fn insert_access_token(&self, token: &NewAccessToken) -> StoreResult<AccessToken> {
+        diesel::insert_into(access_tokens::table)
+            .values((
+                access_tokens::token.eq(token.token.hyphenated().to_string()),
+                access_tokens::client_id.eq(token.client_id),
+                access_tokens::grant_id.eq(token.grant_id),
+                access_tokens::scope.eq(&token.scope),
+                access_tokens::issued_at.eq(token.issued_at),
+                access_tokens::expires_at.eq(token.expires_at),
+                access_tokens::refresh_token_id.eq(token.refresh_token_id),
+                access_tokens::cert_thumbprint.eq(&token.cert_thumbprint),
+                access_tokens::user_id.eq(token.user_id),
+                access_tokens::audience.eq(&token.audience),
+                access_tokens::act.eq(&token.act),
+                access_tokens::subject_token_id.eq(token.subject_token_id),
+            ))
+            .execute(&*self.conn)?;
+
+        self.find_access_token(&token.token)?
+            .ok_or_else(|| StoreError("inserted access token could not be read back".to_owned()))
+    }
+
+    fn find_access_token(&self, token: &Uuid) -> StoreResult<Option<AccessToken>> {
+        let row: Option<AccessTokenRow> = access_tokens::table
+            .filter(access_tokens::token.eq(token.hyphenated().to_string()))
+            .first(&*self.conn)
+            .optional()?;
+
+        match row {
+            Some(r) => r.into_model().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn revoke_access_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let revoked = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let id: i32 = match access_tokens::table
+                .select(access_tokens::id)
+                .filter(access_tokens::token.eq(token.hyphenated().to_string()))
+                .filter(access_tokens::client_id.eq(client.id))
+                .filter(access_tokens::revoked_at.is_null())
+                .first(conn)
+                .optional()?
+            {
+                Some(id) => id,
+                None => return Ok(false),
+            };
+
+            // Tokens exchanged for a revoked token go with it, and so do the
+            // tokens exchanged for those
+            let mut ids = vec![id];
+            while !ids.is_empty() {
+                diesel::update(
+                    access_tokens::table
+                        .filter(access_tokens::id.eq_any(ids.clone()))
+                        .filter(access_tokens::revoked_at.is_null()),
+                ).set(access_tokens::revoked_at.eq(now))
+                    .execute(conn)?;
+                ids = access_tokens::table
+                    .select(access_tokens::id)
+                    .filter(access_tokens::subject_token_id.eq_any(ids))
+                    .filter(access_tokens::revoked_at.is_null())
+                    .load(conn)?;
+            }
+
+            Ok(true)
+        })?;
+
+        Ok(revoked)
+    }
+
+    fn insert_refresh_token(&self, token: &NewRefreshToken) -> StoreResult<RefreshToken> {
+        diesel::insert_into(refresh_tokens::table)
+            .values((
+                refresh_tokens::token.eq(token.token.hyphenated().to_string()),
+                refresh_tokens::client_id.eq(token.client_id),
+                refresh_tokens::scope.eq(&token.scope),
+                refresh_tokens::issued_at.eq(token.issued_at),
+                refresh_tokens::expires_at.eq(token.expires_at),
+                refresh_tokens::user_id.eq(token.user_id),
+                refresh_tokens::family_id.eq(token.family_id),
+            ))
+            .execute(&*self.conn)?;
+
+        self.find_refresh_token_by_value(&token.token)?
+            .ok_or_else(|| StoreError("inserted refresh token could not be read back".to_owned()))
+    }
+
+    fn find_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        let row: Option<RefreshTokenRow> = refresh_tokens::table
+            .filter(refresh_tokens::token.eq(token.hyphenated().to_string()))
+            .filter(refresh_tokens::client_id.eq(client.id))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .filter(refresh_tokens::rotated_at.is_null())
+            .order(refresh_tokens::issued_at.desc())
+            .first(&*self.conn)
+            .optional()?;
+
+        match row {
+            Some(r) => r.into_model().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn find_rotated_refresh_token(
+        &self,
+        client: &Client,
+        token: &Uuid,
+    ) -> StoreResult<Option<RefreshToken>> {
+        let row: Option<RefreshTokenRow> = refresh_tokens::table
+            .filter(refresh_tokens::token.eq(token.hyphenated().to_string()))
+            .filter(refresh_tokens::client_id.eq(client.id))
+            .filter(refresh_tokens::rotated_at.is_not_null())
+            .first(&*self.conn)
+            .optional()?;
+
+        match row {
+            Some(r) => r.into_model().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        replacement: &NewRefreshToken,
+    ) -> StoreResult<Option<RefreshToken>> {
+        let conn = &*self.conn;
+
+        // SQLite serializes writers, so only one caller marks the token
+        let rotated = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let marked = diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::id.eq(token.id))
+                    .filter(refresh_tokens::revoked_at.is_null())
+                    .filter(refresh_tokens::rotated_at.is_null()),
+            ).set(refresh_tokens::rotated_at.eq(Utc::now().naive_utc()))
+                .execute(conn)?;
+            if marked == 0 {
+                return Ok(false);
+            }
+
+            diesel::insert_into(refresh_tokens::table)
+                .values((
+                    refresh_tokens::token.eq(replacement.token.hyphenated().to_string()),
+                    refresh_tokens::client_id.eq(replacement.client_id),
+                    refresh_tokens::scope.eq(&replacement.scope),
+                    refresh_tokens::issued_at.eq(replacement.issued_at),
+                    refresh_tokens::expires_at.eq(replacement.expires_at),
+                    refresh_tokens::user_id.eq(replacement.user_id),
+                    refresh_tokens::family_id.eq(replacement.family_id),
+                ))
+                .execute(conn)?;
+
+            Ok(true)
+        })?;
+
+        if !rotated {
+            return Ok(None);
+        }
+        self.find_refresh_token_by_value(&replacement.token)?
+            .map(Some)
+            .ok_or_else(|| StoreError("inserted refresh token could not be read back".to_owned()))
+    }
+
+    fn revoke_refresh_token_family(&self, token: &RefreshToken) -> StoreResult<()> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+        let family = token.family();
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let mut ids: Vec<i32> = refresh_tokens::table
+                .select(refresh_tokens::id)
+                .filter(refresh_tokens::family_id.eq(family))
+                .load(conn)?;
+            ids.push(family);
+
+            diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::id.eq_any(ids.clone()))
+                    .filter(refresh_tokens::revoked_at.is_null()),
+            ).set(refresh_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+            diesel::update(
+                access_tokens::table
+                    .filter(access_tokens::refresh_token_id.eq_any(ids))
+                    .filter(access_tokens::revoked_at.is_null()),
+            ).set(access_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn revoke_refresh_token(&self, client: &Client, token: &Uuid) -> StoreResult<bool> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let revoked = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let refresh_token_id: i32 = match refresh_tokens::table
+                .select(refresh_tokens::id)
+                .filter(refresh_tokens::token.eq(token.hyphenated().to_string()))
+                .filter(refresh_tokens::client_id.eq(client.id))
+                .filter(refresh_tokens::revoked_at.is_null())
+                .first(conn)
+                .optional()?
+            {
+                Some(id) => id,
+                None => return Ok(false),
+            };
+
+            diesel::update(refresh_tokens::table.filter(refresh_tokens::id.eq(refresh_token_id)))
+                .set(refresh_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+            diesel::update(
+                access_tokens::table
+                    .filter(access_tokens::refresh_token_id.eq(refresh_token_id))
+                    .filter(access_tokens::revoked_at.is_null()),
+            ).set(access_tokens::revoked_at.eq(now))
+                .execute(conn)?;
+
+            Ok(true)
+        })?;
+
+        Ok(revoked)
+    }
+
+    fn insert_auth_code(&self, code: &NewAuthCode) -> StoreResult<AuthCode> {
+        let conn = &*self.conn;
+
+        let inserted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(auth_codes::table)
+                .values((
+                    auth_codes::client_id.eq(code.client_id),
+                    auth_codes::name.eq(&code.name),
+                    auth_codes::scope.eq(&code.scope),
+                    auth_codes::expires_at.eq(code.expires_at),
+                    auth_codes::redirect_uri.eq(&code.redirect_uri),
+                    auth_codes::user_id.eq(code.user_id),
+                    auth_codes::code_challenge.eq(&code.code_challenge),
+                    auth_codes::code_challenge_method.eq(&code.code_challenge_method),
+                ))
+                .execute(conn)?;
+
+            auth_codes::table
+                .filter(auth_codes::name.eq(&code.name))
+                .order(auth_codes::id.desc())
+                .first(conn)
+        })?;
+
+        Ok(inserted)
+    }
+
+    fn take_auth_code(&self, client: &Client, code: &str) -> StoreResult<Option<AuthCode>> {
+        let conn = &*self.conn;
+
+        // SQLite serializes writers, so the delete only succeeds for one caller
+        let taken = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let auth_code: AuthCode = match auth_codes::table
+                .filter(auth_codes::name.eq(code))
+                .filter(auth_codes::client_id.eq(client.id))
+                .first(conn)
+                .optional()?
+            {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+
+            let deleted = diesel::delete(auth_codes::table.filter(auth_codes::id.eq(auth_code.id)))
+                .execute(conn)?;
+
+            Ok(if deleted == 1 { Some(auth_code) } else { None })
+        })?;
+
+        Ok(taken)
+    }
+
+    fn insert_device_code(&self, code: &NewDeviceCode) -> StoreResult<DeviceCode> {
+        let conn = &*self.conn;
+
+        let inserted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(device_codes::table)
+                .values((
+                    device_codes::client_id.eq(code.client_id),
+                    device_codes::device_code.eq(&code.device_code),
+                    device_codes::user_code.eq(&code.user_code),
+                    device_codes::scope.eq(&code.scope),
+                    device_codes::expires_at.eq(code.expires_at),
+                    device_codes::poll_interval.eq(code.poll_interval),
+                ))
+                .execute(conn)?;
+
+            device_codes::table
+                .filter(device_codes::device_code.eq(&code.device_code))
+                .first(conn)
+        })?;
+
+        Ok(inserted)
+    }
+
+    fn find_device_code(&self, user_code: &str) -> StoreResult<Option<DeviceCode>> {
+        Ok(device_codes::table
+            .filter(device_codes::user_code.eq(user_code))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn decide_device_code(
+        &self,
+        code: &DeviceCode,
+        user: &User,
+        approved: bool,
+    ) -> StoreResult<bool> {
+        let status = if approved {
+            DeviceCode::APPROVED
+        } else {
+            DeviceCode::DENIED
+        };
+
+        let decided = diesel::update(
+            device_codes::table
+                .filter(device_codes::id.eq(code.id))
+                .filter(device_codes::status.eq(DeviceCode::PENDING)),
+        ).set((
+            device_codes::status.eq(status),
+            device_codes::user_id.eq(user.id),
+        ))
+            .execute(&*self.conn)?;
+
+        Ok(decided > 0)
+    }
+
+    fn poll_device_code(
+        &self,
+        client: &Client,
+        device_code: &str,
+    ) -> StoreResult<Option<DeviceCode>> {
+        let conn = &*self.conn;
+        let now = Utc::now().naive_utc();
+
+        let polled = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let code: DeviceCode = match device_codes::table
+                .filter(device_codes::device_code.eq(device_code))
+                .filter(device_codes::client_id.eq(client.id))
+                .first(conn)
+                .optional()?
+            {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+
+            diesel::update(device_codes::table.filter(device_codes::id.eq(code.id)))
+                .set(device_codes::last_polled_at.eq(now))
+                .execute(conn)?;
+
+            Ok(Some(code))
+        })?;
+
+        Ok(polled)
+    }
+
+    fn slow_down_device_code(&self, code: &DeviceCode, seconds: i32) -> StoreResult<()> {
+        diesel::update(device_codes::table.filter(device_codes::id.eq(code.id)))
+            .set(device_codes::poll_interval.eq(device_codes::poll_interval + seconds))
+            .execute(&*self.conn)?;
+
+        Ok(())
+    }
+
+    fn take_device_code(&self, code: &DeviceCode) -> StoreResult<bool> {
+        let deleted = diesel::delete(device_codes::table.filter(device_codes::id.eq(code.id)))
+            .execute(&*self.conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl UserStore for SqliteStore {
+    fn find_user(&self, username: &str) -> StoreResult<Option<User>> {
+        Ok(users::table
+            .filter(users::username.eq(username))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn find_user_by_id(&self, id: i32) -> StoreResult<Option<User>> {
+        Ok(users::table
+            .filter(users::id.eq(id))
+            .first(&*self.conn)
+            .optional()?)
+    }
+
+    fn users(&self) -> StoreResult<Vec<User>> {
+        Ok(users::table.order(users::id.asc()).load(&*self.conn)?)
+    }
+
+    fn insert_user(&self, user: &NewUser) -> StoreResult<User> {
+        diesel::insert_into(users::table)
+            .values((
+                users::username.eq(&user.username),
+                users::password_hash.eq(&user.password_hash),
+            ))
+            .execute(&*self.conn)?;
+
+        self.find_user(&user.username)?
+            .ok_or_else(|| StoreError("inserted user could not be read back".to_owned()))
+    }
+
+    fn update_user(&self, user: &User) -> StoreResult<User> {
+        diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::password_hash.eq(&user.password_hash))
+            .execute(&*self.conn)?;
+
+        Ok(users::table
+            .filter(users::id.eq(user.id))
+            .first(&*self.conn)?)
+    }
+
+    fn delete_user(&self, user: &User) -> StoreResult<bool> {
+        let conn = &*self.conn;
+
+        // Access tokens go first, as they reference refresh tokens
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(access_tokens::table.filter(access_tokens::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(refresh_tokens::table.filter(refresh_tokens::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(auth_codes::table.filter(auth_codes::user_id.eq(user.id)))
+                .execute(conn)?;
+            diesel::delete(device_codes::table.filter(device_codes::user_id.eq(user.id)))
+                .execute(conn)?;
+
+            diesel::delete(users::table.filter(users::id.eq(user.id))).execute(conn)
+        })?;
+
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,214 @@
+//! The utils::authorize module holds logic surrounding validating and
+//! processing authorization requests made against the authorization endpoint,
+//! as described in RFC 6749 section 4.1.1.
+//!
+//! The consent form is bound to the page it was rendered on: the page embeds a
+//! random token, and a cookie holds a digest of that token and of the request
+//! the page was rendered for. Posting the form from another site, or with
+//! other parameters, is rejected.
+
+use base64;
+use models::db::{Client, User};
+use models::requests::authorize::AuthorizationRequest;
+use models::responses::authorization_code::{AuthorizationCodeResponse,
+                                            AuthorizationCodeResponseBuilder};
+use models::responses::authorization_error::AuthorizationErrorResponse;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use openssl::rand;
+use sha2::{Digest, Sha256};
+use store::Store;
+use utils;
+use utils::pkce;
+
+/// The cookie binding a consent form to the page it was rendered on.
+pub const CONSENT_COOKIE: &str = "oa2p_consent";
+
+/// Size, in bytes, of consent form tokens, before they are encoded.
+const CONSENT_TOKEN_LEN: usize = 32;
+
+/// Digests a consent token along with the request its form was rendered for.
+fn consent_digest(token: &str, req: &AuthorizationRequest) -> String {
+    let fields = [
+        &req.response_type,
+        &req.client_id,
+        &req.redirect_uri,
+        &req.scope,
+        &req.state,
+        &req.code_challenge,
+        &req.code_challenge_method,
+    ];
+
+    // Each field is length prefixed, so that values cannot bleed into their
+    // neighbours
+    let mut input = token.to_owned();
+    for field in &fields {
+        match **field {
+            Some(ref value) => input.push_str(&format!("\n{}:{}", value.len(), value)),
+            None => input.push_str("\n-"),
+        }
+    }
+
+    base64::encode_config(&Sha256::digest(input.as_bytes()), base64::URL_SAFE_NO_PAD)
+}
+
+/// Generates the token a consent form is rendered with.
+///
+/// Returns: (String, String) --- the token to embed in the form, and the value
+/// of the consent cookie it has to be posted alongside
+pub fn consent_token(req: &AuthorizationRequest) -> (String, String) {
+    let mut bytes = [0u8; CONSENT_TOKEN_LEN];
+    rand::rand_bytes(&mut bytes).unwrap(); // TODO: remove unwrap
+    let token = base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD);
+    let cookie = consent_digest(&token, req);
+
+    (token, cookie)
+}
+
+/// Whether a consent form was posted from the page rendered for the request,
+/// in the same browser.
+pub fn check_consent_token(
+    req: &AuthorizationRequest,
+    token: Option<&str>,
+    cookie: Option<&str>,
+) -> bool {
+    match (token, cookie) {
+        (Some(token), Some(cookie)) => {
+            utils::constant_time_eq(consent_digest(token, req).as_bytes(), cookie.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+/// Validates an authorization request.
+///
+/// Until both the client and its redirect URI have been verified, errors are
+/// returned as `AuthorizationErrorResponse::Invalid` so they are shown to the
+/// resource owner. Afterwards they are redirected back to the client.
+///
+/// Returns: Result<Client, AuthorizationErrorResponse>
+/// - Ok(Client)                        --- the request is valid, and was made
+/// by the resulting Client.
+/// - Err(AuthorizationErrorResponse)   --- The Error value
+pub fn check_authorization_request(
+    store: &dyn Store,
+    req: &AuthorizationRequest,
+) -> Result<Client, AuthorizationErrorResponse> {
+    let client_id = req.client_id
+        .as_ref()
+        .ok_or(AuthorizationErrorResponse::Invalid(
+            OAuth2ErrorResponse::InvalidRequest,
+        ))?;
+    let client = utils::get_client_by_identifier(store, client_id)
+        .map_err(AuthorizationErrorResponse::Invalid)?;
+
+    // We require the redirect URI on every request, rather than falling back to
+    // a lone registered URI, so the token endpoint can always verify it.
+    let redirect_uri = req.redirect_uri
+        .clone()
+        .ok_or(AuthorizationErrorResponse::Invalid(
+            OAuth2ErrorResponse::InvalidRequest,
+        ))?;
+    utils::check_redirect_uri(store, &client, &redirect_uri)
+        .map_err(AuthorizationErrorResponse::Invalid)?;
+
+    // From here on, errors can safely be sent back to the client
+    let redirect_error = |error| AuthorizationErrorResponse::Redirect {
+        redirect_uri: redirect_uri.clone(),
+        error,
+        state: req.state.clone(),
+    };
+
+    match req.response_type.as_ref().map(|v| v.as_str()) {
+        Some("code") => (),
+        Some(_) => return Err(redirect_error(OAuth2ErrorResponse::UnsupportedResponseType)),
+        None => return Err(redirect_error(OAuth2ErrorResponse::InvalidRequest)),
+    }
+
+    // Codes are only handed out to clients that may redeem them
+    utils::check_grant_type(store, &client, "authorization_code")
+        .map_err(|_| redirect_error(OAuth2ErrorResponse::UnauthorizedClient))?;
+
+    match req.scope {
+        Some(ref scope) if check_scope_syntax(scope) => {
+            utils::check_client_scope(&client, scope).map_err(&redirect_error)?
+        }
+        _ => return Err(redirect_error(OAuth2ErrorResponse::InvalidScope)),
+    }
+
+    // Clients flagged as requiring PKCE may not fall back to a bare code
+    match req.code_challenge {
+        Some(ref challenge) => {
+            let method = req.code_challenge_method.as_ref().map(|m| m.as_str());
+            pkce::check_code_challenge(challenge, method).map_err(&redirect_error)?;
+        }
+        None if client.require_pkce => {
+            debug!("Client [{}] requires PKCE.", client.identifier);
+            return Err(redirect_error(OAuth2ErrorResponse::InvalidRequest));
+        }
+        None => (),
+    }
+
+    Ok(client)
+}
+
+/// Checks that a scope value is a space delimited list of scope tokens, as
+/// described in RFC 6749 section 3.3.
+pub fn check_scope_syntax(scope: &str) -> bool {
+    !scope.is_empty() && scope.split(' ').all(|token| {
+        !token.is_empty() && token.chars().all(|c| {
+            c == '\x21' || (c >= '\x23' && c <= '\x5B') || (c >= '\x5D' && c <= '\x7E')
+        })
+    })
+}
+
+/// Issues an authorization code for a validated authorization request, on
+/// behalf of the resource owner who approved it, or an `access_denied` error if
+/// they refused the request.
+///
+/// Returns: Result<AuthorizationCodeResponse, AuthorizationErrorResponse>
+/// - Ok(AuthorizationCodeResponse)   --- the redirect carrying the new code
+/// - Err(AuthorizationErrorResponse) --- The Error value
+pub fn authorization_code(
+    store: &dyn Store,
+    client: &Client,
+    req: AuthorizationRequest,
+    user: &User,
+    approved: bool,
+) -> Result<AuthorizationCodeResponse, AuthorizationErrorResponse> {
+    // Both of these were checked by check_authorization_request
+    let redirect_uri = req.redirect_uri.unwrap_or_default();
+    let scope = req.scope.unwrap_or_default();
+    let code_challenge = match (req.code_challenge, req.code_challenge_method) {
+        (Some(challenge), Some(method)) => Some((challenge, method)),
+        (Some(challenge), None) => Some((challenge, pkce::DEFAULT_METHOD.to_owned())),
+        _ => None,
+    };
+
+    if !approved {
+        return Err(AuthorizationErrorResponse::Redirect {
+            redirect_uri,
+            error: OAuth2ErrorResponse::AccessDenied,
+            state: req.state,
+        });
+    }
+
+    let code = utils::generate_auth_code(
+        store,
+        client,
+        user,
+        &scope,
+        &redirect_uri,
+        code_challenge,
+    );
+    info!(
+        "Client [{}] was issued an authorization code for user [{}] and scope [{}]",
+        client.identifier, user.username, scope
+    );
+
+    Ok(AuthorizationCodeResponseBuilder::default()
+        .redirect_uri(redirect_uri)
+        .code(code.name)
+        .state(req.state)
+        .build()
+        .unwrap()) // TODO: remove unwrap
+}
@@ -0,0 +1,461 @@
+//! The utils::client_auth module authenticates clients at the token, device
+//! authorization, introspection and revocation endpoints. Clients may present
+//! their credentials in one of the ways described in RFC 6749 section 2.3, as
+//! a signed JWT as described in RFC 7523 section 2.2, or as a TLS client
+//! certificate as described in RFC 8705 section 2, and each client is only
+//! allowed the `token_endpoint_auth_method` it declared.
+
+use SETTINGS;
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use models::claims::ClientAssertionClaims;
+use models::db::Client;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use serde_json;
+use std::fmt;
+use store::Store;
+use utils;
+use utils::jwk::JwkSet;
+use utils::jwt::{self, VerifyingKey};
+use web::headers::authorization_token::AuthorizationToken;
+use web::headers::client_certificate::ClientCertificate;
+
+/// The client authentication methods clients can declare. `none` is for
+/// public clients, which only identify themselves.
+pub const SUPPORTED_AUTH_METHODS: &[&str] = &[
+    "client_secret_basic",
+    "client_secret_post",
+    "client_secret_jwt",
+    "private_key_jwt",
+    "tls_client_auth",
+    "self_signed_tls_client_auth",
+    "none",
+];
+
+/// The authentication methods that need a client certificate, which are only
+/// available when `[oauth.mtls]` is configured.
+pub const TLS_AUTH_METHODS: &[&str] = &["tls_client_auth", "self_signed_tls_client_auth"];
+
+/// The authentication method used when a client does not declare one.
+pub const DEFAULT_AUTH_METHOD: &str = "client_secret_basic";
+
+/// The only `client_assertion_type` clients may authenticate with.
+pub const JWT_BEARER_ASSERTION_TYPE: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// The algorithms client assertions may be signed with. HS256 is only used by
+/// `client_secret_jwt`, the others only by `private_key_jwt`.
+pub const ASSERTION_SIGNING_ALGS: &[&str] = &["RS256", "ES256", "EdDSA", "HS256"];
+
+/// The credentials a client presented with a request.
+pub enum ClientCredentials {
+    /// A client_id and client_secret in an `Authorization: Basic` header.
+    Basic { client_id: String, client_secret: String },
+    /// A client_id and client_secret in the request body.
+    Post { client_id: String, client_secret: String },
+    /// Only a client_id in the request body, as sent by public clients, and
+    /// by clients authenticating with a certificate.
+    None { client_id: String },
+    /// A signed JWT in the request body. The client_id is the assertion's
+    /// subject, which is only trusted once the assertion is verified.
+    Jwt {
+        client_id: String,
+        assertion: String,
+        method: &'static str,
+    },
+}
+
+impl fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ClientCredentials {{ method: {}, client_id: {} }}",
+            self.method(),
+            self.client_id()
+        )
+    }
+}
+
+impl ClientCredentials {
+    /// The `token_endpoint_auth_method` these credentials were presented with.
+    pub fn method(&self) -> &'static str {
+        match *self {
+            ClientCredentials::Basic { .. } => "client_secret_basic",
+            ClientCredentials::Post { .. } => "client_secret_post",
+            ClientCredentials::None { .. } => "none",
+            ClientCredentials::Jwt { method, .. } => method,
+        }
+    }
+
+    /// Whether these credentials can authenticate a client registered for the
+    /// given `token_endpoint_auth_method`. The certificate is checked
+    /// separately, so a bare client_id is all the TLS methods need.
+    pub fn allows(&self, method: &str) -> bool {
+        match *self {
+            ClientCredentials::None { .. } => {
+                method == "none" || TLS_AUTH_METHODS.contains(&method)
+            }
+            _ => method == self.method(),
+        }
+    }
+
+    pub fn client_id(&self) -> &str {
+        match *self {
+            ClientCredentials::Basic { ref client_id, .. } => client_id,
+            ClientCredentials::Post { ref client_id, .. } => client_id,
+            ClientCredentials::None { ref client_id } => client_id,
+            ClientCredentials::Jwt { ref client_id, .. } => client_id,
+        }
+    }
+}
+
+/// Works out which credentials a request carries. Clients must not use more
+/// than one authentication method in a request, and a client_id in the body
+/// has to agree with the one in the header or assertion.
+///
+/// Returns: Result<Option<ClientCredentials>, OAuth2Error>
+/// - Ok(Some(ClientCredentials)) --- the credentials sent
+/// - Ok(None)                    --- the request carries no credentials
+/// - Err(OAuth2Error)            --- The Error value
+pub fn credentials(
+    auth: Option<AuthorizationToken>,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+    client_assertion_type: Option<&str>,
+    client_assertion: Option<&str>,
+) -> Result<Option<ClientCredentials>, OAuth2ErrorResponse> {
+    if client_assertion_type.is_some() || client_assertion.is_some() {
+        if auth.is_some() || client_secret.is_some() {
+            return Err(OAuth2ErrorResponse::InvalidRequest);
+        }
+        return assertion_credentials(client_id, client_assertion_type, client_assertion)
+            .map(Some);
+    }
+
+    let auth = match auth {
+        Some(auth) => auth,
+        None => {
+            return match (client_id, client_secret) {
+                (Some(client_id), Some(client_secret)) => Ok(Some(ClientCredentials::Post {
+                    client_id: client_id.to_owned(),
+                    client_secret: client_secret.to_owned(),
+                })),
+                (Some(client_id), None) => Ok(Some(ClientCredentials::None {
+                    client_id: client_id.to_owned(),
+                })),
+                (None, Some(_)) => Err(OAuth2ErrorResponse::InvalidRequest),
+                (None, None) => Ok(None),
+            }
+        }
+    };
+
+    if client_secret.is_some() {
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+    if client_id.map_or(false, |client_id| client_id != auth.user) {
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    Ok(Some(ClientCredentials::Basic {
+        client_id: auth.user,
+        client_secret: auth.pass,
+    }))
+}
+
+/// Reads the client a JWT assertion claims to be from, and the method it
+/// authenticates with. Nothing is verified at this point.
+fn assertion_credentials(
+    client_id: Option<&str>,
+    client_assertion_type: Option<&str>,
+    client_assertion: Option<&str>,
+) -> Result<ClientCredentials, OAuth2ErrorResponse> {
+    if client_assertion_type != Some(JWT_BEARER_ASSERTION_TYPE) {
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+    let assertion = client_assertion.ok_or(OAuth2ErrorResponse::InvalidRequest)?;
+
+    let header = jwt::decode_header(assertion).map_err(|_| OAuth2ErrorResponse::InvalidClient)?;
+    let claims: ClientAssertionClaims =
+        jwt::decode_unverified(assertion).map_err(|_| OAuth2ErrorResponse::InvalidClient)?;
+    if client_id.map_or(false, |client_id| client_id != claims.sub) {
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    let method = if header.alg == "HS256" {
+        "client_secret_jwt"
+    } else {
+        "private_key_jwt"
+    };
+
+    Ok(ClientCredentials::Jwt {
+        client_id: claims.sub,
+        assertion: assertion.to_owned(),
+        method,
+    })
+}
+
+/// Picks the key an assertion was signed with from the client's JWK Set.
+/// Assertions without a `kid` are only accepted from clients with one key.
+fn assertion_key(client: &Client, assertion: &str) -> Result<VerifyingKey, OAuth2ErrorResponse> {
+    let jwks: JwkSet = client
+        .jwks
+        .as_ref()
+        .and_then(|jwks| serde_json::from_str(jwks).ok())
+        .ok_or(OAuth2ErrorResponse::InvalidClient)?;
+    let header = jwt::decode_header(assertion).map_err(|_| OAuth2ErrorResponse::InvalidClient)?;
+
+    let jwk = jwks.signing_key(header.kid.as_ref())
+        .ok_or(OAuth2ErrorResponse::InvalidClient)?;
+
+    jwk.to_verifying_key()
+        .map_err(|_| OAuth2ErrorResponse::InvalidClient)
+}
+
+/// The audiences an assertion may be addressed to: the issuer, or the token
+/// endpoint. This goes for client assertions and JWT bearer grants alike.
+pub fn assertion_audiences() -> Vec<String> {
+    let issuer = SETTINGS.issuer().trim_right_matches('/');
+    vec![issuer.to_owned(), format!("{}/oauth/token", issuer)]
+}
+
+/// Verifies the signature and claims of a client assertion, and makes sure it
+/// is not being replayed.
+///
+/// See: https://tools.ietf.org/html/rfc7523#section-3
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the assertion authenticates the client
+/// - Err(OAuth2Error) --- The Error value
+fn verify_assertion(
+    store: &dyn Store,
+    client: &Client,
+    assertion: &str,
+) -> Result<(), OAuth2ErrorResponse> {
+    let verified = if client.token_endpoint_auth_method == "client_secret_jwt" {
+        let secret = client
+            .jwt_secret
+            .as_ref()
+            .ok_or(OAuth2ErrorResponse::InvalidClient)?;
+        jwt::decode_hs256::<ClientAssertionClaims>(assertion, secret.as_bytes())
+    } else {
+        jwt::decode::<ClientAssertionClaims>(assertion, &assertion_key(client, assertion)?)
+    };
+    let claims = verified.map_err(|e| {
+        debug!(
+            "Rejected the assertion of client [{}]: {}",
+            client.identifier, e
+        );
+        OAuth2ErrorResponse::InvalidClient
+    })?;
+
+    let now = Utc::now().timestamp();
+    if claims.iss != client.identifier || claims.sub != client.identifier {
+        debug!("Assertion issuer or subject is not [{}]", client.identifier);
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+    if !assertion_audiences()
+        .iter()
+        .any(|aud| claims.aud.contains(aud))
+    {
+        debug!("Assertion audience {:?} is not this provider", claims.aud);
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+    if claims.exp <= now || claims.nbf.map_or(false, |nbf| nbf > now) {
+        debug!("Assertion of client [{}] is not valid now", client.identifier);
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    // The jti only has to be remembered for as long as the assertion would
+    // otherwise be accepted
+    let expires_at = NaiveDateTime::from_timestamp_opt(claims.exp, 0)
+        .ok_or(OAuth2ErrorResponse::InvalidClient)?;
+    let first_use = store
+        .record_client_assertion(client, &claims.jti, expires_at)
+        .unwrap(); // TODO: remove unwrap
+    if !first_use {
+        info!(
+            "Client [{}] replayed assertion [{}]",
+            client.identifier, claims.jti
+        );
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    Ok(())
+}
+
+/// Checks that a client presented the certificate it registered: one with the
+/// registered subject DN for `tls_client_auth`, whose certificates the proxy
+/// already verified against its CAs, or one of the certificates in its JWK Set
+/// for `self_signed_tls_client_auth`.
+///
+/// See: https://tools.ietf.org/html/rfc8705#section-2
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the certificate authenticates the client
+/// - Err(OAuth2Error) --- The Error value
+fn verify_certificate(
+    client: &Client,
+    certificate: Option<&ClientCertificate>,
+) -> Result<(), OAuth2ErrorResponse> {
+    let certificate = certificate.ok_or(OAuth2ErrorResponse::InvalidClient)?;
+
+    let matches = if client.token_endpoint_auth_method == "tls_client_auth" {
+        client
+            .tls_client_auth_subject_dn
+            .as_ref()
+            .map_or(false, |dn| *dn == certificate.subject_dn())
+    } else {
+        let der = certificate.to_der();
+        client
+            .jwks
+            .as_ref()
+            .and_then(|jwks| serde_json::from_str::<JwkSet>(jwks).ok())
+            .map_or(false, |jwks| jwks.certificates().contains(&der))
+    };
+    if !matches {
+        debug!(
+            "Client [{}] presented an unregistered certificate: {:?}",
+            client.identifier, certificate
+        );
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    Ok(())
+}
+
+/// Authenticates a client, making sure it used the authentication method it
+/// declared.
+///
+/// Returns: Result<Client, OAuth2Error>
+/// - Ok(Client)       --- The credentials are valid for the Client
+/// - Err(OAuth2Error) --- The Error value
+pub fn authenticate(
+    store: &dyn Store,
+    credentials: &ClientCredentials,
+    certificate: Option<&ClientCertificate>,
+) -> Result<Client, OAuth2ErrorResponse> {
+    let client = match *credentials {
+        ClientCredentials::Basic {
+            ref client_id,
+            ref client_secret,
+        }
+        | ClientCredentials::Post {
+            ref client_id,
+            ref client_secret,
+        } => utils::check_client_credentials(store, client_id, client_secret)?,
+        ClientCredentials::None { ref client_id }
+        | ClientCredentials::Jwt { ref client_id, .. } => {
+            utils::get_client_by_identifier(store, client_id)?
+        }
+    };
+
+    if !credentials.allows(&client.token_endpoint_auth_method) {
+        debug!(
+            "Client [{}] authenticated with [{}], but is registered for [{}]",
+            client.identifier,
+            credentials.method(),
+            client.token_endpoint_auth_method
+        );
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    if let ClientCredentials::Jwt { ref assertion, .. } = *credentials {
+        verify_assertion(store, &client, assertion)?;
+    }
+    if TLS_AUTH_METHODS.contains(&client.token_endpoint_auth_method.as_str()) {
+        verify_certificate(&client, certificate)?;
+    }
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64;
+    use web::headers::client_certificate::tests::self_signed;
+
+    fn client(auth_method: &str, subject_dn: Option<&str>, jwks: Option<String>) -> Client {
+        Client {
+            id: 1,
+            identifier: "client".to_owned(),
+            secret: String::new(),
+            response_type: "confidential".to_owned(),
+            require_pkce: false,
+            client_name: None,
+            scope: None,
+            token_endpoint_auth_method: auth_method.to_owned(),
+            registration_access_token: None,
+            jwks,
+            jwt_secret: None,
+            tls_client_auth_subject_dn: subject_dn.map(String::from),
+        }
+    }
+
+    /// A JWK Set carrying the certificate in `x5c`, as registered by
+    /// self_signed_tls_client_auth clients.
+    fn jwks(certificate: &ClientCertificate) -> String {
+        format!(
+            "{{\"keys\":[{{\"kty\":\"EC\",\"use\":\"sig\",\"x5c\":[\"{}\"]}}]}}",
+            base64::encode(&certificate.to_der())
+        )
+    }
+
+    fn is_invalid_client(result: Result<(), OAuth2ErrorResponse>) -> bool {
+        match result {
+            Err(OAuth2ErrorResponse::InvalidClient) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn tls_client_auth_accepts_the_registered_subject_dn() {
+        let certificate = ClientCertificate(self_signed(&[("O", "oa2p"), ("CN", "test-client")]));
+        let client = client("tls_client_auth", Some("CN=test-client,O=oa2p"), None);
+
+        assert!(verify_certificate(&client, Some(&certificate)).is_ok());
+    }
+
+    #[test]
+    fn tls_client_auth_rejects_another_subject_dn() {
+        let certificate = ClientCertificate(self_signed(&[("O", "oa2p"), ("CN", "other-client")]));
+        let client = client("tls_client_auth", Some("CN=test-client,O=oa2p"), None);
+
+        assert!(is_invalid_client(verify_certificate(&client, Some(&certificate))));
+    }
+
+    #[test]
+    fn tls_client_auth_rejects_clients_without_a_registered_subject_dn() {
+        let certificate = ClientCertificate(self_signed(&[("CN", "test-client")]));
+        let client = client("tls_client_auth", None, None);
+
+        assert!(is_invalid_client(verify_certificate(&client, Some(&certificate))));
+    }
+
+    #[test]
+    fn self_signed_tls_client_auth_accepts_a_registered_certificate() {
+        let certificate = ClientCertificate(self_signed(&[("CN", "self-signed-client")]));
+        let client = client("self_signed_tls_client_auth", None, Some(jwks(&certificate)));
+
+        assert!(verify_certificate(&client, Some(&certificate)).is_ok());
+    }
+
+    #[test]
+    fn self_signed_tls_client_auth_rejects_another_certificate_with_the_same_subject() {
+        let registered = ClientCertificate(self_signed(&[("CN", "self-signed-client")]));
+        let presented = ClientCertificate(self_signed(&[("CN", "self-signed-client")]));
+        let client = client("self_signed_tls_client_auth", None, Some(jwks(&registered)));
+
+        assert!(is_invalid_client(verify_certificate(&client, Some(&presented))));
+    }
+
+    #[test]
+    fn certificate_methods_require_a_certificate() {
+        let certificate = ClientCertificate(self_signed(&[("CN", "test-client")]));
+        let tls = client("tls_client_auth", Some("CN=test-client"), None);
+        let self_signed_tls = client("self_signed_tls_client_auth", None, Some(jwks(&certificate)));
+
+        assert!(is_invalid_client(verify_certificate(&tls, None)));
+        assert!(is_invalid_client(verify_certificate(&self_signed_tls, None)));
+    }
+}
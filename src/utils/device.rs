@@ -0,0 +1,278 @@
+//! The utils::device module holds logic surrounding the device authorization
+//! grant, as described in RFC 8628. Devices request a pair of codes at the
+//! device authorization endpoint, the resource owner enters the user code on
+//! the verification page, and meanwhile the device polls the token endpoint
+//! (see `utils::token::device_code`) until a decision was made.
+
+use SETTINGS;
+use chrono::Duration;
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use models::db::*;
+use models::requests::device::DeviceAuthorizationRequest;
+use models::responses::device_authorization::{DeviceAuthorizationResponse,
+                                              DeviceAuthorizationResponseBuilder};
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use openssl::rand;
+use std::ops::Add;
+use store::Store;
+use utils;
+use utils::authorize::check_scope_syntax;
+use utils::token::DEVICE_CODE_GRANT_TYPE;
+use uuid::Uuid;
+
+/// The path of the verification page resource owners enter user codes on.
+pub const VERIFICATION_PATH: &str = "/oauth/device";
+
+/// Seconds added to the polling interval of a device every time it polls too
+/// soon. See RFC 8628 section 3.5.
+pub const SLOW_DOWN_INCREMENT: i32 = 5;
+
+/// The characters user codes are made of. Vowels are left out so codes can't
+/// spell words, as suggested in RFC 8628 section 6.1.
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+const USER_CODE_LEN: usize = 8;
+
+/// How many user codes are tried before giving up, should they clash with
+/// codes already handed out.
+const USER_CODE_ATTEMPTS: usize = 3;
+
+/// Issues a device code and user code pair for a device authorization
+/// request.
+///
+/// Returns: Result<DeviceAuthorizationResponse, OAuth2Error>
+/// - Ok(DeviceAuthorizationResponse) --- the codes, and where to enter them
+/// - Err(OAuth2Error)                --- The Error value
+pub fn device_authorization(
+    store: &dyn Store,
+    client: &Client,
+    req: &DeviceAuthorizationRequest,
+) -> Result<DeviceAuthorizationResponse, OAuth2ErrorResponse> {
+    let settings = SETTINGS
+        .oauth
+        .device
+        .as_ref()
+        .ok_or(OAuth2ErrorResponse::UnsupportedGrantType)?;
+
+    // Without a scope, the device gets the scope the client registered, if
+    // any (RFC 6749 section 3.3)
+    let scope = match req.scope {
+        Some(ref scope) if check_scope_syntax(scope) => scope.clone(),
+        Some(_) => return Err(OAuth2ErrorResponse::InvalidScope),
+        None => client.scope.clone().unwrap_or_default(),
+    };
+    utils::check_client_scope(client, &scope)?;
+
+    utils::check_grant_type(store, client, DEVICE_CODE_GRANT_TYPE)
+        .map_err(|_| OAuth2ErrorResponse::UnauthorizedClient)?;
+
+    let expiry = Utc::now()
+        .naive_utc()
+        .add(Duration::seconds(settings.code_ttl));
+
+    let mut attempts = 0;
+    let code = loop {
+        let new_code = NewDeviceCodeBuilder::default()
+            .client_id(client.id)
+            .device_code(Uuid::new_v4().simple().to_string())
+            .user_code(generate_user_code())
+            .scope(scope.clone())
+            .expires_at(expiry)
+            .poll_interval(settings.interval)
+            .build()
+            .unwrap(); // TODO: remove unwrap
+
+        attempts += 1;
+        match store.insert_device_code(&new_code) {
+            Err(ref e) if attempts < USER_CODE_ATTEMPTS => {
+                debug!("Failed to insert device code, retrying: {}", e);
+            }
+            result => break result.unwrap(), // TODO: remove unwrap
+        }
+    };
+    info!(
+        "Client [{}] was issued a device code for scope [{}]",
+        client.identifier, code.scope
+    );
+
+    let verification_uri = format!(
+        "{}{}",
+        SETTINGS.issuer().trim_right_matches('/'),
+        VERIFICATION_PATH
+    );
+
+    Ok(DeviceAuthorizationResponseBuilder::default()
+        .device_code(code.device_code)
+        .user_code(format_user_code(&code.user_code))
+        .verification_uri_complete(Some(format!(
+            "{}?user_code={}",
+            verification_uri, code.user_code
+        )))
+        .verification_uri(verification_uri)
+        .expires_in(settings.code_ttl)
+        .interval(code.poll_interval)
+        .build()
+        .unwrap()) // TODO: remove unwrap
+}
+
+/// Generates a random user code from `USER_CODE_CHARSET`.
+fn generate_user_code() -> String {
+    // Bytes past the last multiple of the charset size are skipped, so every
+    // character is equally likely
+    let limit = 256 - 256 % USER_CODE_CHARSET.len();
+    let mut code = String::with_capacity(USER_CODE_LEN);
+    let mut bytes = [0; USER_CODE_LEN * 2];
+
+    while code.len() < USER_CODE_LEN {
+        rand::rand_bytes(&mut bytes).unwrap(); // TODO: remove unwrap
+        let remaining = USER_CODE_LEN - code.len();
+        code.extend(
+            bytes
+                .iter()
+                .map(|b| *b as usize)
+                .filter(|b| *b < limit)
+                .take(remaining)
+                .map(|b| USER_CODE_CHARSET[b % USER_CODE_CHARSET.len()] as char),
+        );
+    }
+
+    code
+}
+
+/// Formats a user code for display, split in two halves so it is easier to
+/// read and type.
+pub fn format_user_code(code: &str) -> String {
+    if code.len() == USER_CODE_LEN {
+        format!("{}-{}", &code[..USER_CODE_LEN / 2], &code[USER_CODE_LEN / 2..])
+    } else {
+        code.to_owned()
+    }
+}
+
+/// Normalizes a user code as typed by the resource owner, dropping the
+/// separator and any whitespace, and ignoring case.
+pub fn normalize_user_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_uppercase())
+        .collect()
+}
+
+fn is_expired(code: &DeviceCode, now: NaiveDateTime) -> bool {
+    code.expires_at.signed_duration_since(now).num_seconds() <= 0
+}
+
+/// Looks up the client a device code was issued to.
+///
+/// Returns: Result<Client, OAuth2Error>
+/// - Ok(Client)       --- the client running on the device
+/// - Err(OAuth2Error) --- The Error value
+pub fn device_code_client(store: &dyn Store, code: &DeviceCode) -> Result<Client, OAuth2ErrorResponse> {
+    store
+        .find_client_by_id(code.client_id)
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)
+}
+
+/// Looks up the device code a resource owner entered the user code of, making
+/// sure it is still awaiting a decision.
+///
+/// Returns: Result<DeviceCode, OAuth2Error>
+/// - Ok(DeviceCode)   --- the pending device code
+/// - Err(OAuth2Error) --- The Error value
+pub fn find_pending_device_code(
+    store: &dyn Store,
+    user_code: &str,
+) -> Result<DeviceCode, OAuth2ErrorResponse> {
+    let code = store
+        .find_device_code(&normalize_user_code(user_code))
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+
+    if code.status != DeviceCode::PENDING || is_expired(&code, Utc::now().naive_utc()) {
+        debug!("Device code [{}] is no longer pending.", code.user_code);
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    Ok(code)
+}
+
+/// Records the resource owner's decision on a pending device code.
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the decision was recorded
+/// - Err(OAuth2Error) --- The Error value
+pub fn decide_device_code(
+    store: &dyn Store,
+    code: &DeviceCode,
+    user: &User,
+    approved: bool,
+) -> Result<(), OAuth2ErrorResponse> {
+    match store.decide_device_code(code, user, approved) {
+        Ok(true) => {
+            info!(
+                "User [{}] {} device code [{}]",
+                user.username,
+                if approved { "approved" } else { "denied" },
+                code.user_code
+            );
+            Ok(())
+        }
+        _ => Err(OAuth2ErrorResponse::InvalidGrant),
+    }
+}
+
+/// Handles a device polling for tokens, as described in RFC 8628 section 3.5.
+/// Once the device was told the outcome, the code is removed, so tokens are
+/// only ever issued for it once.
+///
+/// Returns: Result<DeviceCode, OAuth2Error>
+/// - Ok(DeviceCode)   --- the device code, approved by the resource owner
+/// - Err(OAuth2Error) --- The Error value
+pub fn poll_device_code(
+    store: &dyn Store,
+    client: &Client,
+    device_code: &str,
+) -> Result<DeviceCode, OAuth2ErrorResponse> {
+    let code = store
+        .poll_device_code(client, device_code)
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+    let now = Utc::now().naive_utc();
+
+    if is_expired(&code, now) {
+        debug!("Device code [{}] is expired.", code.user_code);
+        store.take_device_code(&code).unwrap(); // TODO: remove unwrap
+        return Err(OAuth2ErrorResponse::ExpiredToken);
+    }
+
+    if code.status == DeviceCode::DENIED {
+        store.take_device_code(&code).unwrap(); // TODO: remove unwrap
+        return Err(OAuth2ErrorResponse::AccessDenied);
+    }
+
+    if code.status == DeviceCode::PENDING {
+        let too_soon = code.last_polled_at
+            .map(|t| now.signed_duration_since(t).num_seconds() < i64::from(code.poll_interval))
+            .unwrap_or(false);
+        if too_soon {
+            debug!("Client [{}] polled too soon.", client.identifier);
+            store
+                .slow_down_device_code(&code, SLOW_DOWN_INCREMENT)
+                .unwrap(); // TODO: remove unwrap
+            return Err(OAuth2ErrorResponse::SlowDown);
+        }
+
+        return Err(OAuth2ErrorResponse::AuthorizationPending);
+    }
+
+    // Only the poll that actually removes the code gets the tokens
+    match store.take_device_code(&code) {
+        Ok(true) => Ok(code),
+        _ => Err(OAuth2ErrorResponse::InvalidGrant),
+    }
+}
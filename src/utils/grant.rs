@@ -0,0 +1,140 @@
+//! The utils::grant module maps the grant types the token endpoint accepts to
+//! the handlers processing them. The built-in grants of `utils::token` are
+//! registered on first use, leaving out those that are not enabled in
+//! config.toml. Extension grants (RFC 6749 section 4.5) are added with
+//! `register`, before the server is launched, which is also how a built-in
+//! grant can be replaced.
+
+use SETTINGS;
+use models::db::{Client, GrantType};
+use models::requests::access_token::AccessTokenRequest;
+use models::responses::access_token::AccessTokenResponse;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use store::Store;
+use utils::token;
+use web::headers::client_certificate::ClientCertificate;
+
+lazy_static! {
+    static ref GRANT_HANDLERS: RwLock<GrantRegistry> = RwLock::new(GrantRegistry::builtin());
+}
+
+/// Processes the token requests of a single grant type. Handlers are given the
+/// client the request authenticated as, and the certificate it presented, if
+/// any, which the issued access token should be bound to.
+///
+/// The grant type must also exist in the `grant_types` table, and clients
+/// only get to use the grant types they registered. The token endpoint checks
+/// both with `utils::check_grant_type` before handing an authenticated request
+/// to `handle`, along with the `GrantType` it found, so handlers need not
+/// check again. `handle_unauthenticated` has to check them itself, once it has
+/// identified the client.
+pub trait GrantHandler: Send + Sync {
+    /// The `grant_type` requests carry: one of the names defined by RFC 6749,
+    /// or an absolute URI for extension grants.
+    fn grant_type(&self) -> &str;
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse>;
+
+    /// Processes a request that carried no client credentials. Only grants
+    /// whose requests identify the client on their own accept these.
+    fn handle_unauthenticated(
+        &self,
+        _store: &dyn Store,
+        _req: AccessTokenRequest,
+        _certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        Err(OAuth2ErrorResponse::InvalidClient)
+    }
+}
+
+/// The grant handlers, keyed by grant type.
+#[derive(Default)]
+pub struct GrantRegistry {
+    handlers: HashMap<String, Arc<dyn GrantHandler>>,
+}
+
+impl GrantRegistry {
+    /// Builds a registry of the built-in grants. Grants that are enabled by a
+    /// section of their own in config.toml are only registered when it exists.
+    pub fn builtin() -> GrantRegistry {
+        let mut registry = GrantRegistry::default();
+        registry.insert(Arc::new(token::AuthorizationCodeGrant));
+        registry.insert(Arc::new(token::ClientCredentialsGrant));
+        registry.insert(Arc::new(token::PasswordGrant));
+        registry.insert(Arc::new(token::RefreshTokenGrant));
+
+        if SETTINGS.oauth.device.is_some() {
+            registry.insert(Arc::new(token::DeviceCodeGrant));
+        }
+        if SETTINGS.oauth.jwt_bearer.is_some() {
+            registry.insert(Arc::new(token::JwtBearerGrant));
+        }
+        if SETTINGS.oauth.token_exchange.is_some() {
+            registry.insert(Arc::new(token::TokenExchangeGrant));
+        }
+
+        registry
+    }
+
+    /// Adds a handler, replacing any handler of the same grant type.
+    pub fn insert(&mut self, handler: Arc<dyn GrantHandler>) {
+        let grant_type = handler.grant_type().to_owned();
+        if self.handlers.insert(grant_type.clone(), handler).is_some() {
+            info!("Replaced the handler of grant type [{}]", grant_type);
+        }
+    }
+
+    pub fn get(&self, grant_type: &str) -> Option<Arc<dyn GrantHandler>> {
+        self.handlers.get(grant_type).cloned()
+    }
+
+    pub fn contains(&self, grant_type: &str) -> bool {
+        self.handlers.contains_key(grant_type)
+    }
+}
+
+/// Registers the handler of an extension grant, or replaces the handler of a
+/// built-in one.
+pub fn register<H: GrantHandler + 'static>(handler: H) {
+    GRANT_HANDLERS.write().unwrap().insert(Arc::new(handler));
+}
+
+/// Finds the handler of a grant type, if the token endpoint accepts it.
+pub fn find(grant_type: &str) -> Option<Arc<dyn GrantHandler>> {
+    GRANT_HANDLERS.read().unwrap().get(grant_type)
+}
+
+/// Whether the token endpoint accepts a grant type.
+pub fn is_registered(grant_type: &str) -> bool {
+    GRANT_HANDLERS.read().unwrap().contains(grant_type)
+}
+
+/// Looks up the grant types a client asks to use. Only grants the token
+/// endpoint accepts can be given to clients.
+///
+/// Returns: Result<Vec<GrantType>, String>
+/// - Ok(Vec<GrantType>) --- the grant types, in the order they were named
+/// - Err(String)        --- the first name that is not a usable grant type
+pub fn find_grant_types(store: &dyn Store, names: &[String]) -> Result<Vec<GrantType>, String> {
+    let mut grant_types = Vec::new();
+    for name in names {
+        if !is_registered(name) {
+            return Err(name.clone());
+        }
+        match store.find_grant_type(name) {
+            Ok(Some(grant_type)) => grant_types.push(grant_type),
+            _ => return Err(name.clone()),
+        }
+    }
+
+    Ok(grant_types)
+}
@@ -0,0 +1,184 @@
+//! The utils::jwk module converts keys to and from their JSON Web Key
+//! representation, as described in RFC 7517 and RFC 8037.
+
+use base64;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey};
+use openssl::rsa::Rsa;
+use openssl::x509::X509;
+use utils::jwt::{Algorithm, JwtError, VerifyingKey};
+
+// See: https://tools.ietf.org/html/rfc7517#section-4
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize)]
+#[builder(setter(into), default)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    /// The certificate chain of the key, as standard base64 DER. Only the
+    /// first certificate is used, by `self_signed_tls_client_auth`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5c: Option<Vec<String>>,
+}
+
+// See: https://tools.ietf.org/html/rfc7517#section-5
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// The DER encoded certificate each key carries first in its `x5c` chain.
+    /// Entries that are not valid base64, or not a certificate, are skipped.
+    pub fn certificates(&self) -> Vec<Vec<u8>> {
+        self.keys
+            .iter()
+            .filter_map(|k| k.x5c.as_ref().and_then(|x5c| x5c.first()))
+            .filter_map(|cert| base64::decode(cert).ok())
+            .filter(|der| X509::from_der(der).is_ok())
+            .collect()
+    }
+
+    /// Picks the key a token with the given `kid` was signed with. Tokens
+    /// without a `kid` can only be verified against sets with a single signing
+    /// key.
+    pub fn signing_key(&self, kid: Option<&String>) -> Option<&Jwk> {
+        let signing_keys = self.keys
+            .iter()
+            .filter(|k| k.use_.as_ref().map_or(true, |u| u == "sig"))
+            .collect::<Vec<_>>();
+
+        match kid {
+            Some(kid) => signing_keys
+                .into_iter()
+                .find(|k| k.kid.as_ref() == Some(kid)),
+            None if signing_keys.len() == 1 => signing_keys.into_iter().next(),
+            None => None,
+        }
+    }
+}
+
+fn b64(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a required, base64url encoded, key parameter.
+fn b64_param(name: &str, value: &Option<String>) -> Result<Vec<u8>, JwtError> {
+    let value = value
+        .as_ref()
+        .ok_or_else(|| JwtError::Key(format!("missing key parameter [{}]", name)))?;
+    base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| JwtError::Key(format!("invalid key parameter [{}]", name)))
+}
+
+fn check_curve(jwk: &Jwk, curve: &str) -> Result<(), JwtError> {
+    if jwk.crv.as_ref().map(|v| v.as_str()) != Some(curve) {
+        return Err(JwtError::Key(format!("unsupported curve {:?}", jwk.crv)));
+    }
+
+    Ok(())
+}
+
+impl Jwk {
+    /// Builds the public JWK for a verification key.
+    pub fn from_verifying_key(key: &VerifyingKey) -> Result<Jwk, JwtError> {
+        let pkey = key.key();
+        let mut builder = JwkBuilder::default();
+        builder
+            .kid(key.kid.clone())
+            .use_(Some("sig".to_owned()))
+            .alg(Some(key.algorithm.name().to_owned()));
+
+        match pkey.id() {
+            Id::RSA => {
+                let rsa = pkey.rsa()?;
+                builder
+                    .kty("RSA")
+                    .n(Some(b64(&rsa.n().to_vec())))
+                    .e(Some(b64(&rsa.e().to_vec())));
+            }
+            Id::EC => {
+                let ec_key = pkey.ec_key()?;
+                let mut ctx = BigNumContext::new()?;
+                let mut x = BigNum::new()?;
+                let mut y = BigNum::new()?;
+                ec_key.public_key().affine_coordinates_gfp(
+                    ec_key.group(),
+                    &mut x,
+                    &mut y,
+                    &mut ctx,
+                )?;
+                builder
+                    .kty("EC")
+                    .crv(Some("P-256".to_owned()))
+                    .x(Some(b64(&x.to_vec_padded(32)?)))
+                    .y(Some(b64(&y.to_vec_padded(32)?)));
+            }
+            Id::ED25519 => {
+                builder
+                    .kty("OKP")
+                    .crv(Some("Ed25519".to_owned()))
+                    .x(Some(b64(&pkey.raw_public_key()?)));
+            }
+            _ => return Err(JwtError::Key("unsupported key type".to_owned())),
+        }
+
+        builder.build().map_err(JwtError::Key)
+    }
+
+    /// Builds a verification key from a public JWK, such as one registered by
+    /// a client. Keys without an `alg` are used with the algorithm their type
+    /// implies.
+    pub fn to_verifying_key(&self) -> Result<VerifyingKey, JwtError> {
+        let (key, default_algorithm) = match self.kty.as_str() {
+            "RSA" => {
+                let n = BigNum::from_slice(&b64_param("n", &self.n)?)?;
+                let e = BigNum::from_slice(&b64_param("e", &self.e)?)?;
+                (
+                    PKey::from_rsa(Rsa::from_public_components(n, e)?)?,
+                    Algorithm::RS256,
+                )
+            }
+            "EC" => {
+                check_curve(self, "P-256")?;
+                let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+                let x = BigNum::from_slice(&b64_param("x", &self.x)?)?;
+                let y = BigNum::from_slice(&b64_param("y", &self.y)?)?;
+                let ec_key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)?;
+                (PKey::from_ec_key(ec_key)?, Algorithm::ES256)
+            }
+            "OKP" => {
+                check_curve(self, "Ed25519")?;
+                let x = b64_param("x", &self.x)?;
+                (
+                    PKey::public_key_from_raw_bytes(&x, Id::ED25519)?,
+                    Algorithm::EdDSA,
+                )
+            }
+            _ => return Err(JwtError::Key(format!("unsupported key type [{}]", self.kty))),
+        };
+
+        let algorithm = match self.alg {
+            Some(ref alg) => Algorithm::from_name(alg).ok_or(JwtError::AlgorithmMismatch)?,
+            None => default_algorithm,
+        };
+
+        VerifyingKey::new(key, algorithm, self.kid.clone())
+    }
+}
@@ -0,0 +1,331 @@
+//! The utils::jwt module implements the small subset of JSON Web Signature
+//! (RFC 7515) the provider needs: compact serialization of signed tokens, and
+//! verification of their signatures. The provider only signs with asymmetric
+//! algorithms, but HS256 signatures can be verified too, as clients may sign
+//! their assertions with a shared secret.
+
+use base64;
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{Id, PKey, Private, Public};
+use openssl::sign::{Signer, Verifier};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use utils;
+
+/// Size, in bytes, of each of the `r` and `s` values in an ES256 signature.
+const ES256_COMPONENT_LEN: usize = 32;
+
+// See: https://tools.ietf.org/html/rfc7518#section-3.1
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Algorithm {
+    RS256,
+    ES256,
+    EdDSA,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Algorithm::RS256 => "RS256",
+            Algorithm::ES256 => "ES256",
+            Algorithm::EdDSA => "EdDSA",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name {
+            "RS256" => Some(Algorithm::RS256),
+            "ES256" => Some(Algorithm::ES256),
+            "EdDSA" => Some(Algorithm::EdDSA),
+            _ => None,
+        }
+    }
+
+    /// Whether a key is of the right type to be used with this algorithm.
+    fn accepts(&self, id: Id) -> bool {
+        match *self {
+            Algorithm::RS256 => id == Id::RSA,
+            Algorithm::ES256 => id == Id::EC,
+            Algorithm::EdDSA => id == Id::ED25519,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum JwtError {
+    /// The token is not a well formed JWS in compact serialization.
+    Malformed,
+    /// The token uses an algorithm other than the one the key is for.
+    AlgorithmMismatch,
+    /// The signature does not match the token contents.
+    InvalidSignature,
+    /// A key could not be read or used.
+    Key(String),
+}
+
+impl From<ErrorStack> for JwtError {
+    fn from(err: ErrorStack) -> JwtError {
+        JwtError::Key(err.to_string())
+    }
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JwtError::Malformed => write!(f, "malformed token"),
+            JwtError::AlgorithmMismatch => write!(f, "unexpected signing algorithm"),
+            JwtError::InvalidSignature => write!(f, "invalid signature"),
+            JwtError::Key(ref msg) => write!(f, "key error: {}", msg),
+        }
+    }
+}
+
+// See: https://tools.ietf.org/html/rfc7515#section-4.1
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Header {
+    pub alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+}
+
+/// A private key used to sign tokens issued by the provider.
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    key: PKey<Private>,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SigningKey {{ kid: {:?}, algorithm: {:?}, key: [REDACTED] }}",
+            self.kid, self.algorithm
+        )
+    }
+}
+
+impl SigningKey {
+    /// Loads a PEM encoded private key from disk.
+    pub fn from_pem_file(
+        path: &str,
+        algorithm: Algorithm,
+        kid: Option<String>,
+    ) -> Result<SigningKey, JwtError> {
+        let mut pem = Vec::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut pem))
+            .map_err(|e| JwtError::Key(format!("unable to read {}: {}", path, e)))?;
+
+        let key = PKey::private_key_from_pem(&pem)?;
+        if !algorithm.accepts(key.id()) {
+            return Err(JwtError::Key(format!(
+                "{} does not hold a key usable with {}",
+                path,
+                algorithm.name()
+            )));
+        }
+
+        Ok(SigningKey {
+            kid,
+            algorithm,
+            key,
+        })
+    }
+
+    /// Returns the public half of this key, for verifying its own signatures.
+    pub fn verifying_key(&self) -> Result<VerifyingKey, JwtError> {
+        let key = PKey::public_key_from_der(&self.key.public_key_to_der()?)?;
+        Ok(VerifyingKey {
+            kid: self.kid.clone(),
+            algorithm: self.algorithm,
+            key,
+        })
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, JwtError> {
+        match self.algorithm {
+            Algorithm::RS256 => {
+                let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+                signer.update(msg)?;
+                Ok(signer.sign_to_vec()?)
+            }
+            Algorithm::ES256 => {
+                // OpenSSL produces DER encoded ECDSA signatures, while JWS wants
+                // the raw, fixed length, r || s concatenation.
+                let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+                signer.update(msg)?;
+                let sig = EcdsaSig::from_der(&signer.sign_to_vec()?)?;
+                let mut raw = sig.r().to_vec_padded(ES256_COMPONENT_LEN as i32)?;
+                raw.extend(sig.s().to_vec_padded(ES256_COMPONENT_LEN as i32)?);
+                Ok(raw)
+            }
+            Algorithm::EdDSA => {
+                let mut signer = Signer::new_without_digest(&self.key)?;
+                Ok(signer.sign_oneshot_to_vec(msg)?)
+            }
+        }
+    }
+}
+
+/// A public key used to verify token signatures.
+pub struct VerifyingKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    key: PKey<Public>,
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "VerifyingKey {{ kid: {:?}, algorithm: {:?} }}",
+            self.kid, self.algorithm
+        )
+    }
+}
+
+impl VerifyingKey {
+    pub fn new(
+        key: PKey<Public>,
+        algorithm: Algorithm,
+        kid: Option<String>,
+    ) -> Result<VerifyingKey, JwtError> {
+        if !algorithm.accepts(key.id()) {
+            return Err(JwtError::AlgorithmMismatch);
+        }
+
+        Ok(VerifyingKey {
+            kid,
+            algorithm,
+            key,
+        })
+    }
+
+    pub fn key(&self) -> &PKey<Public> {
+        &self.key
+    }
+
+    fn verify(&self, msg: &[u8], sig: &[u8]) -> Result<bool, JwtError> {
+        match self.algorithm {
+            Algorithm::RS256 => {
+                let mut verifier = Verifier::new(MessageDigest::sha256(), &self.key)?;
+                verifier.update(msg)?;
+                Ok(verifier.verify(sig)?)
+            }
+            Algorithm::ES256 => {
+                if sig.len() != ES256_COMPONENT_LEN * 2 {
+                    return Ok(false);
+                }
+                let r = BigNum::from_slice(&sig[..ES256_COMPONENT_LEN])?;
+                let s = BigNum::from_slice(&sig[ES256_COMPONENT_LEN..])?;
+                let der = EcdsaSig::from_private_components(r, s)?.to_der()?;
+
+                let mut verifier = Verifier::new(MessageDigest::sha256(), &self.key)?;
+                verifier.update(msg)?;
+                Ok(verifier.verify(&der)?)
+            }
+            Algorithm::EdDSA => {
+                let mut verifier = Verifier::new_without_digest(&self.key)?;
+                Ok(verifier.verify_oneshot(sig, msg)?)
+            }
+        }
+    }
+}
+
+fn b64_encode(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn b64_decode(data: &str) -> Result<Vec<u8>, JwtError> {
+    base64::decode_config(data, base64::URL_SAFE_NO_PAD).map_err(|_| JwtError::Malformed)
+}
+
+/// Signs a set of claims, returning the token in compact serialization.
+pub fn encode<T: Serialize>(key: &SigningKey, typ: &str, claims: &T) -> Result<String, JwtError> {
+    let header = Header {
+        alg: key.algorithm.name().to_owned(),
+        typ: Some(typ.to_owned()),
+        kid: key.kid.clone(),
+    };
+
+    let header_json = serde_json::to_vec(&header).map_err(|_| JwtError::Malformed)?;
+    let claims_json = serde_json::to_vec(claims).map_err(|_| JwtError::Malformed)?;
+    let signing_input = format!("{}.{}", b64_encode(&header_json), b64_encode(&claims_json));
+    let signature = key.sign(signing_input.as_bytes())?;
+
+    Ok(format!("{}.{}", signing_input, b64_encode(&signature)))
+}
+
+/// Splits a token into its signing input, header and signature. Nothing is
+/// verified at this point.
+fn split(token: &str) -> Result<(&str, &str, &str, &str), JwtError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(JwtError::Malformed);
+    }
+
+    let signing_input = &token[..parts[0].len() + 1 + parts[1].len()];
+    Ok((signing_input, parts[0], parts[1], parts[2]))
+}
+
+/// Reads the header of a token without verifying it, so the right key can be
+/// selected for verification.
+pub fn decode_header(token: &str) -> Result<Header, JwtError> {
+    let (_, header, _, _) = split(token)?;
+    serde_json::from_slice(&b64_decode(header)?).map_err(|_| JwtError::Malformed)
+}
+
+/// Reads the claims of a token without verifying it. Callers must verify the
+/// token with `decode` before trusting anything returned here.
+pub fn decode_unverified<T: DeserializeOwned>(token: &str) -> Result<T, JwtError> {
+    let (_, _, claims, _) = split(token)?;
+    serde_json::from_slice(&b64_decode(claims)?).map_err(|_| JwtError::Malformed)
+}
+
+/// Verifies a token's signature, and returns its claims.
+pub fn decode<T: DeserializeOwned>(token: &str, key: &VerifyingKey) -> Result<T, JwtError> {
+    let (signing_input, header, claims, signature) = split(token)?;
+
+    let header: Header =
+        serde_json::from_slice(&b64_decode(header)?).map_err(|_| JwtError::Malformed)?;
+    if header.alg != key.algorithm.name() {
+        return Err(JwtError::AlgorithmMismatch);
+    }
+
+    if !key.verify(signing_input.as_bytes(), &b64_decode(signature)?)? {
+        return Err(JwtError::InvalidSignature);
+    }
+
+    serde_json::from_slice(&b64_decode(claims)?).map_err(|_| JwtError::Malformed)
+}
+
+/// Verifies a token signed with HS256 under a shared secret, and returns its
+/// claims.
+pub fn decode_hs256<T: DeserializeOwned>(token: &str, secret: &[u8]) -> Result<T, JwtError> {
+    let (signing_input, header, claims, signature) = split(token)?;
+
+    let header: Header =
+        serde_json::from_slice(&b64_decode(header)?).map_err(|_| JwtError::Malformed)?;
+    if header.alg != "HS256" {
+        return Err(JwtError::AlgorithmMismatch);
+    }
+
+    let key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(signing_input.as_bytes())?;
+    if !utils::constant_time_eq(&signer.sign_to_vec()?, &b64_decode(signature)?) {
+        return Err(JwtError::InvalidSignature);
+    }
+
+    serde_json::from_slice(&b64_decode(claims)?).map_err(|_| JwtError::Malformed)
+}
@@ -0,0 +1,266 @@
+//! The utils::jwt_bearer module verifies the assertions presented with the
+//! JWT bearer grant, as described in RFC 7523 section 2.1. Assertions are only
+//! accepted from the trusted issuers configured under `[oauth.jwt_bearer]`,
+//! whose JWK Sets are read from a local file or fetched from a URL, and cached
+//! for `jwks_cache_ttl` seconds.
+
+use SETTINGS;
+use chrono::NaiveDateTime;
+use chrono::offset::Utc;
+use models::claims::JwtBearerClaims;
+use models::configuration::TrustedIssuerSettings;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use reqwest;
+use serde_json;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::sync::RwLock;
+use std::time::Duration;
+use store::Store;
+use utils::client_auth;
+use utils::jwk::JwkSet;
+use utils::jwt;
+
+/// Time allowed for fetching a JWK Set from a URL, in seconds.
+const FETCH_TIMEOUT: u64 = 5;
+
+/// Time to wait after failing to load a JWK Set before trying again, in
+/// seconds. Meanwhile, assertions are checked against the stale copy, if any,
+/// rather than having every request wait on an issuer that is down.
+const RETRY_DELAY: i64 = 30;
+
+lazy_static! {
+    static ref JWKS_CACHE: RwLock<HashMap<String, CachedJwkSet>> = RwLock::new(HashMap::new());
+    static ref JWKS_FAILURES: RwLock<HashMap<String, NaiveDateTime>> = RwLock::new(HashMap::new());
+}
+
+struct CachedJwkSet {
+    jwks: JwkSet,
+    loaded_at: NaiveDateTime,
+}
+
+/// Reads a JWK Set from a local file, or fetches it when given an http(s)
+/// URL.
+fn load_jwks(location: &str) -> Result<JwkSet, String> {
+    if location.starts_with("https://") || location.starts_with("http://") {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(FETCH_TIMEOUT))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut response = client.get(location).send().map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("unexpected status {}", response.status()));
+        }
+
+        response.json().map_err(|e| e.to_string())
+    } else {
+        let mut json = String::new();
+        File::open(location)
+            .and_then(|mut f| f.read_to_string(&mut json))
+            .map_err(|e| e.to_string())?;
+
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+}
+
+/// The stale copy of an issuer's JWK Set, if it was ever loaded.
+fn stale_jwks(issuer: &TrustedIssuerSettings) -> Option<JwkSet> {
+    JWKS_CACHE
+        .read()
+        .unwrap() // TODO: remove unwrap
+        .get(&issuer.issuer)
+        .map(|cached| cached.jwks.clone())
+}
+
+/// Returns the JWK Set of a trusted issuer, from the cache while it is fresh.
+/// Should it fail to load again, the stale copy is used rather than rejecting
+/// every assertion until it can be reloaded, and loading is not attempted
+/// again for `RETRY_DELAY` seconds.
+fn issuer_jwks(issuer: &TrustedIssuerSettings, cache_ttl: i64) -> Option<JwkSet> {
+    let now = Utc::now().naive_utc();
+    {
+        let cache = JWKS_CACHE.read().unwrap(); // TODO: remove unwrap
+        if let Some(cached) = cache.get(&issuer.issuer) {
+            if now.signed_duration_since(cached.loaded_at).num_seconds() < cache_ttl {
+                return Some(cached.jwks.clone());
+            }
+        }
+    }
+    {
+        let failures = JWKS_FAILURES.read().unwrap(); // TODO: remove unwrap
+        if let Some(failed_at) = failures.get(&issuer.issuer) {
+            if now.signed_duration_since(*failed_at).num_seconds() < RETRY_DELAY {
+                return stale_jwks(issuer);
+            }
+        }
+    }
+
+    match load_jwks(&issuer.jwks) {
+        Ok(jwks) => {
+            debug!("Loaded the JWK Set of issuer [{}]", issuer.issuer);
+            JWKS_FAILURES
+                .write()
+                .unwrap() // TODO: remove unwrap
+                .remove(&issuer.issuer);
+            let mut cache = JWKS_CACHE.write().unwrap(); // TODO: remove unwrap
+            cache.insert(
+                issuer.issuer.clone(),
+                CachedJwkSet {
+                    jwks: jwks.clone(),
+                    loaded_at: now,
+                },
+            );
+            Some(jwks)
+        }
+        Err(e) => {
+            warn!(
+                "Unable to load the JWK Set of issuer [{}] from [{}], retrying in {} seconds: {}",
+                issuer.issuer, issuer.jwks, RETRY_DELAY, e
+            );
+            JWKS_FAILURES
+                .write()
+                .unwrap() // TODO: remove unwrap
+                .insert(issuer.issuer.clone(), now);
+            stale_jwks(issuer)
+        }
+    }
+}
+
+/// Verifies the signature and claims of a JWT bearer grant assertion, against
+/// the trusted issuer named in its `iss` claim.
+///
+/// See: https://tools.ietf.org/html/rfc7523#section-3
+///
+/// Returns: Result<(&TrustedIssuerSettings, JwtBearerClaims), OAuth2Error>
+/// - Ok((TrustedIssuerSettings, JwtBearerClaims)) --- the issuer of the
+/// assertion, and its verified claims
+/// - Err(OAuth2Error) --- The Error value
+pub fn verify_assertion(
+    assertion: &str,
+) -> Result<(&'static TrustedIssuerSettings, JwtBearerClaims), OAuth2ErrorResponse> {
+    let settings = SETTINGS
+        .oauth
+        .jwt_bearer
+        .as_ref()
+        .ok_or(OAuth2ErrorResponse::UnsupportedGrantType)?;
+
+    // The issuer has to be known before anything can be verified
+    let unverified: JwtBearerClaims =
+        jwt::decode_unverified(assertion).map_err(|_| OAuth2ErrorResponse::InvalidGrant)?;
+    let issuer = settings
+        .issuers
+        .iter()
+        .find(|i| i.issuer == unverified.iss)
+        .ok_or_else(|| {
+            debug!("Assertion issuer [{}] is not trusted", unverified.iss);
+            OAuth2ErrorResponse::InvalidGrant
+        })?;
+
+    let header = jwt::decode_header(assertion).map_err(|_| OAuth2ErrorResponse::InvalidGrant)?;
+    let key = issuer_jwks(issuer, settings.jwks_cache_ttl)
+        .and_then(|jwks| {
+            jwks.signing_key(header.kid.as_ref())
+                .and_then(|jwk| jwk.to_verifying_key().ok())
+        })
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+    let claims: JwtBearerClaims = jwt::decode(assertion, &key).map_err(|e| {
+        debug!(
+            "Rejected an assertion of issuer [{}]: {}",
+            issuer.issuer, e
+        );
+        OAuth2ErrorResponse::InvalidGrant
+    })?;
+
+    let now = Utc::now().timestamp();
+    if !client_auth::assertion_audiences()
+        .iter()
+        .any(|aud| claims.aud.contains(aud))
+    {
+        debug!("Assertion audience {:?} is not this provider", claims.aud);
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+    if claims.exp <= now || claims.nbf.map_or(false, |nbf| nbf > now) {
+        debug!("Assertion of issuer [{}] is not valid now", issuer.issuer);
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    Ok((issuer, claims))
+}
+
+/// Remembers the `jti` of an assertion until it expires, so it can only be
+/// exchanged once, whichever client presents it. A `jti` is only unique to
+/// the issuer that assigned it. Assertions without one cannot be told apart,
+/// and are accepted for as long as they are valid.
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the assertion was not used before
+/// - Err(OAuth2Error) --- The Error value
+pub fn record_assertion(
+    store: &dyn Store,
+    claims: &JwtBearerClaims,
+) -> Result<(), OAuth2ErrorResponse> {
+    let jti = match claims.jti {
+        Some(ref jti) => jti,
+        None => return Ok(()),
+    };
+
+    let expires_at = NaiveDateTime::from_timestamp_opt(claims.exp, 0)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+    let first_use = store
+        .record_bearer_assertion(&claims.iss, jti, expires_at)
+        .unwrap(); // TODO: remove unwrap
+    if !first_use {
+        info!(
+            "Assertion [{}] of issuer [{}] was replayed",
+            jti, claims.iss
+        );
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "memory-store"))]
+mod tests {
+    use super::*;
+    use models::claims::Audience;
+    use store::StoreProvider;
+    use store::memory::MemoryStoreProvider;
+
+    fn claims(iss: &str, jti: Option<&str>) -> JwtBearerClaims {
+        JwtBearerClaims {
+            iss: iss.to_owned(),
+            sub: "user".to_owned(),
+            aud: Audience::One("https://provider.example".to_owned()),
+            exp: Utc::now().timestamp() + 60,
+            jti: jti.map(String::from),
+            nbf: None,
+            iat: None,
+        }
+    }
+
+    #[test]
+    fn assertions_cannot_be_replayed() {
+        let store = MemoryStoreProvider::new().get().unwrap();
+
+        assert!(record_assertion(&*store, &claims("https://a.example", Some("jti"))).is_ok());
+        assert!(record_assertion(&*store, &claims("https://a.example", Some("jti"))).is_err());
+    }
+
+    #[test]
+    fn jtis_are_scoped_to_their_issuer() {
+        let store = MemoryStoreProvider::new().get().unwrap();
+
+        assert!(record_assertion(&*store, &claims("https://a.example", Some("jti"))).is_ok());
+        assert!(record_assertion(&*store, &claims("https://b.example", Some("jti"))).is_ok());
+    }
+
+    #[test]
+    fn assertions_without_a_jti_are_not_tracked() {
+        let store = MemoryStoreProvider::new().get().unwrap();
+
+        assert!(record_assertion(&*store, &claims("https://a.example", None)).is_ok());
+        assert!(record_assertion(&*store, &claims("https://a.example", None)).is_ok());
+    }
+}
@@ -1,21 +1,47 @@
+pub mod authorize;
+pub mod client_auth;
+pub mod device;
+pub mod grant;
+pub mod jwk;
+pub mod jwt;
+pub mod jwt_bearer;
+pub mod pkce;
+pub mod registration;
 pub mod token;
+pub mod token_exchange;
 
 use SETTINGS;
+use argon2;
+use base64;
 use bcrypt;
 use chrono::Duration;
 use chrono::offset::Utc;
-use diesel;
-use diesel::pg::PgConnection;
-use diesel::prelude::*;
+use keystore;
+use models::claims::{AccessTokenClaims, AccessTokenClaimsBuilder, Actor, Audience, Confirmation};
+use models::configuration::TokenFormat;
 use models::db::*;
 use models::responses::access_token::{AccessTokenResponse, AccessTokenResponseBuilder};
 use models::responses::introspection_err::{IntrospectionErrResponse,
                                            IntrospectionErrResponseBuilder};
 use models::responses::oauth2_error::OAuth2ErrorResponse;
-use persistence::*;
+use openssl::rand;
+use serde_json;
 use std::ops::Add;
+use store::Store;
 use uuid::Uuid;
 
+/// Size, in bytes, of generated client secrets, before they are encoded.
+const CLIENT_SECRET_LEN: usize = 32;
+
+/// Compares two strings without short circuiting on the first difference.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Generates an IntrospectionErrResponse struct.
 ///
 /// Returns: IntrospectionErrResponse --- A standard error response struct when
@@ -34,19 +60,20 @@ pub fn introspection_error() -> IntrospectionErrResponse {
 /// - Ok(Client)       --- The client credentials are valid, and map to the
 /// resulting Client object. - Err(OAuth2Error) --- The Error value
 pub fn check_client_credentials<'a>(
-    conn: &PgConnection,
+    store: &dyn Store,
     client_id: &'a str,
     client_secret: &'a str,
 ) -> Result<Client, OAuth2ErrorResponse> {
     trace!("Checking client credentials...");
 
-    let opt_client: QueryResult<Client> = clients::table
-        .filter(clients::identifier.eq(client_id))
-        .first(conn);
+    let opt_client = store.find_client(client_id);
 
     trace!("Client result: {:?}", &opt_client);
 
-    let unverified_client = opt_client.map_err(|_| OAuth2ErrorResponse::InvalidClient)?;
+    let unverified_client = opt_client
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidClient)?;
 
     // Check the hashed client_secret against the user provided secret + the
     // clients marked salt
@@ -55,46 +82,222 @@ pub fn check_client_credentials<'a>(
         "Attempted to verify client. Underlying result is: {:?}",
         &result_verified_client
     );
-    result_verified_client.map_err(|_| OAuth2ErrorResponse::InvalidClient)?;
 
-    Ok(unverified_client)
+    // A mismatch is Ok(false), not an error
+    match result_verified_client {
+        Ok(true) => Ok(unverified_client),
+        _ => Err(OAuth2ErrorResponse::InvalidClient),
+    }
+}
+
+/// Looks up a Client by its public identifier, without authenticating it.
+///
+/// Returns: Result<Client, OAuth2Error>
+/// - Ok(Client)       --- The client exists.
+/// - Err(OAuth2Error) --- The Error value
+pub fn get_client_by_identifier(
+    store: &dyn Store,
+    client_id: &str,
+) -> Result<Client, OAuth2ErrorResponse> {
+    store
+        .find_client(client_id)
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidClient)
+}
+
+/// Generates a new, random client secret, suitable for handing out to a client
+/// once. Only its hash (see `hash_client_secret`) should ever be stored.
+pub fn generate_client_secret() -> String {
+    let mut bytes = [0u8; CLIENT_SECRET_LEN];
+    rand::rand_bytes(&mut bytes).unwrap(); // TODO: remove unwrap
+    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Hashes a client secret for storage, in the format `check_client_credentials`
+/// verifies against.
+pub fn hash_client_secret(secret: &str) -> Result<String, bcrypt::BcryptError> {
+    bcrypt::hash(secret, bcrypt::DEFAULT_COST)
+}
+
+/// Hashes a user's password for storage.
+pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
+    bcrypt::hash(password, bcrypt::DEFAULT_COST)
+}
+
+/// Checks a password against a stored hash. New hashes are bcrypt, but argon2
+/// hashes (in their encoded `$argon2...` form) are accepted too, so that users
+/// can be imported from elsewhere without resetting their passwords.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$argon2") {
+        argon2::verify_encoded(hash, password.as_bytes()).unwrap_or(false)
+    } else {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    }
 }
 
-/// Validates the Grant Type passed in.
+/// Validates a resource owner's username and password.
+///
+/// Returns: Result<User, OAuth2Error>
+/// - Ok(User)         --- The credentials are valid, and belong to the User.
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_user_credentials(
+    store: &dyn Store,
+    username: &str,
+    password: &str,
+) -> Result<User, OAuth2ErrorResponse> {
+    store
+        .user_store()
+        .authenticate_user(username, password)
+        .ok()
+        .and_then(|u| u)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)
+}
+
+/// Looks up the resource owner a token or code was issued on behalf of.
+///
+/// Returns: Result<Option<User>, OAuth2Error>
+/// - Ok(Option<User>) --- The User, or None if there is no resource owner.
+/// - Err(OAuth2Error) --- The Error value
+fn find_token_user(
+    store: &dyn Store,
+    user_id: Option<i32>,
+) -> Result<Option<User>, OAuth2ErrorResponse> {
+    match user_id {
+        Some(id) => store
+            .user_store()
+            .find_user_by_id(id)
+            .ok()
+            .and_then(|u| u)
+            .map(Some)
+            .ok_or(OAuth2ErrorResponse::InvalidGrant),
+        None => Ok(None),
+    }
+}
+
+/// Validates a redirect URI against the URIs registered for the client.
+/// Registered URIs are compared using simple string comparison, as described
+/// in RFC 3986 section 6.2.1.
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the redirect URI is registered for the client.
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_redirect_uri(
+    store: &dyn Store,
+    client: &Client,
+    redirect_uri: &str,
+) -> Result<(), OAuth2ErrorResponse> {
+    match store.has_redirect_uri(client, redirect_uri) {
+        Ok(true) => Ok(()),
+        _ => Err(OAuth2ErrorResponse::InvalidRequest),
+    }
+}
+
+/// Validates the Grant Type passed in, and that the client registered to use
+/// it.
 ///
 /// Returns: Result<GrantType, OAuth2Error>
-/// - Ok(GrantType)    --- the grant type is valid, and supported.
+/// - Ok(GrantType)    --- the grant type is valid, supported, and allowed for
+/// the client.
 /// - Err(OAuth2Error) --- The Error value
-fn check_grant_type<'r>(
-    conn: &PgConnection,
+pub fn check_grant_type<'r>(
+    store: &dyn Store,
+    client: &Client,
     grant_type: &'r str,
 ) -> Result<GrantType, OAuth2ErrorResponse> {
-    let opt: QueryResult<GrantType> = grant_types::table
-        .filter(grant_types::name.eq(grant_type))
-        .first(conn);
+    let grant_type = store
+        .find_grant_type(grant_type)
+        .ok()
+        .and_then(|g| g)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+
+    let allowed = store
+        .client_grant_types(client)
+        .unwrap_or_default(); // TODO: surface store errors
+    if !allowed.iter().any(|g| g.id == grant_type.id) {
+        info!(
+            "Client [{}] is not allowed to use grant type [{}]",
+            client.identifier, grant_type.name
+        );
+        return Err(OAuth2ErrorResponse::UnauthorizedClient);
+    }
 
-    opt.map_err(|_| OAuth2ErrorResponse::InvalidGrant)
+    Ok(grant_type)
 }
 
 /// Validates a Refresh Token, ensuring the client owns the token.
 ///
+/// A token that was already rotated should never be presented again, so it
+/// is taken as a sign of theft, and its whole family is revoked.
+///
 /// Returns: Result<RefreshToken, OAuth2Error>
 /// - Ok(RefreshToken) --- the token itself, if valid
 /// - Err(OAuth2Error) --- The Error value
 fn check_refresh_token<'a>(
-    conn: &PgConnection,
+    store: &dyn Store,
     client: &Client,
     token: &'a str,
 ) -> Result<RefreshToken, OAuth2ErrorResponse> {
     let refresh_token = Uuid::parse_str(&token).map_err(|_| OAuth2ErrorResponse::InvalidRequest)?;
 
-    let token = refresh_tokens::table
-        .filter(refresh_tokens::token.eq(refresh_token))
-        .filter(refresh_tokens::client_id.eq(client.id))
-        .order(refresh_tokens::issued_at.desc())
-        .first(conn);
+    if let Some(t) = store.find_refresh_token(client, &refresh_token).ok().and_then(|t| t) {
+        return Ok(t);
+    }
 
-    token.map_err(|_| OAuth2ErrorResponse::InvalidRequest)
+    match store
+        .find_rotated_refresh_token(client, &refresh_token)
+        .ok()
+        .and_then(|t| t)
+    {
+        Some(rotated) => {
+            warn!(
+                "Client [{}] presented rotated refresh token [{}], revoking its family",
+                client.identifier, token
+            );
+            revoke_refresh_token_family(store, &rotated);
+            Err(OAuth2ErrorResponse::InvalidGrant)
+        }
+        None => Err(OAuth2ErrorResponse::InvalidRequest),
+    }
+}
+
+/// Redeems an Authorization Code, ensuring the client owns the code and that
+/// the redirect URI matches the one used to request it.
+///
+/// The code is deleted as part of redemption, so a given code can only ever be
+/// exchanged once.
+///
+/// Returns: Result<AuthCode, OAuth2Error>
+/// - Ok(AuthCode)     --- the redeemed code, if valid
+/// - Err(OAuth2Error) --- The Error value
+fn redeem_auth_code<'a>(
+    store: &dyn Store,
+    client: &Client,
+    code: &'a str,
+    redirect_uri: &'a str,
+) -> Result<AuthCode, OAuth2ErrorResponse> {
+    // Only the request that actually removes the code gets to use it
+    let auth_code = store
+        .take_auth_code(client, code)
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+
+    if auth_code
+        .expires_at
+        .signed_duration_since(Utc::now().naive_utc())
+        .num_seconds() <= 0
+    {
+        debug!("Authorization code is expired.");
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    if auth_code.redirect_uri != redirect_uri {
+        debug!("Authorization code redirect URI mismatch.");
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    Ok(auth_code)
 }
 
 /// Validates a Scope list.
@@ -104,7 +307,7 @@ fn check_refresh_token<'a>(
 /// appear in both the original request, and the
 /// existing token) - Err(OAuth2Error) --- The Error value
 fn check_scope<'a>(
-    _conn: &PgConnection,
+    _store: &dyn Store,
     req: &'a str,
     prev: &'a str,
 ) -> Result<String, OAuth2ErrorResponse> {
@@ -120,66 +323,304 @@ fn check_scope<'a>(
     Ok(request_scopes.join(" "))
 }
 
-/// Generates an AccessToken.
+/// Checks that a client may ask for a scope: every scope token must be one
+/// the client registered or, for clients that registered none, one of the
+/// `scopes_supported`. Without either, any scope is accepted.
 ///
-/// Returns: AccessToken --- the AccessToken to send back to the caller
-pub fn generate_access_token(
-    conn: &PgConnection,
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the client may ask for the scope
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_client_scope(client: &Client, scope: &str) -> Result<(), OAuth2ErrorResponse> {
+    let allowed: Vec<&str> = match client.scope {
+        Some(ref registered) => registered.split(' ').collect(),
+        None => match SETTINGS.oauth.scopes_supported {
+            Some(ref supported) => supported.iter().map(|s| s.as_str()).collect(),
+            None => return Ok(()),
+        },
+    };
+
+    if scope
+        .split(' ')
+        .filter(|s| !s.is_empty())
+        .all(|s| allowed.contains(&s))
+    {
+        Ok(())
+    } else {
+        info!(
+            "Client [{}] asked for scope [{}], beyond the scope it may use",
+            client.identifier, scope
+        );
+        Err(OAuth2ErrorResponse::InvalidScope)
+    }
+}
+
+/// Starts building an Access Token, filling in everything but its audience
+/// and actor.
+fn new_access_token(
     c: &Client,
+    user: Option<&User>,
     g: &GrantType,
     scope: &str,
-) -> AccessToken {
+    rt: Option<&RefreshToken>,
+    cert_thumbprint: Option<String>,
+) -> NewAccessTokenBuilder {
     let token_ttl = SETTINGS.oauth.access_token_ttl;
     let expiry = Utc::now().naive_utc().add(Duration::seconds(token_ttl));
 
-    let new_token = NewAccessTokenBuilder::default()
+    let mut builder = NewAccessTokenBuilder::default();
+    builder
+        .token(Uuid::new_v4())
         .client_id(c.id)
         .grant_id(g.id)
         .scope(scope.clone())
         .issued_at(Utc::now().naive_utc())
         .expires_at(expiry)
+        .refresh_token_id(rt.map(|t| t.id))
+        .cert_thumbprint(cert_thumbprint)
+        .user_id(user.map(|u| u.id));
+    builder
+}
+
+/// Generates an AccessToken. Access tokens are linked to the refresh token
+/// they were issued alongside (or from), so that revoking the refresh token
+/// also revokes them, and to the resource owner they were issued on behalf of.
+/// When the client presented a certificate, the token is bound to its
+/// thumbprint.
+///
+/// Returns: AccessToken --- the AccessToken to send back to the caller
+pub fn generate_access_token(
+    store: &dyn Store,
+    c: &Client,
+    user: Option<&User>,
+    g: &GrantType,
+    scope: &str,
+    rt: Option<&RefreshToken>,
+    cert_thumbprint: Option<String>,
+) -> AccessToken {
+    let new_token = new_access_token(c, user, g, scope, rt, cert_thumbprint)
+        .audience(None::<String>)
+        .act(None::<String>)
+        .subject_token_id(None::<i32>)
         .build()
         .unwrap(); // TODO: remove unwrap
 
-    let res = diesel::insert_into(access_tokens::table)
-        .values(&new_token)
-        .get_result::<AccessToken>(conn);
+    let res = store.insert_access_token(&new_token);
 
     res.unwrap() // TODO: remove unwrap
 }
 
-/// Generates a Refresh Token.
-///
-/// Returns: RefreshToken --- A refresh Token for the given client, allowing
-/// callers to generate a new access token using the
-/// stored scope.
-pub fn generate_refresh_token(conn: &PgConnection, c: &Client, s: &str) -> RefreshToken {
+/// Builds a Refresh Token, starting a new family unless one is given.
+fn new_refresh_token(
+    c: &Client,
+    user: Option<&User>,
+    s: &str,
+    family_id: Option<i32>,
+) -> NewRefreshToken {
     let token_ttl = SETTINGS.oauth.refresh_token_ttl;
     let expiry = match token_ttl {
         -1 => None,
         val => Some(Utc::now().naive_utc().add(Duration::seconds(val))),
     };
 
-    let new_token = NewRefreshTokenBuilder::default()
+    NewRefreshTokenBuilder::default()
+        .token(Uuid::new_v4())
         .client_id(c.id)
         .scope(s.clone())
         .issued_at(Utc::now().naive_utc())
         .expires_at(expiry)
+        .user_id(user.map(|u| u.id))
+        .family_id(family_id)
+        .build()
+        .unwrap() // TODO: remove unwrap
+}
+
+/// Generates a Refresh Token.
+///
+/// Returns: RefreshToken --- A refresh Token for the given client, allowing
+/// callers to generate a new access token using the
+/// stored scope.
+pub fn generate_refresh_token(
+    store: &dyn Store,
+    c: &Client,
+    user: Option<&User>,
+    s: &str,
+) -> RefreshToken {
+    let new_token = new_refresh_token(c, user, s, None);
+
+    store
+        .insert_refresh_token(&new_token)
+        .unwrap() // TODO: remove unwrap
+}
+
+/// Replaces a Refresh Token with a new one of the same family and scope. If
+/// the token was rotated concurrently, one of the requests replayed it, so the
+/// whole family is revoked.
+///
+/// Returns: Result<RefreshToken, OAuth2Error>
+/// - Ok(RefreshToken) --- the replacement token
+/// - Err(OAuth2Error) --- The Error value
+pub fn rotate_refresh_token(
+    store: &dyn Store,
+    c: &Client,
+    user: Option<&User>,
+    rt: &RefreshToken,
+) -> Result<RefreshToken, OAuth2ErrorResponse> {
+    let replacement = new_refresh_token(c, user, &rt.scope, Some(rt.family()));
+
+    match store
+        .rotate_refresh_token(rt, &replacement)
+        .unwrap() // TODO: remove unwrap
+    {
+        Some(t) => Ok(t),
+        None => {
+            warn!(
+                "Refresh token [{}] of client [{}] was rotated twice, revoking its family",
+                rt.token, c.identifier
+            );
+            revoke_refresh_token_family(store, rt);
+            Err(OAuth2ErrorResponse::InvalidGrant)
+        }
+    }
+}
+
+/// Generates an Authorization Code.
+///
+/// Returns: AuthCode --- A short lived, single use code the client can exchange
+/// for an access token at the token endpoint, on behalf of the user.
+pub fn generate_auth_code(
+    store: &dyn Store,
+    c: &Client,
+    user: &User,
+    scope: &str,
+    redirect_uri: &str,
+    code_challenge: Option<(String, String)>,
+) -> AuthCode {
+    let code_ttl = SETTINGS.oauth.auth_code_ttl;
+    let expiry = Utc::now().naive_utc().add(Duration::seconds(code_ttl));
+
+    let (challenge, challenge_method) = match code_challenge {
+        Some((challenge, method)) => (Some(challenge), Some(method)),
+        None => (None, None),
+    };
+
+    let new_code = NewAuthCodeBuilder::default()
+        .client_id(c.id)
+        .name(Uuid::new_v4().simple().to_string())
+        .scope(scope)
+        .expires_at(expiry)
+        .redirect_uri(redirect_uri)
+        .user_id(Some(user.id))
+        .code_challenge(challenge)
+        .code_challenge_method(challenge_method)
         .build()
         .unwrap(); // TODO: remove unwrap
 
-    diesel::insert_into(refresh_tokens::table)
-        .values(&new_token)
-        .get_result::<RefreshToken>(conn)
+    store
+        .insert_auth_code(&new_code)
         .unwrap() // TODO: remove unwrap
 }
 
+/// The confirmation claim binding an AccessToken to a client certificate, if
+/// it is bound to one.
+pub fn confirmation(at: &AccessToken) -> Option<Confirmation> {
+    at.cert_thumbprint.as_ref().map(|thumbprint| Confirmation {
+        x5t_s256: thumbprint.clone(),
+    })
+}
+
+/// The services an AccessToken was exchanged for, if it is aimed at specific
+/// ones.
+pub fn token_audience(at: &AccessToken) -> Option<Audience> {
+    at.audience.as_ref().map(|audience| {
+        let mut values: Vec<String> = audience.split(' ').map(String::from).collect();
+        if values.len() == 1 {
+            Audience::One(values.remove(0))
+        } else {
+            Audience::Many(values)
+        }
+    })
+}
+
+/// The actor claim of an AccessToken obtained through token exchange, if a
+/// party acts on behalf of its subject.
+pub fn actor(at: &AccessToken) -> Option<Actor> {
+    at.act
+        .as_ref()
+        .and_then(|act| serde_json::from_str(act).ok())
+}
+
+/// Formats an AccessToken the way it is handed out to clients, according to
+/// the configured token format. JWT access tokens use the stored token as
+/// their `jti`, so they can still be introspected and revoked. Their subject
+/// is the resource owner, or the client itself if there is none, and their
+/// audience the configured one, unless they were exchanged for other
+/// services.
+///
+/// Returns: String --- the access token value to send back to the caller
+pub fn format_access_token(c: &Client, user: Option<&User>, at: &AccessToken) -> String {
+    let jti = at.token.hyphenated().to_string();
+    if SETTINGS.oauth.token_format == TokenFormat::Uuid {
+        return jti;
+    }
+
+    // Both of these are checked when the key store is initialized
+    let jwt_settings = SETTINGS.oauth.jwt.as_ref().unwrap();
+    let keys = keystore::read();
+    let key = keys.active().unwrap();
+
+    let claims = AccessTokenClaimsBuilder::default()
+        .iss(SETTINGS.issuer().to_owned())
+        .sub(user.map_or_else(|| c.identifier.clone(), |u| u.username.clone()))
+        .aud(token_audience(at).unwrap_or_else(|| Audience::One(jwt_settings.audience.clone())))
+        .client_id(c.identifier.clone())
+        .scope(at.scope.clone())
+        .iat(at.issued_at.timestamp())
+        .exp(at.expires_at.timestamp())
+        .jti(jti)
+        .cnf(confirmation(at))
+        .act(actor(at))
+        .build()
+        .unwrap(); // TODO: remove unwrap
+
+    jwt::encode(key, "at+jwt", &claims).unwrap() // TODO: remove unwrap
+}
+
+/// Parses an access token presented by a caller back into the stored token
+/// value. Both plain UUID tokens and JWT access tokens signed by the provider
+/// are accepted, regardless of the format currently being issued.
+///
+/// Returns: Option<Uuid> --- the stored token, if the value could be parsed
+pub fn parse_access_token(token: &str) -> Option<Uuid> {
+    if let Ok(uuid) = Uuid::parse_str(token) {
+        return Some(uuid);
+    }
+
+    let header = jwt::decode_header(token).ok()?;
+    let key = keystore::read()
+        .find(header.kid.as_ref().map(|v| v.as_str()))?
+        .verifying_key()
+        .ok()?;
+    let claims: AccessTokenClaims = jwt::decode(token, &key)
+        .map_err(|e| debug!("Rejected JWT access token: {}", e))
+        .ok()?;
+    if claims.iss != SETTINGS.issuer() {
+        return None;
+    }
+
+    Uuid::parse_str(&claims.jti).ok()
+}
+
 /// Generates an AccessTokenResponse.
 ///
 /// Returns: AccessTokenResponse --- the access token response object that
 /// should be sent to the caller.
-pub fn generate_token_response(at: AccessToken, rt: Option<RefreshToken>) -> AccessTokenResponse {
-    let access_token = at.token.hyphenated().to_string();
+pub fn generate_token_response(
+    c: &Client,
+    user: Option<&User>,
+    at: AccessToken,
+    rt: Option<RefreshToken>,
+) -> AccessTokenResponse {
+    let access_token = format_access_token(c, user, &at);
     let mut builder = AccessTokenResponseBuilder::default();
 
     builder
@@ -206,13 +647,82 @@ pub fn generate_token_response(at: AccessToken, rt: Option<RefreshToken>) -> Acc
         }
         None => builder.refresh_token(None).refresh_expires_in(None),
     };
+    builder.issued_token_type(None);
 
     builder.build().unwrap() // TODO: remove unwrap
 }
 
-pub fn get_grant_type_by_name(conn: &PgConnection, name: &str) -> GrantType {
-    grant_types::table
-        .filter(grant_types::name.eq(name))
-        .first(conn)
-        .unwrap() // TODO: remove unwrap
+/// Revokes an AccessToken owned by the given client.
+///
+/// Returns: bool --- whether a matching, unrevoked token was found.
+pub fn revoke_access_token(store: &dyn Store, client: &Client, token: &Uuid) -> bool {
+    store
+        .revoke_access_token(client, token)
+        .unwrap_or(false) // TODO: surface store errors
+}
+
+/// Revokes a RefreshToken owned by the given client, along with every
+/// AccessToken that was issued from it.
+///
+/// Returns: bool --- whether a matching, unrevoked token was found.
+pub fn revoke_refresh_token(store: &dyn Store, client: &Client, token: &Uuid) -> bool {
+    store
+        .revoke_refresh_token(client, token)
+        .unwrap_or(false) // TODO: surface store errors
+}
+
+/// Revokes every RefreshToken of the token's family, along with every
+/// AccessToken issued from them.
+pub fn revoke_refresh_token_family(store: &dyn Store, token: &RefreshToken) {
+    if let Err(e) = store.revoke_refresh_token_family(token) {
+        error!("Failed to revoke refresh token family [{}]: {}", token.family(), e);
+    }
+}
+
+/// Fetches every Grant Type known to the provider.
+///
+/// Returns: Vec<GrantType> --- every grant type in the store
+pub fn get_grant_types(store: &dyn Store) -> Vec<GrantType> {
+    store.grant_types().unwrap_or_default() // TODO: surface store errors
+}
+
+#[cfg(all(test, feature = "memory-store"))]
+mod tests {
+    use super::*;
+    use store::StoreProvider;
+    use store::memory::tests::{client, new_refresh_token, provider};
+
+    fn is_invalid_grant(result: Result<RefreshToken, OAuth2ErrorResponse>) -> bool {
+        match result {
+            Err(OAuth2ErrorResponse::InvalidGrant) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn the_current_refresh_token_is_accepted() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let token = store.insert_refresh_token(&new_refresh_token(&client, None)).unwrap();
+
+        let found = check_refresh_token(&*store, &client, &token.token.to_string()).unwrap();
+
+        assert_eq!(found.id, token.id);
+    }
+
+    #[test]
+    fn replaying_a_rotated_refresh_token_revokes_its_family() {
+        let store = provider().get().unwrap();
+        let client = client(&*store, "first");
+        let original = store.insert_refresh_token(&new_refresh_token(&client, None)).unwrap();
+        let replacement = store
+            .rotate_refresh_token(&original, &new_refresh_token(&client, Some(original.family())))
+            .unwrap()
+            .unwrap();
+
+        let replayed = check_refresh_token(&*store, &client, &original.token.to_string());
+
+        assert!(is_invalid_grant(replayed));
+        assert!(check_refresh_token(&*store, &client, &replacement.token.to_string()).is_err());
+    }
 }
@@ -0,0 +1,150 @@
+//! The utils::pkce module implements Proof Key for Code Exchange, as described
+//! in RFC 7636. Challenges are attached to authorization codes when they are
+//! issued, and checked against the verifier when the code is redeemed.
+
+use base64;
+use models::db::AuthCode;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use sha2::{Digest, Sha256};
+use utils::constant_time_eq;
+
+/// The transformation applied when no `code_challenge_method` is provided.
+pub const DEFAULT_METHOD: &str = "plain";
+
+/// Checks that a value only uses the characters and length allowed for code
+/// verifiers (and, by extension, `plain` / `S256` code challenges).
+///
+/// See: https://tools.ietf.org/html/rfc7636#section-4.1
+fn check_syntax(value: &str) -> bool {
+    value.len() >= 43 && value.len() <= 128 && value.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_' || c == '~'
+    })
+}
+
+/// Validates a code challenge sent with an authorization request.
+///
+/// Returns: Result<String, OAuth2Error>
+/// - Ok(String)       --- the challenge method to store alongside the code
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_code_challenge(
+    challenge: &str,
+    method: Option<&str>,
+) -> Result<String, OAuth2ErrorResponse> {
+    let method = method.unwrap_or(DEFAULT_METHOD);
+    if method != "plain" && method != "S256" {
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+
+    if !check_syntax(challenge) {
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+
+    Ok(method.to_owned())
+}
+
+/// Verifies a code verifier against the challenge stored with an
+/// authorization code. Codes issued without a challenge must not be redeemed
+/// with a verifier, so a verifier is only accepted when a challenge exists.
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the verifier matches (or neither was provided)
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_code_verifier(
+    code: &AuthCode,
+    verifier: Option<&str>,
+) -> Result<(), OAuth2ErrorResponse> {
+    let (challenge, verifier) = match (code.code_challenge.as_ref(), verifier) {
+        (None, None) => return Ok(()),
+        (Some(challenge), Some(verifier)) => (challenge, verifier),
+        _ => return Err(OAuth2ErrorResponse::InvalidGrant),
+    };
+
+    if !check_syntax(verifier) {
+        return Err(OAuth2ErrorResponse::InvalidGrant);
+    }
+
+    let method = code.code_challenge_method
+        .as_ref()
+        .map(|m| m.as_str())
+        .unwrap_or(DEFAULT_METHOD);
+    let computed = match method {
+        "S256" => base64::encode_config(&Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD),
+        "plain" => verifier.to_owned(),
+        _ => return Err(OAuth2ErrorResponse::InvalidGrant),
+    };
+
+    if constant_time_eq(computed.as_bytes(), challenge.as_bytes()) {
+        Ok(())
+    } else {
+        debug!("PKCE code verifier mismatch.");
+        Err(OAuth2ErrorResponse::InvalidGrant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::offset::Utc;
+
+    // The example from RFC 7636, Appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const S256_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    fn auth_code(challenge: Option<&str>, method: Option<&str>) -> AuthCode {
+        AuthCode {
+            id: 1,
+            client_id: 1,
+            name: "code".to_owned(),
+            scope: "read".to_owned(),
+            expires_at: Utc::now().naive_utc(),
+            redirect_uri: "https://client.example/cb".to_owned(),
+            user_id: Some(1),
+            code_challenge: challenge.map(String::from),
+            code_challenge_method: method.map(String::from),
+        }
+    }
+
+    #[test]
+    fn challenges_default_to_plain() {
+        assert_eq!(check_code_challenge(VERIFIER, None).unwrap(), "plain");
+        assert_eq!(check_code_challenge(S256_CHALLENGE, Some("S256")).unwrap(), "S256");
+    }
+
+    #[test]
+    fn malformed_challenges_are_rejected() {
+        assert!(check_code_challenge(S256_CHALLENGE, Some("S512")).is_err());
+        assert!(check_code_challenge("too-short", Some("S256")).is_err());
+        assert!(check_code_challenge(&format!("{}+", S256_CHALLENGE), None).is_err());
+    }
+
+    #[test]
+    fn s256_verifiers_are_hashed() {
+        let code = auth_code(Some(S256_CHALLENGE), Some("S256"));
+
+        assert!(check_code_verifier(&code, Some(VERIFIER)).is_ok());
+        assert!(check_code_verifier(&code, Some(S256_CHALLENGE)).is_err());
+    }
+
+    #[test]
+    fn plain_verifiers_are_compared_as_is() {
+        let code = auth_code(Some(VERIFIER), None);
+
+        assert!(check_code_verifier(&code, Some(VERIFIER)).is_ok());
+        assert!(check_code_verifier(&code, Some(S256_CHALLENGE)).is_err());
+    }
+
+    #[test]
+    fn verifiers_must_match_the_presence_of_a_challenge() {
+        assert!(check_code_verifier(&auth_code(None, None), None).is_ok());
+        assert!(check_code_verifier(&auth_code(None, None), Some(VERIFIER)).is_err());
+        assert!(check_code_verifier(&auth_code(Some(S256_CHALLENGE), Some("S256")), None).is_err());
+    }
+
+    #[test]
+    fn malformed_verifiers_are_rejected() {
+        let short = &VERIFIER[..42];
+        let code = auth_code(Some(short), None);
+
+        assert!(check_code_verifier(&code, Some(short)).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! The utils::registration module implements Dynamic Client Registration, as
+//! described in RFC 7591, and the management of registrations described in
+//! RFC 7592. Registered metadata is validated here, and stored alongside the
+//! client so it can be read back later. The endpoints then hold clients to it:
+//! to their grant types, redirect URIs and authentication method, and to
+//! their scope (see `utils::check_client_scope`).
+
+use SETTINGS;
+use bcrypt;
+use chrono::offset::Utc;
+use models::db::*;
+use models::requests::register::ClientRegistrationRequest;
+use models::responses::client_information::{ClientInformationResponse,
+                                            ClientInformationResponseBuilder};
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use store::Store;
+use serde_json;
+use url::Url;
+use utils;
+use utils::authorize::check_scope_syntax;
+use utils::client_auth::{DEFAULT_AUTH_METHOD, SUPPORTED_AUTH_METHODS, TLS_AUTH_METHODS};
+use utils::grant;
+use utils::jwk::JwkSet;
+use utils::token::{JWT_BEARER_GRANT_TYPE, TOKEN_EXCHANGE_GRANT_TYPE};
+use uuid::Uuid;
+
+/// Checks that a redirect URI is absolute, and does not include a fragment.
+///
+/// See: https://tools.ietf.org/html/rfc6749#section-3.1.2
+pub fn check_redirect_uri_syntax(uri: &str) -> bool {
+    match Url::parse(uri) {
+        Ok(url) => url.fragment().is_none(),
+        Err(_) => false,
+    }
+}
+
+/// Checks the initial access token sent with a registration request, if the
+/// provider is configured to require one.
+///
+/// Returns: Result<(), OAuth2Error>
+/// - Ok(())           --- the request may register a client
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_initial_access_token(token: Option<&str>) -> Result<(), OAuth2ErrorResponse> {
+    let expected = match SETTINGS.oauth.registration {
+        Some(ref settings) => match settings.initial_access_token {
+            Some(ref expected) => expected,
+            None => return Ok(()),
+        },
+        None => return Err(OAuth2ErrorResponse::InvalidToken),
+    };
+
+    match token {
+        Some(token) if utils::constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(OAuth2ErrorResponse::InvalidToken),
+    }
+}
+
+/// The grant types that have a client act on behalf of resource owners
+/// without their consent. Only clients registered with an initial access
+/// token may use them.
+const PRIVILEGED_GRANT_TYPES: &[&str] = &[
+    "password",
+    JWT_BEARER_GRANT_TYPE,
+    TOKEN_EXCHANGE_GRANT_TYPE,
+];
+
+/// Whether anybody may register clients, without an initial access token.
+fn is_open_registration() -> bool {
+    SETTINGS
+        .oauth
+        .registration
+        .as_ref()
+        .map_or(false, |s| s.initial_access_token.is_none())
+}
+
+/// Registration metadata that passed validation, with defaults filled in.
+struct ClientMetadata {
+    redirect_uris: Vec<String>,
+    grant_types: Vec<GrantType>,
+    token_endpoint_auth_method: String,
+    scope: Option<String>,
+    client_name: Option<String>,
+    jwks: Option<JwkSet>,
+    tls_client_auth_subject_dn: Option<String>,
+}
+
+/// Whether clients using an authentication method are handed a secret.
+/// Public clients have nothing to authenticate with, and `private_key_jwt`
+/// and certificate clients authenticate with their own keys.
+fn issues_secret(auth_method: &str) -> bool {
+    auth_method != "none" && auth_method != "private_key_jwt"
+        && !TLS_AUTH_METHODS.contains(&auth_method)
+}
+
+/// Checks that every signing key in a JWK Set is usable, and returns how many
+/// there are.
+fn check_jwks(jwks: &JwkSet) -> Result<usize, OAuth2ErrorResponse> {
+    let signing_keys = jwks.keys
+        .iter()
+        .filter(|k| k.use_.as_ref().map_or(true, |u| u == "sig"))
+        .collect::<Vec<_>>();
+    if signing_keys.iter().any(|k| k.to_verifying_key().is_err()) {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+
+    Ok(signing_keys.len())
+}
+
+/// Validates the metadata of a registration request.
+///
+/// Returns: Result<ClientMetadata, OAuth2Error>
+/// - Ok(ClientMetadata) --- the metadata to register the client with
+/// - Err(OAuth2Error)   --- The Error value
+fn check_metadata(
+    store: &dyn Store,
+    req: ClientRegistrationRequest,
+) -> Result<ClientMetadata, OAuth2ErrorResponse> {
+    let auth_method = req.token_endpoint_auth_method
+        .unwrap_or_else(|| DEFAULT_AUTH_METHOD.to_owned());
+    if !SUPPORTED_AUTH_METHODS.contains(&auth_method.as_str()) {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+    if TLS_AUTH_METHODS.contains(&auth_method.as_str()) && SETTINGS.oauth.mtls.is_none() {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+
+    // Only grants the token endpoint actually handles can be registered
+    let mut grant_names = req.grant_types
+        .unwrap_or_else(|| vec!["authorization_code".to_owned()]);
+    grant_names.sort();
+    grant_names.dedup();
+    if is_open_registration()
+        && grant_names
+            .iter()
+            .any(|g| PRIVILEGED_GRANT_TYPES.contains(&g.as_str()))
+    {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+    let grant_types = grant::find_grant_types(store, &grant_names)
+        .map_err(|_| OAuth2ErrorResponse::InvalidClientMetadata)?;
+
+    // Clients authenticating with their own keys have to register them
+    let signing_keys = match req.jwks {
+        Some(ref jwks) => check_jwks(jwks)?,
+        None => 0,
+    };
+    if auth_method == "private_key_jwt" && signing_keys == 0 {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+
+    // Certificate clients have to register what identifies their certificate
+    let registered_certificate = match auth_method.as_str() {
+        "tls_client_auth" => req.tls_client_auth_subject_dn
+            .as_ref()
+            .map_or(false, |dn| !dn.is_empty()),
+        "self_signed_tls_client_auth" => req.jwks
+            .as_ref()
+            .map_or(false, |jwks| !jwks.certificates().is_empty()),
+        _ => true,
+    };
+    if !registered_certificate {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+
+    // Public clients have no credentials to use the client_credentials grant
+    // with
+    if auth_method == "none" && grant_names.iter().any(|g| g == "client_credentials") {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+
+    let redirect_uris = req.redirect_uris.unwrap_or_default();
+    if !redirect_uris.iter().all(|uri| check_redirect_uri_syntax(uri)) {
+        return Err(OAuth2ErrorResponse::InvalidRedirectUri);
+    }
+    if redirect_uris.is_empty() && grant_names.iter().any(|g| g == "authorization_code") {
+        return Err(OAuth2ErrorResponse::InvalidRedirectUri);
+    }
+
+    if let Some(ref scope) = req.scope {
+        if !check_scope_syntax(scope) {
+            return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+        }
+        if let Some(ref supported) = SETTINGS.oauth.scopes_supported {
+            if !scope.split(' ').all(|s| supported.iter().any(|v| v == s)) {
+                return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+            }
+        }
+    }
+
+    Ok(ClientMetadata {
+        redirect_uris,
+        grant_types,
+        token_endpoint_auth_method: auth_method,
+        scope: req.scope,
+        client_name: req.client_name,
+        jwks: req.jwks,
+        tls_client_auth_subject_dn: req.tls_client_auth_subject_dn,
+    })
+}
+
+/// The URL clients manage their registration at.
+pub fn registration_client_uri(client: &Client) -> String {
+    format!(
+        "{}/oauth/register/{}",
+        SETTINGS.issuer().trim_right_matches('/'),
+        client.identifier
+    )
+}
+
+/// The registered JWK Set, in the form it is stored in.
+fn jwks_json(metadata: &ClientMetadata) -> Option<String> {
+    metadata
+        .jwks
+        .as_ref()
+        .map(|jwks| serde_json::to_string(jwks).unwrap()) // TODO: remove unwrap
+}
+
+/// `client_secret_jwt` clients sign their assertions with their secret, so
+/// it has to be kept in a form signatures can be checked with.
+fn jwt_secret(metadata: &ClientMetadata, secret: &str) -> Option<String> {
+    if metadata.token_endpoint_auth_method == "client_secret_jwt" {
+        Some(secret.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Stores the registered redirect URIs and grant types of a client, replacing
+/// any it had.
+fn save_metadata(store: &dyn Store, client: &Client, metadata: &ClientMetadata) {
+    let registered = store.redirect_uris(client).unwrap(); // TODO: remove unwrap
+    for uri in registered
+        .iter()
+        .filter(|u| !metadata.redirect_uris.contains(&u.redirect_uri))
+    {
+        store
+            .delete_redirect_uri(client, &uri.redirect_uri)
+            .unwrap(); // TODO: remove unwrap
+    }
+    for uri in metadata
+        .redirect_uris
+        .iter()
+        .filter(|u| !registered.iter().any(|r| r.redirect_uri == **u))
+    {
+        let new_uri = NewClientRedirectUriBuilder::default()
+            .client_id(client.id)
+            .redirect_uri(uri.clone())
+            .build()
+            .unwrap(); // TODO: remove unwrap
+        store.insert_redirect_uri(&new_uri).unwrap(); // TODO: remove unwrap
+    }
+
+    store
+        .set_client_grant_types(client, &metadata.grant_types)
+        .unwrap(); // TODO: remove unwrap
+}
+
+/// Builds the response describing a client's registration. Secrets and tokens
+/// are only ever included when they have just been issued.
+fn client_information(
+    store: &dyn Store,
+    client: &Client,
+    client_secret: Option<String>,
+    registration_access_token: Option<String>,
+    issued_at: Option<i64>,
+) -> ClientInformationResponse {
+    let redirect_uris = store
+        .redirect_uris(client)
+        .unwrap() // TODO: remove unwrap
+        .into_iter()
+        .map(|u| u.redirect_uri)
+        .collect::<Vec<_>>();
+    let grant_types = store
+        .client_grant_types(client)
+        .unwrap() // TODO: remove unwrap
+        .into_iter()
+        .map(|g| g.name)
+        .collect::<Vec<_>>();
+    let jwks = client
+        .jwks
+        .as_ref()
+        .and_then(|jwks| serde_json::from_str::<JwkSet>(jwks).ok());
+
+    // A secret that never expires is signalled with 0
+    let client_secret_expires_at = client_secret.as_ref().map(|_| 0);
+
+    ClientInformationResponseBuilder::default()
+        .client_id(client.identifier.clone())
+        .client_secret(client_secret)
+        .client_id_issued_at(issued_at)
+        .client_secret_expires_at(client_secret_expires_at)
+        .redirect_uris(redirect_uris)
+        .grant_types(grant_types)
+        .token_endpoint_auth_method(client.token_endpoint_auth_method.clone())
+        .scope(client.scope.clone())
+        .client_name(client.client_name.clone())
+        .jwks(jwks)
+        .tls_client_auth_subject_dn(client.tls_client_auth_subject_dn.clone())
+        .registration_access_token(registration_access_token)
+        .registration_client_uri(registration_client_uri(client))
+        .build()
+        .unwrap() // TODO: remove unwrap
+}
+
+/// Registers a new client from a registration request.
+///
+/// Returns: Result<ClientInformationResponse, OAuth2Error>
+/// - Ok(ClientInformationResponse) --- the registered client, including its
+/// one-time plaintext secret and registration access token
+/// - Err(OAuth2Error)              --- The Error value
+pub fn register(
+    store: &dyn Store,
+    req: ClientRegistrationRequest,
+) -> Result<ClientInformationResponse, OAuth2ErrorResponse> {
+    let metadata = check_metadata(store, req)?;
+    let is_public = metadata.token_endpoint_auth_method == "none";
+    let issues_secret = issues_secret(&metadata.token_endpoint_auth_method);
+
+    // Clients that are not handed their secret cannot authenticate with it.
+    // A random one is still stored, as every client has one.
+    let secret = utils::generate_client_secret();
+    let registration_access_token = utils::generate_client_secret();
+    let new_client = NewClientBuilder::default()
+        .identifier(Uuid::new_v4().simple().to_string())
+        .secret(utils::hash_client_secret(&secret).unwrap()) // TODO: remove unwrap
+        .response_type(if is_public { "public" } else { "confidential" })
+        .require_pkce(is_public)
+        .client_name(metadata.client_name.clone())
+        .scope(metadata.scope.clone())
+        .token_endpoint_auth_method(metadata.token_endpoint_auth_method.clone())
+        .registration_access_token(Some(
+            utils::hash_client_secret(&registration_access_token).unwrap(), // TODO: remove unwrap
+        ))
+        .jwks(jwks_json(&metadata))
+        .jwt_secret(jwt_secret(&metadata, &secret))
+        .tls_client_auth_subject_dn(metadata.tls_client_auth_subject_dn.clone())
+        .build()
+        .unwrap(); // TODO: remove unwrap
+    let client = store.insert_client(&new_client).unwrap(); // TODO: remove unwrap
+    save_metadata(store, &client, &metadata);
+
+    info!(
+        "Registered client [{}] with grant types {:?}",
+        client.identifier,
+        metadata.grant_types.iter().map(|g| &g.name).collect::<Vec<_>>()
+    );
+
+    Ok(client_information(
+        store,
+        &client,
+        if issues_secret { Some(secret) } else { None },
+        Some(registration_access_token),
+        Some(Utc::now().timestamp()),
+    ))
+}
+
+/// Authenticates a request to manage a client's registration, using the
+/// registration access token issued when the client registered. Unknown
+/// clients are indistinguishable from invalid tokens.
+///
+/// Returns: Result<Client, OAuth2Error>
+/// - Ok(Client)       --- the client the token was issued to
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_registration_access_token(
+    store: &dyn Store,
+    client_id: &str,
+    token: Option<&str>,
+) -> Result<Client, OAuth2ErrorResponse> {
+    let token = token.ok_or(OAuth2ErrorResponse::InvalidToken)?;
+    let client = store
+        .find_client(client_id)
+        .ok()
+        .and_then(|c| c)
+        .ok_or(OAuth2ErrorResponse::InvalidToken)?;
+
+    let verified = match client.registration_access_token {
+        Some(ref hash) => bcrypt::verify(token, hash).unwrap_or(false),
+        None => false,
+    };
+    if !verified {
+        return Err(OAuth2ErrorResponse::InvalidToken);
+    }
+
+    Ok(client)
+}
+
+/// Reads a client's current registration.
+///
+/// Returns: ClientInformationResponse --- the registration, without any
+/// secrets
+pub fn read(store: &dyn Store, client: &Client) -> ClientInformationResponse {
+    client_information(store, client, None, None, None)
+}
+
+/// Replaces a client's registration with the metadata in the request. Fields
+/// left out of the request are reset to their defaults, and the client secret
+/// is rotated.
+///
+/// Returns: Result<ClientInformationResponse, OAuth2Error>
+/// - Ok(ClientInformationResponse) --- the updated client, including its new
+/// plaintext secret
+/// - Err(OAuth2Error)              --- The Error value
+pub fn update(
+    store: &dyn Store,
+    mut client: Client,
+    req: ClientRegistrationRequest,
+) -> Result<ClientInformationResponse, OAuth2ErrorResponse> {
+    // See: https://tools.ietf.org/html/rfc7592#section-2.2
+    if req.client_id.as_ref() != Some(&client.identifier) {
+        return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+    }
+    if let Some(ref secret) = req.client_secret {
+        if !bcrypt::verify(secret, &client.secret).unwrap_or(false) {
+            return Err(OAuth2ErrorResponse::InvalidClientMetadata);
+        }
+    }
+
+    let metadata = check_metadata(store, req)?;
+    let is_public = metadata.token_endpoint_auth_method == "none";
+    let issues_secret = issues_secret(&metadata.token_endpoint_auth_method);
+
+    let secret = utils::generate_client_secret();
+    client.secret = utils::hash_client_secret(&secret).unwrap(); // TODO: remove unwrap
+    client.response_type = if is_public { "public" } else { "confidential" }.to_owned();
+    // Public clients always need PKCE, while confidential ones keep whatever
+    // an administrator set
+    client.require_pkce = client.require_pkce || is_public;
+    client.client_name = metadata.client_name.clone();
+    client.scope = metadata.scope.clone();
+    client.token_endpoint_auth_method = metadata.token_endpoint_auth_method.clone();
+    client.jwks = jwks_json(&metadata);
+    client.jwt_secret = jwt_secret(&metadata, &secret);
+    client.tls_client_auth_subject_dn = metadata.tls_client_auth_subject_dn.clone();
+    let client = store.update_client(&client).unwrap(); // TODO: remove unwrap
+    save_metadata(store, &client, &metadata);
+
+    info!("Updated the registration of client [{}]", client.identifier);
+
+    Ok(client_information(
+        store,
+        &client,
+        if issues_secret { Some(secret) } else { None },
+        None,
+        None,
+    ))
+}
+
+/// Deletes a client, along with every token and authorization code issued to
+/// it.
+pub fn delete(store: &dyn Store, client: &Client) {
+    store.delete_client(client).unwrap(); // TODO: remove unwrap
+    info!("Deleted the registration of client [{}]", client.identifier);
+}
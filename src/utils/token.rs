@@ -3,15 +3,47 @@
 //! should be one function designed to handle a particular grant type request.
 //! Stylistically these functions are named after the grant type they
 //! are processing, and conform to the following function signature, which
-//! gives them access to the underlying datastore as well as the entire request
-//! data sent by the caller.
+//! gives them access to the underlying datastore, the entire request data sent
+//! by the caller, the client it was authenticated as (see
+//! `utils::client_auth`), the grant type the token endpoint checked the client
+//! may use, and the certificate the client presented, if any,
+//! which the issued access token is bound to. Tokens issued on behalf of a
+//! resource owner are linked to them, and so are the tokens later refreshed
+//! from them. When rotation is enabled, every refresh replaces the refresh
+//! token as well.
+//!
+//! Grants that trade a token or an assertion for another token (JWT bearer,
+//! token exchange) only ever issue an access token.
+//!
+//! Each function is wrapped in a `GrantHandler` (see `utils::grant`), which
+//! is how the token endpoint finds it.
 
-use diesel::pg::PgConnection;
+use SETTINGS;
+use models::configuration::SubjectType;
 use models::requests::access_token::AccessTokenRequest;
 use models::responses::access_token::AccessTokenResponse;
 use models::responses::oauth2_error::OAuth2ErrorResponse;
+use store::Store;
 use utils;
-use web::headers::authorization_token::AuthorizationToken;
+use models::db::{Client, GrantType};
+use utils::device;
+use utils::grant::GrantHandler;
+use utils::jwt_bearer;
+use utils::pkce;
+use utils::token_exchange::{self, Exchange};
+use web::headers::client_certificate::ClientCertificate;
+
+/// The grant type devices poll the token endpoint with. See RFC 8628
+/// section 3.4.
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// The grant type assertions from trusted issuers are exchanged with. See
+/// RFC 7523 section 2.1.
+pub const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// The grant type tokens are traded for downscoped ones with. See RFC 8693
+/// section 2.1.
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
 
 /// Processes a `client_credentials` request, and returns a Result on whether
 /// or not it was successful.
@@ -21,18 +53,17 @@ use web::headers::authorization_token::AuthorizationToken;
 /// - Err(OAuth2Error) prefilled with an error message if something
 /// went wrong.
 pub fn client_credentials(
-    conn: &PgConnection,
+    store: &dyn Store,
     req: AccessTokenRequest,
-    auth: AuthorizationToken,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
 ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
     // Requests missing a scope are pretty bogus
     if req.scope.is_none() {
         return Err(OAuth2ErrorResponse::InvalidRequest);
     }
 
-    // Ensure the client information from the request is valid
-    let client = utils::check_client_credentials(conn, &auth.user, &auth.pass)?;
-
     // Is the client a `confidential` client?
     // TODO: This works for now but we should do something better than string
     // checks directly.
@@ -40,13 +71,326 @@ pub fn client_credentials(
         return Err(OAuth2ErrorResponse::UnauthorizedClient);
     }
 
-    // Ensure valid grant type
-    let grant_type = utils::check_grant_type(conn, &req.grant_type.unwrap())?; // TODO: remove unwrap
-
     let scope = &req.scope.unwrap(); // TODO: remove unwrap
-    let at = utils::generate_access_token(conn, &client, &grant_type, scope);
-    let rt = utils::generate_refresh_token(conn, &client, scope);
-    Ok(utils::generate_token_response(at, Some(rt)))
+    utils::check_client_scope(&client, scope)?;
+    let rt = utils::generate_refresh_token(store, &client, None, scope);
+    let at = utils::generate_access_token(
+        store,
+        &client,
+        None,
+        &grant_type,
+        scope,
+        Some(&rt),
+        certificate.map(|c| c.thumbprint()),
+    );
+    Ok(utils::generate_token_response(&client, None, at, Some(rt)))
+}
+
+/// Processes a `password` request, and returns a Result on whether or not it
+/// was successful. The resource owner's credentials are checked against the
+/// store's `UserStore`.
+///
+/// Returns: Result<AccessTokenResponse, OAuth2Error>
+///          - Ok(AccessTokenResponse) if the request was accepted
+/// - Err(OAuth2Error) prefilled with an error message if something
+/// went wrong.
+pub fn password(
+    store: &dyn Store,
+    req: AccessTokenRequest,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
+) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+    // Requests missing a scope are pretty bogus, as they are for
+    // client_credentials
+    let (username, password, scope) = match (req.username, req.password, req.scope) {
+        (Some(username), Some(password), Some(scope)) => (username, password, scope),
+        _ => return Err(OAuth2ErrorResponse::InvalidRequest),
+    };
+    utils::check_client_scope(&client, &scope)?;
+
+    // Unknown users and wrong passwords are indistinguishable to the caller
+    let user = utils::check_user_credentials(store, &username, &password).map_err(|e| {
+        info!(
+            "Client [{}] failed to authenticate user [{}]",
+            client.identifier, username
+        );
+        e
+    })?;
+
+    let rt = utils::generate_refresh_token(store, &client, Some(&user), &scope);
+    let at = utils::generate_access_token(
+        store,
+        &client,
+        Some(&user),
+        &grant_type,
+        &scope,
+        Some(&rt),
+        certificate.map(|c| c.thumbprint()),
+    );
+    Ok(utils::generate_token_response(&client, Some(&user), at, Some(rt)))
+}
+
+/// Processes an `authorization_code` request, and returns a Result on whether
+/// or not it was successful.
+///
+/// Returns: Result<AccessTokenResponse, OAuth2Error>
+///          - Ok(AccessTokenResponse) if the request was accepted
+/// - Err(OAuth2Error) prefilled with an error message if something
+/// went wrong.
+pub fn authorization_code(
+    store: &dyn Store,
+    req: AccessTokenRequest,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
+) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+    // Both the code and the redirect URI it was issued against are required
+    let (code, redirect_uri) = match (req.code.clone(), req.redirect_uri.clone()) {
+        (Some(code), Some(redirect_uri)) => (code, redirect_uri),
+        _ => return Err(OAuth2ErrorResponse::InvalidRequest),
+    };
+
+    // Public clients can only identify themselves, and have to prove they own
+    // the code through PKCE.
+    if client.token_endpoint_auth_method == "none"
+        && (client.response_type == "confidential" || req.code_verifier.is_none())
+    {
+        return Err(OAuth2ErrorResponse::InvalidClient);
+    }
+
+    // The scope was fixed when the code was issued, so we use it as-is
+    let auth_code = utils::redeem_auth_code(store, &client, &code, &redirect_uri)?;
+    pkce::check_code_verifier(&auth_code, req.code_verifier.as_ref().map(|v| v.as_str()))?;
+    utils::check_client_scope(&client, &auth_code.scope)?;
+    let user = utils::find_token_user(store, auth_code.user_id)?;
+
+    let rt = utils::generate_refresh_token(store, &client, user.as_ref(), &auth_code.scope);
+    let at = utils::generate_access_token(
+        store,
+        &client,
+        user.as_ref(),
+        &grant_type,
+        &auth_code.scope,
+        Some(&rt),
+        certificate.map(|c| c.thumbprint()),
+    );
+    Ok(utils::generate_token_response(&client, user.as_ref(), at, Some(rt)))
+}
+
+/// Processes a device code request, and returns a Result on whether or not it
+/// was successful. Until the resource owner has approved the device, this
+/// returns one of the errors telling the device to keep polling.
+///
+/// Returns: Result<AccessTokenResponse, OAuth2Error>
+///          - Ok(AccessTokenResponse) if the request was accepted
+/// - Err(OAuth2Error) prefilled with an error message if something
+/// went wrong.
+pub fn device_code(
+    store: &dyn Store,
+    req: AccessTokenRequest,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
+) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+    if SETTINGS.oauth.device.is_none() {
+        return Err(OAuth2ErrorResponse::UnsupportedGrantType);
+    }
+
+    let device_code = req.device_code.ok_or(OAuth2ErrorResponse::InvalidRequest)?;
+
+    // The scope was fixed when the device requested its codes, so we use it
+    // as-is
+    let code = device::poll_device_code(store, &client, &device_code)?;
+    utils::check_client_scope(&client, &code.scope)?;
+    let user = utils::find_token_user(store, code.user_id)?;
+
+    let rt = utils::generate_refresh_token(store, &client, user.as_ref(), &code.scope);
+    let at = utils::generate_access_token(
+        store,
+        &client,
+        user.as_ref(),
+        &grant_type,
+        &code.scope,
+        Some(&rt),
+        certificate.map(|c| c.thumbprint()),
+    );
+    Ok(utils::generate_token_response(&client, user.as_ref(), at, Some(rt)))
+}
+
+/// Processes a JWT bearer request, and returns a Result on whether or not it
+/// was successful. Depending on the issuer of the assertion, its subject is
+/// either the client the token is issued to, which needs no other credentials,
+/// or a user the authenticated client acts for. Only an access token is
+/// issued, as the client can always present a fresh assertion. Authenticated
+/// clients come with the grant type the token endpoint checked them for.
+///
+/// Returns: Result<AccessTokenResponse, OAuth2Error>
+///          - Ok(AccessTokenResponse) if the request was accepted
+/// - Err(OAuth2Error) prefilled with an error message if something
+/// went wrong.
+pub fn jwt_bearer(
+    store: &dyn Store,
+    req: AccessTokenRequest,
+    client: Option<(Client, GrantType)>,
+    certificate: Option<&ClientCertificate>,
+) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+    let assertion = req.assertion.ok_or(OAuth2ErrorResponse::InvalidRequest)?;
+    let (issuer, claims) = jwt_bearer::verify_assertion(&assertion)?;
+
+    let (client, grant_type, user) = match issuer.subject {
+        SubjectType::Client => {
+            let subject = utils::get_client_by_identifier(store, &claims.sub)
+                .map_err(|_| OAuth2ErrorResponse::InvalidGrant)?;
+            match client {
+                // A client that did authenticate may only present its own
+                // assertions
+                Some((client, grant_type)) => {
+                    if client.id != subject.id {
+                        return Err(OAuth2ErrorResponse::InvalidGrant);
+                    }
+                    (client, grant_type, None)
+                }
+                // Otherwise the assertion identified the client, which the
+                // token endpoint could not check
+                None => {
+                    let grant_type =
+                        utils::check_grant_type(store, &subject, JWT_BEARER_GRANT_TYPE)?;
+                    (subject, grant_type, None)
+                }
+            }
+        }
+        SubjectType::User => {
+            let (client, grant_type) = client.ok_or(OAuth2ErrorResponse::InvalidClient)?;
+            // Anybody can claim to be a public client, so they would let
+            // whoever holds the assertion act on the user's behalf
+            if client.token_endpoint_auth_method == "none" {
+                return Err(OAuth2ErrorResponse::UnauthorizedClient);
+            }
+            let user = store
+                .user_store()
+                .find_user(&claims.sub)
+                .ok()
+                .and_then(|u| u)
+                .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+            (client, grant_type, Some(user))
+        }
+    };
+
+    // The issuer's policy caps the scope, which requests may narrow down
+    let scope = match req.scope {
+        Some(ref scope) => utils::check_scope(store, scope, &issuer.scope)?,
+        None => issuer.scope.clone(),
+    };
+    utils::check_client_scope(&client, &scope)?;
+    jwt_bearer::record_assertion(store, &claims)?;
+    info!(
+        "Client [{}] exchanged an assertion of issuer [{}] for subject [{}]",
+        client.identifier, issuer.issuer, claims.sub
+    );
+
+    let at = utils::generate_access_token(
+        store,
+        &client,
+        user.as_ref(),
+        &grant_type,
+        &scope,
+        None,
+        certificate.map(|c| c.thumbprint()),
+    );
+    Ok(utils::generate_token_response(&client, user.as_ref(), at, None))
+}
+
+/// Processes a token exchange request, and returns a Result on whether or not
+/// it was successful. The subject token must have been issued on behalf of a
+/// resource owner, whom the new token is issued for too, with at most the
+/// scope of the subject token. The authenticated client is the one the new
+/// token is issued to.
+///
+/// Returns: Result<AccessTokenResponse, OAuth2Error>
+///          - Ok(AccessTokenResponse) if the request was accepted
+/// - Err(OAuth2Error) prefilled with an error message if something
+/// went wrong.
+pub fn token_exchange(
+    store: &dyn Store,
+    req: AccessTokenRequest,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
+) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+    if SETTINGS.oauth.token_exchange.is_none() {
+        return Err(OAuth2ErrorResponse::UnsupportedGrantType);
+    }
+
+    // Clients that can only identify themselves have no business trading
+    // other parties' tokens
+    if client.token_endpoint_auth_method == "none" {
+        return Err(OAuth2ErrorResponse::UnauthorizedClient);
+    }
+
+    let (subject_token, subject_token_type) = match (req.subject_token, req.subject_token_type) {
+        (Some(token), Some(token_type)) => (token, token_type),
+        _ => return Err(OAuth2ErrorResponse::InvalidRequest),
+    };
+    if req.requested_token_type
+        .as_ref()
+        .map_or(false, |t| t != token_exchange::ACCESS_TOKEN_TYPE)
+    {
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+
+    let thumbprint = certificate.map(|c| c.thumbprint());
+    let subject =
+        token_exchange::resolve_token(store, &client, &subject_token, &subject_token_type)?;
+    subject.check_binding(thumbprint.as_ref().map(|v| v.as_str()))?;
+    let actor = match (req.actor_token, req.actor_token_type) {
+        (Some(token), Some(token_type)) => Some(token_exchange::resolve_token(
+            store,
+            &client,
+            &token,
+            &token_type,
+        )?),
+        (None, None) => None,
+        _ => return Err(OAuth2ErrorResponse::InvalidRequest),
+    };
+    if let Some(ref actor) = actor {
+        actor.check_binding(thumbprint.as_ref().map(|v| v.as_str()))?;
+    }
+    let audience = token_exchange::check_targets(
+        req.audience.as_ref().map(|v| v.as_str()),
+        req.resource.as_ref().map(|v| v.as_str()),
+    )?;
+
+    // The new token may only narrow down the subject token's scope
+    let scope = match req.scope {
+        Some(ref scope) => utils::check_scope(store, scope, &subject.scope)?,
+        None => subject.scope.clone(),
+    };
+    utils::check_client_scope(&client, &scope)?;
+    let act = token_exchange::delegate(&subject, actor.as_ref());
+    let user = subject.user.ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+    info!(
+        "Client [{}] exchanged a token of user [{}]{}",
+        client.identifier,
+        user.username,
+        act.as_ref()
+            .map_or_else(String::new, |a| format!(", acting as [{}]", a.sub))
+    );
+
+    let exchange = Exchange {
+        user,
+        scope,
+        audience,
+        act,
+        subject_token_id: subject.access_token_id,
+        refresh_token_id: subject.refresh_token_id,
+        expires_at: subject.expires_at,
+    };
+    let at =
+        token_exchange::generate_access_token(store, &client, &grant_type, &exchange, thumbprint);
+    let mut response = utils::generate_token_response(&client, Some(&exchange.user), at, None);
+    response.issued_token_type = Some(token_exchange::ACCESS_TOKEN_TYPE.to_string());
+    Ok(response)
 }
 
 /// Processes a `refresh_token` request, and returns a Result on whether or not
@@ -57,9 +401,11 @@ pub fn client_credentials(
 /// - Err(OAuth2Error) prefilled with an error message if something
 /// went wrong.
 pub fn refresh_token(
-    conn: &PgConnection,
+    store: &dyn Store,
     req: AccessTokenRequest,
-    auth: AuthorizationToken,
+    client: Client,
+    grant_type: GrantType,
+    certificate: Option<&ClientCertificate>,
 ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
     // If we arent given the required params in the payload, we can immediately
     // respond with `invalid_request`
@@ -67,19 +413,225 @@ pub fn refresh_token(
         return Err(OAuth2ErrorResponse::InvalidRequest);
     }
 
-    // Fetch the building blocks using request data. This means the client, refresh
-    // token, and scope. For the client and refresh token, we should be able to
-    // get hits out of the database.
-    let client = utils::check_client_credentials(conn, &auth.user, &auth.pass)?;
+    // Fetch the building blocks using request data. This means the refresh
+    // token, and scope. For the refresh token, we should be able to get a hit
+    // out of the database.
     let refresh_token =
-        utils::check_refresh_token(conn, &client, &req.refresh_token.clone().unwrap())?; // TODO: Remove unwrap
-    let scope = utils::check_scope(conn, &req.scope.unwrap(), &refresh_token.scope.clone())?; // TODO: Remove unwrap
+        utils::check_refresh_token(store, &client, &req.refresh_token.clone().unwrap())?; // TODO: Remove unwrap
+    let scope = utils::check_scope(store, &req.scope.unwrap(), &refresh_token.scope.clone())?; // TODO: Remove unwrap
+    utils::check_client_scope(&client, &scope)?;
+    let user = utils::find_token_user(store, refresh_token.user_id)?;
+
+    // The replacement keeps the scope of the original grant, not the narrower
+    // one requested here
+    let refresh_token = if SETTINGS.oauth.rotate_refresh_tokens {
+        utils::rotate_refresh_token(store, &client, user.as_ref(), &refresh_token)?
+    } else {
+        refresh_token
+    };
 
     // The request appears valid. Generate an access token and reply with it.
-    let grant_type = utils::get_grant_type_by_name(conn, "refresh_token");
-    let access_token = utils::generate_access_token(conn, &client, &grant_type, &scope);
+    let access_token = utils::generate_access_token(
+        store,
+        &client,
+        user.as_ref(),
+        &grant_type,
+        &scope,
+        Some(&refresh_token),
+        certificate.map(|c| c.thumbprint()),
+    );
     Ok(utils::generate_token_response(
+        &client,
+        user.as_ref(),
         access_token,
         Some(refresh_token),
     ))
 }
+
+pub struct AuthorizationCodeGrant;
+
+impl GrantHandler for AuthorizationCodeGrant {
+    fn grant_type(&self) -> &str {
+        "authorization_code"
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        authorization_code(store, req, client, grant_type, certificate)
+    }
+}
+
+pub struct ClientCredentialsGrant;
+
+impl GrantHandler for ClientCredentialsGrant {
+    fn grant_type(&self) -> &str {
+        "client_credentials"
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        client_credentials(store, req, client, grant_type, certificate)
+    }
+}
+
+pub struct PasswordGrant;
+
+impl GrantHandler for PasswordGrant {
+    fn grant_type(&self) -> &str {
+        "password"
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        password(store, req, client, grant_type, certificate)
+    }
+}
+
+pub struct RefreshTokenGrant;
+
+impl GrantHandler for RefreshTokenGrant {
+    fn grant_type(&self) -> &str {
+        "refresh_token"
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        refresh_token(store, req, client, grant_type, certificate)
+    }
+}
+
+pub struct DeviceCodeGrant;
+
+impl GrantHandler for DeviceCodeGrant {
+    fn grant_type(&self) -> &str {
+        DEVICE_CODE_GRANT_TYPE
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        device_code(store, req, client, grant_type, certificate)
+    }
+}
+
+pub struct JwtBearerGrant;
+
+impl GrantHandler for JwtBearerGrant {
+    fn grant_type(&self) -> &str {
+        JWT_BEARER_GRANT_TYPE
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        jwt_bearer(store, req, Some((client, grant_type)), certificate)
+    }
+
+    /// An assertion whose subject is a client authenticates that client by
+    /// itself, so the grant may be used without credentials.
+    fn handle_unauthenticated(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        jwt_bearer(store, req, None, certificate)
+    }
+}
+
+pub struct TokenExchangeGrant;
+
+impl GrantHandler for TokenExchangeGrant {
+    fn grant_type(&self) -> &str {
+        TOKEN_EXCHANGE_GRANT_TYPE
+    }
+
+    fn handle(
+        &self,
+        store: &dyn Store,
+        req: AccessTokenRequest,
+        client: Client,
+        grant_type: GrantType,
+        certificate: Option<&ClientCertificate>,
+    ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
+        token_exchange(store, req, client, grant_type, certificate)
+    }
+}
+
+#[cfg(all(test, feature = "memory-store"))]
+mod tests {
+    use super::*;
+    use serde_json;
+    use store::StoreProvider;
+    use store::memory::tests::{client, fixture_client, seeded};
+
+    fn is_invalid_scope<T>(result: Result<T, OAuth2ErrorResponse>) -> bool {
+        match result {
+            Err(OAuth2ErrorResponse::InvalidScope) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn clients_may_only_ask_for_their_registered_scope() {
+        let mut registered = fixture_client("client");
+        registered.scope = Some("read write".to_owned());
+        let store = seeded(vec![registered]).get().unwrap();
+        let client = client(&*store, "client");
+
+        assert!(utils::check_client_scope(&client, "read").is_ok());
+        assert!(utils::check_client_scope(&client, "write read").is_ok());
+        assert!(is_invalid_scope(utils::check_client_scope(&client, "read admin")));
+    }
+
+    #[test]
+    fn client_credentials_requests_beyond_the_registered_scope_are_refused() {
+        let mut registered = fixture_client("client");
+        registered.scope = Some("read".to_owned());
+        registered.grant_types = vec!["client_credentials".to_owned()];
+        let store = seeded(vec![registered]).get().unwrap();
+        let client = client(&*store, "client");
+        let grant_type = utils::check_grant_type(&*store, &client, "client_credentials").unwrap();
+        let req: AccessTokenRequest =
+            serde_json::from_str(r#"{"grant_type": "client_credentials", "scope": "read admin"}"#)
+                .unwrap();
+
+        let result = client_credentials(&*store, req, client, grant_type, None);
+
+        assert!(is_invalid_scope(result));
+    }
+}
@@ -0,0 +1,260 @@
+//! The utils::token_exchange module resolves the tokens presented with the
+//! token exchange grant, as described in RFC 8693. The subject token is traded
+//! for an access token aimed at one of the services configured under
+//! `[oauth.token_exchange]`. When an actor token is presented too, the actor
+//! is recorded in the `act` claim of the new token (delegation); otherwise the
+//! new token simply stands in for the subject (impersonation). Either way, the
+//! parties that acted on behalf of the subject before are kept, nested in the
+//! `act` claim, so that introspection can return the whole chain.
+//!
+//! An exchanged token never outlives its subject token, and is revoked along
+//! with it.
+
+use SETTINGS;
+use chrono::{Duration, NaiveDateTime};
+use chrono::offset::Utc;
+use models::claims::Actor;
+use models::db::*;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use serde_json;
+use std::cmp::min;
+use store::Store;
+use utils;
+use uuid::Uuid;
+
+/// The token type of access tokens. It is the only type issued.
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// The token type of refresh tokens, which clients may present as subject or
+/// actor tokens, but only their own.
+pub const REFRESH_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:refresh_token";
+
+/// A subject or actor token, resolved to the parties it was issued for.
+#[derive(Debug)]
+pub struct PresentedToken {
+    /// The client the token was issued to.
+    pub client: Client,
+    /// The resource owner the token was issued on behalf of, if any.
+    pub user: Option<User>,
+    pub scope: String,
+    /// The parties already acting on behalf of the token's subject.
+    pub act: Option<Actor>,
+    /// The id of the access token presented, if it was one.
+    pub access_token_id: Option<i32>,
+    /// The refresh token the presented token was issued from, or is.
+    pub refresh_token_id: Option<i32>,
+    /// The thumbprint of the certificate the token is bound to, if any.
+    pub cert_thumbprint: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl PresentedToken {
+    /// Checks that a token bound to a certificate is presented over a
+    /// connection authenticated with that very certificate (RFC 8705 section
+    /// 3).
+    ///
+    /// Returns: Result<(), OAuth2Error>
+    /// - Ok(())           --- the token is unbound, or the thumbprints match
+    /// - Err(OAuth2Error) --- The Error value
+    pub fn check_binding(&self, thumbprint: Option<&str>) -> Result<(), OAuth2ErrorResponse> {
+        match self.cert_thumbprint {
+            Some(ref bound) if Some(bound.as_str()) != thumbprint => {
+                debug!("Presented token is bound to another certificate.");
+                Err(OAuth2ErrorResponse::InvalidGrant)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The name the token's subject goes by in an `act` claim: the resource
+    /// owner's username, or the client identifier if there is none.
+    fn subject(&self) -> String {
+        self.user
+            .as_ref()
+            .map_or_else(|| self.client.identifier.clone(), |u| u.username.clone())
+    }
+}
+
+/// Everything an exchanged access token is issued with.
+#[derive(Debug)]
+pub struct Exchange {
+    pub user: User,
+    pub scope: String,
+    /// The services the token is aimed at, as a space delimited list.
+    pub audience: String,
+    pub act: Option<Actor>,
+    /// The access token exchanged, if the subject token was one.
+    pub subject_token_id: Option<i32>,
+    /// The refresh token the subject token was issued from, or is.
+    pub refresh_token_id: Option<i32>,
+    /// When the subject token expires, if it does.
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Resolves a subject or actor token of the given type. Access tokens are
+/// accepted whichever client they were issued to, as long as they are active.
+///
+/// Returns: Result<PresentedToken, OAuth2Error>
+/// - Ok(PresentedToken) --- the token is valid
+/// - Err(OAuth2Error)   --- The Error value
+pub fn resolve_token(
+    store: &dyn Store,
+    client: &Client,
+    token: &str,
+    token_type: &str,
+) -> Result<PresentedToken, OAuth2ErrorResponse> {
+    match token_type {
+        ACCESS_TOKEN_TYPE => {
+            let access_token = utils::parse_access_token(token)
+                .and_then(|t| store.find_access_token(&t).ok().and_then(|t| t))
+                .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+            if access_token.revoked_at.is_some()
+                || access_token
+                    .expires_at
+                    .signed_duration_since(Utc::now().naive_utc())
+                    .num_seconds() <= 0
+            {
+                debug!("Presented access token is not active.");
+                return Err(OAuth2ErrorResponse::InvalidGrant);
+            }
+
+            let owner = store
+                .find_client_by_id(access_token.client_id)
+                .ok()
+                .and_then(|c| c)
+                .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+            Ok(PresentedToken {
+                client: owner,
+                user: utils::find_token_user(store, access_token.user_id)?,
+                act: utils::actor(&access_token),
+                access_token_id: Some(access_token.id),
+                refresh_token_id: access_token.refresh_token_id,
+                cert_thumbprint: access_token.cert_thumbprint,
+                expires_at: Some(access_token.expires_at),
+                scope: access_token.scope,
+            })
+        }
+        REFRESH_TOKEN_TYPE => {
+            let token = Uuid::parse_str(token).map_err(|_| OAuth2ErrorResponse::InvalidGrant)?;
+            let refresh_token = store
+                .find_refresh_token(client, &token)
+                .ok()
+                .and_then(|t| t)
+                .ok_or(OAuth2ErrorResponse::InvalidGrant)?;
+            if let Some(expiry) = refresh_token.expires_at {
+                if expiry
+                    .signed_duration_since(Utc::now().naive_utc())
+                    .num_seconds() <= 0
+                {
+                    debug!("Presented refresh token is expired.");
+                    return Err(OAuth2ErrorResponse::InvalidGrant);
+                }
+            }
+
+            Ok(PresentedToken {
+                client: client.clone(),
+                user: utils::find_token_user(store, refresh_token.user_id)?,
+                act: None,
+                access_token_id: None,
+                refresh_token_id: Some(refresh_token.id),
+                cert_thumbprint: None,
+                expires_at: refresh_token.expires_at,
+                scope: refresh_token.scope,
+            })
+        }
+        _ => Err(OAuth2ErrorResponse::InvalidRequest),
+    }
+}
+
+/// Builds the `act` claim of an exchanged token. With an actor, the actor is
+/// the one acting now, and the subject's own actors are nested in it.
+/// Without one, the subject's actors carry over unchanged.
+pub fn delegate(subject: &PresentedToken, actor: Option<&PresentedToken>) -> Option<Actor> {
+    match actor {
+        Some(actor) => Some(Actor {
+            sub: actor.subject(),
+            act: subject.act.clone().map(Box::new),
+        }),
+        None => subject.act.clone(),
+    }
+}
+
+/// Validates the requested audience and resource against the configured
+/// targets. At least one of them is required, so that exchanged tokens are
+/// always aimed at a specific service.
+///
+/// Returns: Result<String, OAuth2Error>
+/// - Ok(String)       --- the targets, as a space delimited list
+/// - Err(OAuth2Error) --- The Error value
+pub fn check_targets(
+    audience: Option<&str>,
+    resource: Option<&str>,
+) -> Result<String, OAuth2ErrorResponse> {
+    let settings = SETTINGS
+        .oauth
+        .token_exchange
+        .as_ref()
+        .ok_or(OAuth2ErrorResponse::UnsupportedGrantType)?;
+
+    let mut targets: Vec<&str> = Vec::new();
+    for target in audience.into_iter().chain(resource) {
+        if !settings.audiences.iter().any(|a| a == target) {
+            debug!("Token exchange target [{}] is not configured.", target);
+            return Err(OAuth2ErrorResponse::InvalidTarget);
+        }
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    if targets.is_empty() {
+        debug!("Token exchange request names neither an audience nor a resource.");
+        return Err(OAuth2ErrorResponse::InvalidRequest);
+    }
+
+    Ok(targets.join(" "))
+}
+
+/// Generates an exchanged AccessToken. Unlike other access tokens, it is aimed
+/// at specific services, and may record the parties acting on behalf of the
+/// resource owner. It expires no later than the subject token, and is linked
+/// to the subject token, and to the refresh token the subject token came from,
+/// so that revoking either revokes it too.
+///
+/// Returns: AccessToken --- the AccessToken to send back to the caller
+pub fn generate_access_token(
+    store: &dyn Store,
+    c: &Client,
+    g: &GrantType,
+    exchange: &Exchange,
+    cert_thumbprint: Option<String>,
+) -> AccessToken {
+    let act = exchange
+        .act
+        .as_ref()
+        .map(|a| serde_json::to_string(a).unwrap()); // TODO: remove unwrap
+    let mut new_token = utils::new_access_token(
+        c,
+        Some(&exchange.user),
+        g,
+        &exchange.scope,
+        None,
+        cert_thumbprint,
+    );
+    if let Some(expiry) = exchange.expires_at {
+        let ttl_expiry =
+            Utc::now().naive_utc() + Duration::seconds(SETTINGS.oauth.access_token_ttl);
+        new_token.expires_at(min(ttl_expiry, expiry));
+    }
+    let new_token = new_token
+        .audience(Some(exchange.audience.clone()))
+        .act(act)
+        .subject_token_id(exchange.subject_token_id)
+        .refresh_token_id(exchange.refresh_token_id)
+        .build()
+        .unwrap(); // TODO: remove unwrap
+
+    store
+        .insert_access_token(&new_token)
+        .unwrap() // TODO: remove unwrap
+}
@@ -0,0 +1,121 @@
+use STORE;
+use models::db::Client;
+use models::requests::authorize::{AuthorizationConsentRequest, AuthorizationRequest};
+use models::responses::authorization_code::AuthorizationCodeResponse;
+use models::responses::authorization_error::AuthorizationErrorResponse;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::http::{Cookie, Cookies, SameSite};
+use rocket::request::Form;
+use utils;
+use utils::authorize::CONSENT_COOKIE;
+use web::views;
+
+/// Renders the consent page for a request, and sets the cookie its form has to
+/// be posted alongside. Rendering the page again replaces the cookie.
+fn consent_page(
+    cookies: &mut Cookies,
+    client: &Client,
+    req: &AuthorizationRequest,
+    error: Option<&str>,
+) -> views::Page {
+    let (token, digest) = utils::authorize::consent_token(req);
+    cookies.add(
+        Cookie::build(CONSENT_COOKIE, digest)
+            .path("/oauth/authorize")
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .finish(),
+    );
+
+    views::Page(views::consent_page(client, req, &token, error))
+}
+
+#[get("/oauth/authorize?<req>")]
+pub fn get(
+    req: AuthorizationRequest,
+    mut cookies: Cookies,
+) -> Result<views::Page, AuthorizationErrorResponse> {
+    trace!("Entering the authorize handler.");
+    debug!("authorization request: {:?}", &req);
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let client = utils::authorize::check_authorization_request(store, &req)?;
+    Ok(consent_page(&mut cookies, &client, &req, None))
+}
+
+#[post("/oauth/authorize", data = "<req>")]
+pub fn post(
+    req: Option<Form<AuthorizationConsentRequest>>,
+    mut cookies: Cookies,
+) -> Result<AuthorizationCodeResponse, AuthorizationErrorResponse> {
+    trace!("Entering the authorize consent handler.");
+    let consent = req.map(|v| v.into_inner())
+        .ok_or(AuthorizationErrorResponse::Invalid(
+            OAuth2ErrorResponse::InvalidRequest,
+        ))?;
+    debug!("consent request: {:?}", &consent);
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    // The consent form round trips the original request, so it has to be
+    // validated all over again.
+    let request = consent.authorization_request();
+    let client = utils::authorize::check_authorization_request(store, &request)?;
+
+    // Only the page rendered for this very request, in this browser, may post
+    // the form
+    let digest = cookies
+        .get(CONSENT_COOKIE)
+        .map(|c| c.value().to_owned());
+    if !utils::authorize::check_consent_token(
+        &request,
+        consent.csrf_token.as_ref().map(|v| v.as_str()),
+        digest.as_ref().map(|v| v.as_str()),
+    ) {
+        warn!(
+            "Rejected a consent form for client [{}] without a matching token",
+            client.identifier
+        );
+        return Err(AuthorizationErrorResponse::Invalid(
+            OAuth2ErrorResponse::InvalidRequest,
+        ));
+    }
+    cookies.remove(Cookie::build(CONSENT_COOKIE, "").path("/oauth/authorize").finish());
+
+    // Denying takes signing in as well, so nobody can deny requests on
+    // somebody else's behalf
+    let user = match (consent.username.as_ref(), consent.password.as_ref()) {
+        (Some(username), Some(password)) => {
+            utils::check_user_credentials(store, username, password).ok()
+        }
+        _ => None,
+    };
+    let user = match user {
+        Some(user) => user,
+        None => {
+            info!(
+                "Failed to authenticate user [{:?}] for client [{}]",
+                consent.username, client.identifier
+            );
+            return Err(AuthorizationErrorResponse::SignInFailed(consent_page(
+                &mut cookies,
+                &client,
+                &request,
+                Some("The username or password is incorrect."),
+            )));
+        }
+    };
+
+    let result = utils::authorize::authorization_code(
+        store,
+        &client,
+        request,
+        &user,
+        consent.decision == "allow",
+    );
+    trace!("authorize endpoint response: {:?}", result);
+    result
+}
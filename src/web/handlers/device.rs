@@ -0,0 +1,134 @@
+use STORE;
+use models::db::{Client, DeviceCode};
+use models::requests::device::{DeviceAuthorizationRequest, DeviceConsentRequest,
+                               DeviceVerificationRequest};
+use models::responses::device_authorization::DeviceAuthorizationResponse;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::request::Form;
+use rocket::response::status::BadRequest;
+use store::Store;
+use utils;
+use utils::client_auth;
+use utils::device;
+use web::headers::authorization_token::AuthorizationToken;
+use web::headers::client_certificate::ClientCertificate;
+use web::views;
+
+#[post("/oauth/device_authorization", data = "<req>")]
+pub fn authorization(
+    req: Option<Form<DeviceAuthorizationRequest>>,
+    auth: Option<AuthorizationToken>,
+    certificate: Option<ClientCertificate>,
+) -> Result<DeviceAuthorizationResponse, OAuth2ErrorResponse> {
+    trace!("Entering the device authorization handler.");
+
+    debug!("device authorization request: {:?}", &req);
+    let request = req.map(|v| v.into_inner())
+        .ok_or(OAuth2ErrorResponse::InvalidRequest)?;
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    // Devices authenticate the same way they would at the token endpoint
+    let credentials = client_auth::credentials(
+        auth,
+        request.client_id.as_ref().map(|v| v.as_str()),
+        request.client_secret.as_ref().map(|v| v.as_str()),
+        request.client_assertion_type.as_ref().map(|v| v.as_str()),
+        request.client_assertion.as_ref().map(|v| v.as_str()),
+    )?.ok_or(OAuth2ErrorResponse::InvalidClient)?;
+    let client = client_auth::authenticate(store, &credentials, certificate.as_ref())?;
+
+    let result = device::device_authorization(store, &client, &request);
+    trace!("device authorization endpoint response: {:?}", result);
+    result
+}
+
+/// Looks up a pending device code, along with the client it was issued to.
+fn find_pending(store: &dyn Store, user_code: &str) -> Option<(DeviceCode, Client)> {
+    let code = device::find_pending_device_code(store, user_code).ok()?;
+    let client = device::device_code_client(store, &code).ok()?;
+    Some((code, client))
+}
+
+#[get("/oauth/device?<req>")]
+pub fn get(req: DeviceVerificationRequest) -> views::Page {
+    trace!("Entering the device verification handler.");
+    debug!("device verification request: {:?}", &req);
+
+    let user_code = match req.user_code {
+        Some(user_code) => user_code,
+        None => return get_empty(),
+    };
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    // Unknown codes are only reported once the form is posted
+    let pending = find_pending(store, &user_code);
+    views::Page(views::device_page(
+        &user_code,
+        pending.as_ref().map(|&(ref code, ref client)| (code, client)),
+        None,
+    ))
+}
+
+#[get("/oauth/device", rank = 2)]
+pub fn get_empty() -> views::Page {
+    views::Page(views::device_page("", None, None))
+}
+
+fn rejected(
+    user_code: &str,
+    pending: Option<(&DeviceCode, &Client)>,
+    message: &str,
+) -> BadRequest<views::Page> {
+    BadRequest(Some(views::Page(views::device_page(
+        user_code,
+        pending,
+        Some(message),
+    ))))
+}
+
+#[post("/oauth/device", data = "<req>")]
+pub fn post(
+    req: Option<Form<DeviceConsentRequest>>,
+) -> Result<views::Page, BadRequest<views::Page>> {
+    trace!("Entering the device consent handler.");
+    let consent = req.map(|v| v.into_inner())
+        .ok_or_else(|| rejected("", None, "The request is missing a parameter."))?;
+    debug!("device consent request: {:?}", &consent);
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let user_code = consent.user_code.clone().unwrap_or_default();
+    let (code, client) = find_pending(store, &user_code)
+        .ok_or_else(|| rejected(&user_code, None, "The code is invalid or has expired."))?;
+
+    // Denying a device takes signing in as well, so nobody can deny codes on
+    // somebody else's behalf
+    let user = match (consent.username.as_ref(), consent.password.as_ref()) {
+        (Some(username), Some(password)) => {
+            utils::check_user_credentials(store, username, password).ok()
+        }
+        _ => None,
+    };
+    let user = user.ok_or_else(|| {
+        info!(
+            "Failed to authenticate user [{:?}] for device code [{}]",
+            consent.username, code.user_code
+        );
+        rejected(
+            &user_code,
+            Some((&code, &client)),
+            "The username or password is incorrect.",
+        )
+    })?;
+
+    let approved = consent.decision == "allow";
+    device::decide_device_code(store, &code, &user, approved)
+        .map_err(|_| rejected(&user_code, None, "The code is invalid or has expired."))?;
+
+    Ok(views::Page(views::device_done_page(approved)))
+}
@@ -0,0 +1,92 @@
+use STORE;
+use SETTINGS;
+use models::responses::discovery::{AuthorizationServerMetadataResponse,
+                                   AuthorizationServerMetadataResponseBuilder};
+use rocket::State;
+use utils;
+use utils::client_auth;
+use utils::grant;
+use web::MountedRoutes;
+
+/// Builds the absolute URL of an endpoint, if it is mounted.
+fn endpoint(routes: &MountedRoutes, path: &str) -> Option<String> {
+    if routes.contains(path) {
+        Some(format!("{}{}", SETTINGS.issuer().trim_right_matches('/'), path))
+    } else {
+        None
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| (*v).to_owned()).collect()
+}
+
+#[get("/.well-known/oauth-authorization-server")]
+pub fn get(routes: State<MountedRoutes>) -> AuthorizationServerMetadataResponse {
+    trace!("Entering the discovery handler.");
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    // Only advertise grants that are both enabled in the database and
+    // actually handled by the token endpoint. The metadata describes the
+    // server as a whole (RFC 8414), so these are not narrowed down to what any
+    // one client registered: clients read back their own grant types from
+    // the registration endpoint.
+    let grant_types: Vec<String> = utils::get_grant_types(store)
+        .into_iter()
+        .map(|g| g.name)
+        .filter(|name| grant::is_registered(name))
+        .collect();
+
+    let authorization_endpoint = endpoint(&routes, "/oauth/authorize");
+    let response_types = if authorization_endpoint.is_some() {
+        strings(&["code"])
+    } else {
+        Vec::new()
+    };
+    // Certificates can only be presented when mutual TLS is configured.
+    // Introspection needs credentials, so `none` is accepted at the token and
+    // revocation endpoints only.
+    let mtls_enabled = SETTINGS.oauth.mtls.is_some();
+    let token_auth_methods: Vec<String> = client_auth::SUPPORTED_AUTH_METHODS
+        .iter()
+        .filter(|m| mtls_enabled || !client_auth::TLS_AUTH_METHODS.contains(m))
+        .map(|m| (*m).to_owned())
+        .collect();
+    let introspection_auth_methods: Vec<String> = token_auth_methods
+        .iter()
+        .filter(|m| *m != "none")
+        .cloned()
+        .collect();
+    let signing_algs = strings(client_auth::ASSERTION_SIGNING_ALGS);
+
+    let mut builder = AuthorizationServerMetadataResponseBuilder::default();
+    builder
+        .issuer(SETTINGS.issuer().to_owned())
+        .token_endpoint(endpoint(&routes, "/oauth/token"))
+        .introspection_endpoint(endpoint(&routes, "/oauth/introspect"))
+        .revocation_endpoint(endpoint(&routes, "/oauth/revoke"))
+        .jwks_uri(endpoint(&routes, "/.well-known/jwks.json"))
+        .registration_endpoint(endpoint(&routes, "/oauth/register"))
+        .device_authorization_endpoint(endpoint(&routes, "/oauth/device_authorization"))
+        .scopes_supported(SETTINGS.oauth.scopes_supported.clone())
+        .response_types_supported(response_types)
+        .grant_types_supported(grant_types)
+        .token_endpoint_auth_methods_supported(token_auth_methods.clone())
+        .token_endpoint_auth_signing_alg_values_supported(Some(signing_algs.clone()))
+        .introspection_endpoint_auth_methods_supported(Some(introspection_auth_methods))
+        .introspection_endpoint_auth_signing_alg_values_supported(Some(signing_algs.clone()))
+        .revocation_endpoint_auth_methods_supported(Some(token_auth_methods))
+        .revocation_endpoint_auth_signing_alg_values_supported(Some(signing_algs))
+        .tls_client_certificate_bound_access_tokens(if mtls_enabled { Some(true) } else { None });
+
+    if authorization_endpoint.is_some() {
+        builder.code_challenge_methods_supported(Some(strings(&["plain", "S256"])));
+    } else {
+        builder.code_challenge_methods_supported(None);
+    }
+    builder.authorization_endpoint(authorization_endpoint);
+
+    builder.build().unwrap() // TODO: remove unwrap
+}
@@ -1,47 +1,61 @@
-use DB_POOL;
+use STORE;
 use chrono::offset::Utc;
-use diesel::prelude::*;
-use models::db::*;
 use models::requests::introspect::IntrospectionRequest;
 use models::responses::introspection_err::IntrospectionErrResponse;
 use models::responses::introspection_ok::{IntrospectionOkResponse, IntrospectionOkResponseBuilder};
-use persistence::*;
 use rocket::request::Form;
 use utils;
-use uuid::Uuid;
+use utils::client_auth;
 use web::headers::authorization_token::AuthorizationToken;
+use web::headers::client_certificate::ClientCertificate;
 
 #[post("/oauth/introspect", data = "<req>")]
 pub fn post(
     req: Option<Form<IntrospectionRequest>>,
     auth: Option<AuthorizationToken>,
+    certificate: Option<ClientCertificate>,
 ) -> Result<IntrospectionOkResponse, IntrospectionErrResponse> {
     debug!("Checking validitity of a supposed auth token.");
-    let auth_token = auth.ok_or(utils::introspection_error())?;
 
     trace!("Introspect endpoint request: {:?}", req);
     let request = req.map(|v| v.into_inner())
         .ok_or(utils::introspection_error())?;
 
-    trace!("Attempting to get DB connection.");
-    let conn = &*DB_POOL.get().unwrap(); // TODO: remove unwrap
-    trace!("DB connection successfully established.");
+    trace!("Attempting to get a store.");
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Store successfully obtained.");
 
-    trace!("authenticating client credentials: {:?}", &auth_token);
-    let client = utils::check_client_credentials(&conn, &auth_token.user, &auth_token.pass)
+    // Only clients holding credentials may introspect
+    let credentials = client_auth::credentials(
+        auth,
+        request.client_id.as_ref().map(|v| v.as_str()),
+        request.client_secret.as_ref().map(|v| v.as_str()),
+        request.client_assertion_type.as_ref().map(|v| v.as_str()),
+        request.client_assertion.as_ref().map(|v| v.as_str()),
+    ).ok()
+        .and_then(|c| c)
+        .ok_or(utils::introspection_error())?;
+
+    trace!("authenticating client credentials: {:?}", &credentials);
+    let client = client_auth::authenticate(store, &credentials, certificate.as_ref())
         .map_err(|_| utils::introspection_error())?;
+    if client.token_endpoint_auth_method == "none" {
+        return Err(utils::introspection_error());
+    }
 
-    // Tokens are always UUIDs
+    // Tokens are either UUIDs, or JWTs wrapping one
     // No token  -->  not active
     trace!("Parsing token into UUID: {:?}", &request.token);
-    let token_as_uuid = Uuid::parse_str(&request.token).map_err(|_| utils::introspection_error())?;
+    let token_as_uuid =
+        utils::parse_access_token(&request.token).ok_or(utils::introspection_error())?;
 
-    let opt_token: QueryResult<AccessToken> = access_tokens::table
-        .filter(access_tokens::token.eq(token_as_uuid))
-        .first(conn);
+    let opt_token = store.find_access_token(&token_as_uuid);
 
-    trace!("Access Token from DB: {:?}", opt_token);
-    let access_token = opt_token.map_err(|_| utils::introspection_error())?;
+    trace!("Access Token from store: {:?}", opt_token);
+    let access_token = opt_token
+        .ok()
+        .and_then(|t| t)
+        .ok_or(utils::introspection_error())?;
 
     // Make sure the authenticated client owns this token
     if client.id != access_token.client_id {
@@ -49,6 +63,12 @@ pub fn post(
         return Err(utils::introspection_error());
     }
 
+    // Revoked  -->  not active
+    if access_token.revoked_at.is_some() {
+        debug!("Token has been revoked.");
+        return Err(utils::introspection_error());
+    }
+
     // expires_at <= Now  -->  not active
     if access_token
         .expires_at
@@ -60,12 +80,27 @@ pub fn post(
     }
 
     // That means that for our current implementation, the token itself is valid.
+    let cnf = utils::confirmation(&access_token);
+    let aud = utils::token_audience(&access_token);
+    let act = utils::actor(&access_token);
+    let username = access_token.user_id.and_then(|id| {
+        store
+            .user_store()
+            .find_user_by_id(id)
+            .ok()
+            .and_then(|u| u)
+            .map(|u| u.username)
+    });
     let response = IntrospectionOkResponseBuilder::default()
         .active(true)
         .scope(Some(access_token.scope))
         .client_id(Some(client.identifier))
+        .username(username)
         .exp(Some(access_token.expires_at.timestamp()))
         .iat(Some(access_token.issued_at.timestamp()))
+        .cnf(cnf)
+        .aud(aud)
+        .act(act)
         .build()
         .unwrap(); // TODO: remove unwrap
     debug!("Token is valid: {:?}", response);
@@ -0,0 +1,22 @@
+use keystore;
+use models::responses::jwks::JwkSetResponse;
+use utils::jwk::{Jwk, JwkSet};
+
+#[get("/.well-known/jwks.json")]
+pub fn get() -> JwkSetResponse {
+    trace!("Entering the JWKS handler.");
+
+    let keys = keystore::read()
+        .published()
+        .into_iter()
+        .filter_map(|key| {
+            let jwk = key.verifying_key().and_then(|k| Jwk::from_verifying_key(&k));
+            if let Err(ref e) = jwk {
+                error!("Unable to publish key [{:?}]: {}", key.kid, e);
+            }
+            jwk.ok()
+        })
+        .collect();
+
+    JwkSetResponse(JwkSet { keys })
+}
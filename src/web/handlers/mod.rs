@@ -1,2 +1,8 @@
+pub mod authorize;
+pub mod device;
+pub mod discovery;
 pub mod introspect;
+pub mod jwks;
+pub mod register;
+pub mod revoke;
 pub mod token;
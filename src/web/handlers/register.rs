@@ -0,0 +1,88 @@
+use STORE;
+use models::requests::register::ClientRegistrationRequest;
+use models::responses::client_information::ClientInformationResponse;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::response::status::{Created, NoContent};
+use rocket_contrib::Json;
+use utils;
+use web::headers::bearer_token::BearerToken;
+
+fn token(auth: &Option<BearerToken>) -> Option<&str> {
+    auth.as_ref().map(|t| t.0.as_str())
+}
+
+#[post("/oauth/register", data = "<req>")]
+pub fn post(
+    req: Option<Json<ClientRegistrationRequest>>,
+    auth: Option<BearerToken>,
+) -> Result<Created<ClientInformationResponse>, OAuth2ErrorResponse> {
+    trace!("Entering the registration handler.");
+
+    utils::registration::check_initial_access_token(token(&auth))?;
+
+    debug!("registration request: {:?}", &req);
+    let request = req.map(|v| v.into_inner())
+        .ok_or(OAuth2ErrorResponse::InvalidClientMetadata)?;
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let result = utils::registration::register(store, request);
+    trace!("registration endpoint response: {:?}", result);
+    result.map(|r| Created(r.registration_client_uri.clone(), Some(r)))
+}
+
+#[get("/oauth/register/<client_id>")]
+pub fn get(
+    client_id: String,
+    auth: Option<BearerToken>,
+) -> Result<ClientInformationResponse, OAuth2ErrorResponse> {
+    trace!("Entering the client configuration read handler.");
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let client =
+        utils::registration::check_registration_access_token(store, &client_id, token(&auth))?;
+    Ok(utils::registration::read(store, &client))
+}
+
+#[put("/oauth/register/<client_id>", data = "<req>")]
+pub fn put(
+    client_id: String,
+    req: Option<Json<ClientRegistrationRequest>>,
+    auth: Option<BearerToken>,
+) -> Result<ClientInformationResponse, OAuth2ErrorResponse> {
+    trace!("Entering the client configuration update handler.");
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let client =
+        utils::registration::check_registration_access_token(store, &client_id, token(&auth))?;
+
+    debug!("client update request: {:?}", &req);
+    let request = req.map(|v| v.into_inner())
+        .ok_or(OAuth2ErrorResponse::InvalidClientMetadata)?;
+
+    let result = utils::registration::update(store, client, request);
+    trace!("client configuration update response: {:?}", result);
+    result
+}
+
+#[delete("/oauth/register/<client_id>")]
+pub fn delete(
+    client_id: String,
+    auth: Option<BearerToken>,
+) -> Result<NoContent, OAuth2ErrorResponse> {
+    trace!("Entering the client configuration delete handler.");
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    let client =
+        utils::registration::check_registration_access_token(store, &client_id, token(&auth))?;
+    utils::registration::delete(store, &client);
+
+    Ok(NoContent)
+}
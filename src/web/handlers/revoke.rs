@@ -0,0 +1,66 @@
+use STORE;
+use models::requests::revoke::RevocationRequest;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::request::Form;
+use utils;
+use utils::client_auth;
+use web::headers::authorization_token::AuthorizationToken;
+use web::headers::client_certificate::ClientCertificate;
+
+#[post("/oauth/revoke", data = "<req>")]
+pub fn post(
+    req: Option<Form<RevocationRequest>>,
+    auth: Option<AuthorizationToken>,
+    certificate: Option<ClientCertificate>,
+) -> Result<(), OAuth2ErrorResponse> {
+    trace!("Entering the revocation handler.");
+
+    debug!("revocation request: {:?}", &req);
+    let request = req.map(|v| v.into_inner())
+        .ok_or(OAuth2ErrorResponse::InvalidRequest)?;
+
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
+
+    // Confidential clients have to authenticate, while public clients only
+    // identify themselves with their client_id, as they would at the token
+    // endpoint. Either way, clients can only revoke their own tokens. See RFC
+    // 7009 section 2.1.
+    let credentials = client_auth::credentials(
+        auth,
+        request.client_id.as_ref().map(|v| v.as_str()),
+        request.client_secret.as_ref().map(|v| v.as_str()),
+        request.client_assertion_type.as_ref().map(|v| v.as_str()),
+        request.client_assertion.as_ref().map(|v| v.as_str()),
+    )?.ok_or(OAuth2ErrorResponse::InvalidClient)?;
+    let client = client_auth::authenticate(store, &credentials, certificate.as_ref())?;
+
+    // Invalid tokens, and tokens belonging to other clients, are reported as
+    // successfully revoked. See RFC 7009 section 2.2.
+    let token = match utils::parse_access_token(&request.token) {
+        Some(token) => token,
+        None => return Ok(()),
+    };
+
+    // The hint only decides which kind of token we look for first. Unknown
+    // hints are ignored rather than rejected.
+    let revoked = match request.token_type_hint.as_ref().map(|v| v.as_str()) {
+        Some("refresh_token") => {
+            utils::revoke_refresh_token(store, &client, &token)
+                || utils::revoke_access_token(store, &client, &token)
+        }
+        _ => {
+            utils::revoke_access_token(store, &client, &token)
+                || utils::revoke_refresh_token(store, &client, &token)
+        }
+    };
+
+    if revoked {
+        info!(
+            "Client [{}] revoked token [{}]",
+            client.identifier, request.token
+        );
+    }
+
+    Ok(())
+}
@@ -1,37 +1,61 @@
-use DB_POOL;
+use STORE;
 use models::requests::access_token::AccessTokenRequest;
 use models::responses::access_token::AccessTokenResponse;
 use models::responses::oauth2_error::OAuth2ErrorResponse;
 use rocket::request::Form;
 use utils;
+use utils::client_auth;
+use utils::grant;
 use web::headers::authorization_token::AuthorizationToken;
+use web::headers::client_certificate::ClientCertificate;
 
 #[post("/oauth/token", data = "<req>")]
 pub fn post(
     req: Option<Form<AccessTokenRequest>>,
     auth: Option<AuthorizationToken>,
+    certificate: Option<ClientCertificate>,
 ) -> Result<AccessTokenResponse, OAuth2ErrorResponse> {
     trace!("Entering the token handler.");
     debug!("Auth token from request: {:?}", &auth);
-    let auth_token = auth.ok_or(OAuth2ErrorResponse::InvalidClient)?;
 
     trace!("Extracting access token");
     debug!("token request: {:?}", &req);
     let request = req.map(|v| v.into_inner())
         .ok_or(OAuth2ErrorResponse::InvalidRequest)?;
 
-    let conn = &*DB_POOL.get().unwrap(); // TODO: remove unwrap
-    trace!("Successfully grabbed connection from the database connection pool.");
+    let store = &*STORE.get().unwrap(); // TODO: remove unwrap
+    trace!("Successfully grabbed a store from the store provider.");
 
-    let grant_type = request
+    let handler = request
         .grant_type
-        .clone()
+        .as_ref()
+        .and_then(|g| grant::find(g))
         .ok_or(OAuth2ErrorResponse::UnsupportedGrantType)?;
 
-    let result = match grant_type.as_str() {
-        "client_credentials" => utils::token::client_credentials(conn, request, auth_token.clone()),
-        "refresh_token" => utils::token::refresh_token(conn, request, auth_token.clone()),
-        _ => Err(OAuth2ErrorResponse::UnsupportedGrantType),
+    // Clients authenticate the same way for every grant, either in the
+    // Authorization header, in the request body, with a signed assertion, or
+    // with a certificate. Access tokens are bound to any certificate
+    // presented, whichever way the client authenticated.
+    let credentials = client_auth::credentials(
+        auth,
+        request.client_id.as_ref().map(|v| v.as_str()),
+        request.client_secret.as_ref().map(|v| v.as_str()),
+        request.client_assertion_type.as_ref().map(|v| v.as_str()),
+        request.client_assertion.as_ref().map(|v| v.as_str()),
+    )?;
+    let certificate = certificate.as_ref();
+
+    // Requests without credentials are left to grants that identify the
+    // client on their own, which then check the client's grant types
+    // themselves. Every other client only gets to use the grant types it
+    // registered, whichever handler processes them.
+    let result = match credentials {
+        Some(credentials) => {
+            let client = client_auth::authenticate(store, &credentials, certificate)?;
+            let grant_type = utils::check_grant_type(store, &client, handler.grant_type())?;
+            handler.handle(store, request, client, grant_type, certificate)
+        }
+        None => handler.handle_unauthenticated(store, request, certificate),
     };
     trace!("auth token endpoint response: {:?}", result);
     result
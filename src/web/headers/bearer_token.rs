@@ -0,0 +1,35 @@
+use rocket::Outcome::{self, Failure, Success};
+use rocket::Request;
+use rocket::http::Status;
+use rocket::request::FromRequest;
+use std::fmt;
+
+/// A bearer token sent in the Authorization header, as described in RFC 6750
+/// section 2.1.
+#[derive(Clone)]
+pub struct BearerToken(pub String);
+
+impl fmt::Debug for BearerToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BearerToken([REDACTED])")
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for BearerToken {
+    type Error = ();
+
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, (Status, ()), ()> {
+        let header = match req.headers().get_one("Authorization") {
+            Some(v) => v,
+            None => return Failure((Status::Unauthorized, ())),
+        };
+
+        let mut components = header.splitn(2, ' ');
+        match (components.next(), components.next()) {
+            (Some("Bearer"), Some(token)) if !token.is_empty() => {
+                Success(BearerToken(token.to_owned()))
+            }
+            _ => Failure((Status::Unauthorized, ())),
+        }
+    }
+}
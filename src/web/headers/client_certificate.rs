@@ -0,0 +1,221 @@
+use SETTINGS;
+use base64;
+use openssl::hash::MessageDigest;
+use openssl::x509::X509;
+use rocket::Outcome::{self, Failure, Forward, Success};
+use rocket::Request;
+use rocket::http::Status;
+use rocket::request::FromRequest;
+use std::fmt;
+use url::percent_encoding::percent_decode;
+
+/// A client certificate presented over mutual TLS, as described in RFC 8705.
+/// TLS is terminated by a proxy, which forwards the certificate it verified in
+/// the header named by `[oauth.mtls] client_cert_header`.
+pub struct ClientCertificate(pub X509);
+
+impl fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClientCertificate {{ subject: {} }}", self.subject_dn())
+    }
+}
+
+/// Escapes the characters RFC 4514 section 2.4 reserves in attribute values.
+fn escape_dn_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        let reserved = match c {
+            ',' | '+' | '"' | '\\' | '<' | '>' | ';' => true,
+            ' ' | '#' if i == 0 => true,
+            ' ' if i == value.chars().count() - 1 => true,
+            _ => false,
+        };
+        if reserved {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+impl ClientCertificate {
+    /// Parses a URL encoded PEM certificate, the way proxies such as nginx
+    /// (`$ssl_client_escaped_cert`) forward it.
+    pub fn from_forwarded(value: &str) -> Option<ClientCertificate> {
+        let pem = percent_decode(value.as_bytes()).collect::<Vec<u8>>();
+        X509::from_pem(&pem).ok().map(ClientCertificate)
+    }
+
+    pub fn to_der(&self) -> Vec<u8> {
+        self.0.to_der().unwrap() // TODO: remove unwrap
+    }
+
+    /// The base64url encoded SHA-256 hash of the DER certificate, which tokens
+    /// are bound to as `x5t#S256`.
+    ///
+    /// See: https://tools.ietf.org/html/rfc8705#section-3.1
+    pub fn thumbprint(&self) -> String {
+        let digest = self.0.digest(MessageDigest::sha256()).unwrap(); // TODO: remove unwrap
+        base64::encode_config(&digest[..], base64::URL_SAFE_NO_PAD)
+    }
+
+    /// The subject distinguished name, as an RFC 4514 string such as
+    /// `CN=client,O=Example,C=US`.
+    pub fn subject_dn(&self) -> String {
+        // RFC 4514 lists the most specific RDN first, the reverse of the
+        // order they are encoded in
+        let mut rdns = self.0
+            .subject_name()
+            .entries()
+            .map(|entry| {
+                let name = entry.object().nid().short_name().unwrap_or("UNKNOWN");
+                let value = entry
+                    .data()
+                    .as_utf8()
+                    .map(|v| escape_dn_value(&v))
+                    .unwrap_or_default();
+                format!("{}={}", name, value)
+            })
+            .collect::<Vec<_>>();
+        rdns.reverse();
+
+        rdns.join(",")
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientCertificate {
+    type Error = ();
+
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, (Status, ()), ()> {
+        let header = match SETTINGS.oauth.mtls {
+            Some(ref settings) => &settings.client_cert_header,
+            None => return Forward(()),
+        };
+        let value = match req.headers().get_one(header) {
+            Some(v) if !v.is_empty() => v,
+            _ => return Forward(()),
+        };
+
+        match ClientCertificate::from_forwarded(value) {
+            Some(certificate) => Success(certificate),
+            None => {
+                warn!("Unable to parse the forwarded client certificate.");
+                Failure((Status::BadRequest, ()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::sha::sha256;
+    use openssl::x509::X509NameBuilder;
+
+    /// Generates a self-signed certificate with the given subject, the way
+    /// extras/gen-test-certs.sh does, but in memory.
+    pub fn self_signed(subject: &[(&str, &str)]) -> X509 {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        for &(field, value) in subject {
+            name.append_entry_by_text(field, value).unwrap();
+        }
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /// Percent-encodes every byte, as a proxy forwarding the PEM would.
+    fn forwarded(certificate: &X509) -> String {
+        certificate
+            .to_pem()
+            .unwrap()
+            .iter()
+            .map(|b| format!("%{:02X}", b))
+            .collect()
+    }
+
+    #[test]
+    fn subject_dn_lists_the_most_specific_rdn_first() {
+        let certificate =
+            ClientCertificate(self_signed(&[("C", "US"), ("O", "Example"), ("CN", "client")]));
+
+        assert_eq!(certificate.subject_dn(), "CN=client,O=Example,C=US");
+    }
+
+    #[test]
+    fn subject_dn_escapes_reserved_characters() {
+        let certificate = ClientCertificate(self_signed(&[
+            ("O", "Acme, Inc."),
+            ("OU", "#ops "),
+            ("CN", "a+b"),
+        ]));
+
+        assert_eq!(
+            certificate.subject_dn(),
+            "CN=a\\+b,OU=\\#ops\\ ,O=Acme\\, Inc."
+        );
+    }
+
+    #[test]
+    fn thumbprint_is_the_base64url_sha256_of_the_der_certificate() {
+        let certificate = ClientCertificate(self_signed(&[("CN", "client")]));
+        let expected = base64::encode_config(
+            &sha256(&certificate.to_der())[..],
+            base64::URL_SAFE_NO_PAD,
+        );
+
+        assert_eq!(certificate.thumbprint(), expected);
+        assert_eq!(certificate.thumbprint().len(), 43);
+    }
+
+    #[test]
+    fn thumbprints_differ_between_certificates() {
+        let first = ClientCertificate(self_signed(&[("CN", "client")]));
+        let second = ClientCertificate(self_signed(&[("CN", "client")]));
+
+        assert_ne!(first.thumbprint(), second.thumbprint());
+    }
+
+    #[test]
+    fn from_forwarded_parses_url_encoded_pem() {
+        let certificate = self_signed(&[("CN", "client")]);
+        let parsed = ClientCertificate::from_forwarded(&forwarded(&certificate)).unwrap();
+
+        assert_eq!(parsed.to_der(), certificate.to_der().unwrap());
+    }
+
+    #[test]
+    fn from_forwarded_accepts_plain_pem() {
+        let certificate = self_signed(&[("CN", "client")]);
+        let pem = String::from_utf8(certificate.to_pem().unwrap()).unwrap();
+        let parsed = ClientCertificate::from_forwarded(&pem).unwrap();
+
+        assert_eq!(parsed.to_der(), certificate.to_der().unwrap());
+    }
+
+    #[test]
+    fn from_forwarded_rejects_anything_else() {
+        assert!(ClientCertificate::from_forwarded("").is_none());
+        assert!(ClientCertificate::from_forwarded("not%20a%20certificate").is_none());
+    }
+}
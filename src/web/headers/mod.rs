@@ -1 +1,3 @@
 pub mod authorization_token;
+pub mod bearer_token;
+pub mod client_certificate;
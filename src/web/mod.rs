@@ -1,2 +1,54 @@
 pub mod handlers;
 pub mod headers;
+pub mod views;
+
+use SETTINGS;
+use rocket::Route;
+
+/// Every route served by the provider. The discovery document is built from
+/// this list, so an endpoint is only advertised once it is mounted here.
+pub fn routes() -> Vec<Route> {
+    let mut mounted = routes![
+        handlers::authorize::get,
+        handlers::authorize::post,
+        handlers::token::post,
+        handlers::introspect::post,
+        handlers::revoke::post,
+        handlers::discovery::get,
+        handlers::jwks::get
+    ];
+
+    if SETTINGS.oauth.registration.is_some() {
+        mounted.extend(routes![
+            handlers::register::post,
+            handlers::register::get,
+            handlers::register::put,
+            handlers::register::delete
+        ]);
+    }
+
+    if SETTINGS.oauth.device.is_some() {
+        mounted.extend(routes![
+            handlers::device::authorization,
+            handlers::device::get,
+            handlers::device::get_empty,
+            handlers::device::post
+        ]);
+    }
+
+    mounted
+}
+
+/// The paths of the mounted routes, kept in managed state so handlers can tell
+/// which endpoints are available.
+pub struct MountedRoutes(pub Vec<String>);
+
+impl MountedRoutes {
+    pub fn new(routes: &[Route]) -> MountedRoutes {
+        MountedRoutes(routes.iter().map(|r| r.uri.path().to_owned()).collect())
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.0.iter().any(|p| p == path)
+    }
+}
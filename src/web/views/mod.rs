@@ -0,0 +1,198 @@
+//! Server-side rendered pages shown to resource owners. These are kept
+//! deliberately plain; deployments wanting their own branding can put a proxy
+//! in front, or replace the markup here.
+//!
+//! Pages are sent with headers forbidding other sites from framing them, so
+//! resource owners cannot be tricked into clicking through them.
+
+use models::db::{Client, DeviceCode};
+use models::requests::authorize::AuthorizationRequest;
+use models::responses::oauth2_error::OAuth2ErrorResponse;
+use rocket::Request;
+use rocket::response::{Responder, Response};
+use rocket::response::Result as RocketResult;
+use rocket::response::content::Html;
+
+/// A page shown to resource owners, which may not be framed.
+#[derive(Debug)]
+pub struct Page(pub String);
+
+impl<'r> Responder<'r> for Page {
+    fn respond_to(self, req: &Request) -> RocketResult<'r> {
+        Response::build_from(Html(self.0).respond_to(req)?)
+            .raw_header("X-Frame-Options", "DENY")
+            .raw_header("Content-Security-Policy", "frame-ancestors 'none'")
+            .raw_header("Cache-Control", "no-store")
+            .ok()
+    }
+}
+
+/// Escapes a value for safe inclusion in HTML text and attribute values.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn layout(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+fn hidden_input(name: &str, value: &Option<String>) -> String {
+    match *value {
+        Some(ref v) => format!(
+            "<input type=\"hidden\" name=\"{}\" value=\"{}\">\n",
+            name,
+            escape(v)
+        ),
+        None => String::new(),
+    }
+}
+
+/// How a client is named on pages: by its name, if it registered one, along
+/// with its identifier.
+fn client_label(client: &Client) -> String {
+    match client.client_name {
+        Some(ref name) => format!("{} ({})", name, client.identifier),
+        None => client.identifier.clone(),
+    }
+}
+
+fn error_message(error: Option<&str>) -> String {
+    error
+        .map(|e| format!("<p><strong>{}</strong></p>\n", escape(e)))
+        .unwrap_or_default()
+}
+
+/// Renders the consent page, where the resource owner signs in, and decides
+/// whether the client may be granted the requested scopes. The form carries
+/// the token the page was rendered with.
+pub fn consent_page(
+    client: &Client,
+    req: &AuthorizationRequest,
+    csrf_token: &str,
+    error: Option<&str>,
+) -> String {
+    let scopes = req.scope
+        .as_ref()
+        .map(|s| {
+            s.split(' ')
+                .map(|scope| format!("<li>{}</li>\n", escape(scope)))
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+
+    let mut form = String::new();
+    form.push_str(&hidden_input("response_type", &req.response_type));
+    form.push_str(&hidden_input("client_id", &req.client_id));
+    form.push_str(&hidden_input("redirect_uri", &req.redirect_uri));
+    form.push_str(&hidden_input("scope", &req.scope));
+    form.push_str(&hidden_input("state", &req.state));
+    form.push_str(&hidden_input("code_challenge", &req.code_challenge));
+    form.push_str(&hidden_input("code_challenge_method", &req.code_challenge_method));
+    form.push_str(&hidden_input("csrf_token", &Some(csrf_token.to_owned())));
+
+    let body = format!(
+        "<h1>Authorize {client}</h1>\n\
+         {error}\
+         <p>The application <strong>{client}</strong> is requesting access to:</p>\n\
+         <ul>\n{scopes}</ul>\n\
+         <form method=\"post\" action=\"/oauth/authorize\">\n{form}\
+         <label>Username <input type=\"text\" name=\"username\"></label>\n\
+         <label>Password <input type=\"password\" name=\"password\"></label>\n\
+         <button type=\"submit\" name=\"decision\" value=\"allow\">Allow</button>\n\
+         <button type=\"submit\" name=\"decision\" value=\"deny\">Deny</button>\n\
+         </form>",
+        client = escape(&client_label(client)),
+        error = error_message(error),
+        scopes = scopes,
+        form = form
+    );
+
+    layout("Authorize application", &body)
+}
+
+/// Renders the verification page, where the resource owner enters the code
+/// shown on their device, signs in, and approves or denies the device. When
+/// the code is already known, the client the device runs and the scopes it
+/// requested are shown, so the resource owner knows what they are approving
+/// (RFC 8628 section 5.4).
+pub fn device_page(
+    user_code: &str,
+    pending: Option<(&DeviceCode, &Client)>,
+    error: Option<&str>,
+) -> String {
+    let scopes = pending.map(|(code, client)| {
+        let client = escape(&client_label(client));
+        if code.scope.is_empty() {
+            return format!(
+                "<p>The application <strong>{}</strong> is requesting access to your account.</p>\n",
+                client
+            );
+        }
+
+        let scopes = code.scope
+            .split(' ')
+            .map(|scope| format!("<li>{}</li>\n", escape(scope)))
+            .collect::<String>();
+        format!(
+            "<p>The application <strong>{}</strong> is requesting access to:</p>\n<ul>\n{}</ul>\n",
+            client, scopes
+        )
+    }).unwrap_or_default();
+    let error = error_message(error);
+
+    let body = format!(
+        "<h1>Connect a device</h1>\n\
+         {error}\
+         <p>Enter the code shown on your device, and sign in to approve it.</p>\n\
+         {scopes}\
+         <form method=\"post\" action=\"/oauth/device\">\n\
+         <label>Code <input type=\"text\" name=\"user_code\" value=\"{user_code}\" autocomplete=\"off\"></label>\n\
+         <label>Username <input type=\"text\" name=\"username\"></label>\n\
+         <label>Password <input type=\"password\" name=\"password\"></label>\n\
+         <button type=\"submit\" name=\"decision\" value=\"allow\">Allow</button>\n\
+         <button type=\"submit\" name=\"decision\" value=\"deny\">Deny</button>\n\
+         </form>",
+        error = error,
+        scopes = scopes,
+        user_code = escape(user_code)
+    );
+
+    layout("Connect a device", &body)
+}
+
+/// Renders the page shown once the resource owner decided on a device.
+pub fn device_done_page(approved: bool) -> String {
+    let body = if approved {
+        "<h1>Device connected</h1>\n<p>You can return to your device.</p>"
+    } else {
+        "<h1>Device denied</h1>\n<p>The device was not given access.</p>"
+    };
+
+    layout("Connect a device", body)
+}
+
+/// Renders an error page for authorization requests that cannot be redirected
+/// back to the client.
+pub fn error_page(error: &OAuth2ErrorResponse) -> String {
+    let body = format!(
+        "<h1>Authorization failed</h1>\n<p>The authorization request was rejected: <code>{}</code></p>",
+        escape(error.message())
+    );
+
+    layout("Authorization failed", &body)
+}